@@ -0,0 +1,244 @@
+//! Baseline
+//!
+//! A reference built from several known-good dumps (a directory holding
+//! many device roots) is a baseline: its tables hold every distinct row
+//! of the dumps, and for every row the number of dumps it was seen on.
+//! Comparisons then tell whether an observed value was never, rarely or
+//! commonly seen on the clean units.
+
+use std::{fmt, io::Error, sync::Mutex};
+use sqlite::{ConnectionThreadSafe, State};
+use serde::{Deserialize, Serialize};
+
+use super::reference::VALUES_SEPARATOR;
+
+/// Table of the dumps a reference was built from, one row per device root.
+pub const BASELINE_DEVICES_TABLE: &str = "asc_baseline_devices";
+/// Table of the number of dumps each row of each module was seen on.
+pub const VALUE_FREQUENCY_TABLE: &str = "asc_value_frequency";
+/// Share of the baseline dumps up to which a value is rare.
+pub const DEFAULT_RARE_THRESHOLD: f64 = 0.25;
+
+/// Serialize 'count_row()', called by the parallel workers of the referencing:
+/// the lookup of a row and its insert or update must not interleave.
+static COUNT_ROW_LOCK: Mutex<()> = Mutex::new(());
+
+/// How often a value was seen in the baseline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrequencyClass {
+    /// On none of the baseline dumps.
+    Never,
+    /// On at most the rare threshold of the baseline dumps.
+    Rare,
+    Common,
+}
+
+impl fmt::Display for FrequencyClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrequencyClass::Never => write!(f, "never"),
+            FrequencyClass::Rare => write!(f, "rare"),
+            FrequencyClass::Common => write!(f, "common"),
+        }
+    }
+}
+
+/// Number of baseline dumps a value was seen on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueFrequency {
+    /// Dumps holding the value.
+    pub seen: usize,
+    /// Dumps of the baseline.
+    pub baseline: usize,
+    pub class: FrequencyClass,
+}
+
+impl ValueFrequency {
+    /// Frequency of a value 'seen' on 'baseline' dumps, rare up to 'rare_threshold' of them.
+    pub fn new(seen: usize,
+            baseline: usize,
+            rare_threshold: f64) -> Self
+    {
+        let class = match seen {
+            0 => FrequencyClass::Never,
+            x if (x as f64) <= rare_threshold * baseline as f64 => FrequencyClass::Rare,
+            _ => FrequencyClass::Common,
+        };
+        Self { seen, baseline, class }
+    }
+}
+
+impl fmt::Display for ValueFrequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}/{})", self.class, self.seen, self.baseline)
+    }
+}
+
+/// Whether a row counted by 'count_row()' is new to the reference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RowCount {
+    /// First seen, to be inserted into the module table.
+    New,
+    /// Already seen on a previous dump, only counted.
+    Counted,
+    /// Already seen on this dump, neither counted nor inserted.
+    Duplicate,
+}
+
+fn to_io_error(err: sqlite::Error) -> Error {
+    Error::other(format!("Baseline statistics => {err}"))
+}
+
+/// Create the baseline tables of 'connx', empty.
+pub(crate) fn create_baseline_tables(connx: &ConnectionThreadSafe) -> Result<(), Error> {
+    connx.execute(format!("DROP TABLE IF EXISTS '{BASELINE_DEVICES_TABLE}'")).map_err(to_io_error)?;
+    connx.execute(format!("DROP TABLE IF EXISTS '{VALUE_FREQUENCY_TABLE}'")).map_err(to_io_error)?;
+    connx.execute(format!("CREATE TABLE '{BASELINE_DEVICES_TABLE}' (device TEXT)")).map_err(to_io_error)?;
+    connx.execute(format!("CREATE TABLE '{VALUE_FREQUENCY_TABLE}' (module TEXT, key TEXT, value TEXT, seen INTEGER, last_device INTEGER)")).map_err(to_io_error)?;
+    create_frequency_index(connx)
+}
+
+/// Index 'VALUE_FREQUENCY_TABLE', lost when a saved reference is copied.
+pub(crate) fn create_frequency_index(connx: &ConnectionThreadSafe) -> Result<(), Error> {
+    connx.execute(format!("CREATE UNIQUE INDEX IF NOT EXISTS 'asc_value_frequency_index' ON '{VALUE_FREQUENCY_TABLE}' (module, key, value)"))
+            .map_err(to_io_error)
+}
+
+/// Add the dump 'device' to the baseline, returning its number (from 1).
+pub(crate) fn add_baseline_device(connx: &ConnectionThreadSafe,
+        device: &str) -> Result<i64, Error>
+{
+    let mut statement = connx.prepare(format!("INSERT INTO '{BASELINE_DEVICES_TABLE}' (device) VALUES (:device)"))
+            .map_err(to_io_error)?;
+    statement.bind((":device", device)).map_err(to_io_error)?;
+    while let Ok(State::Row) = statement.next() {}
+    Ok(baseline_devices(connx) as i64)
+}
+
+/// Number of dumps the reference of 'connx' was built from, 0 for a
+/// reference saved before the baselines.
+pub(crate) fn baseline_devices(connx: &ConnectionThreadSafe) -> usize {
+    let mut stmt = match connx.prepare(format!("SELECT COUNT(*) FROM '{BASELINE_DEVICES_TABLE}'")) {
+        Ok(x) => x,
+        Err(_) => return 0,
+    };
+    match stmt.next() {
+        Ok(State::Row) => stmt.read::<i64, _>(0).unwrap_or_default() as usize,
+        _ => 0,
+    }
+}
+
+/// Count the row 'key', 'values' of 'module' for the dump number 'device',
+/// the lookup and the insert or update being done as a whole.
+pub(crate) fn count_row(connx: &ConnectionThreadSafe,
+        module: &str,
+        key: &str,
+        values: &[&str],
+        device: i64) -> Result<RowCount, Error>
+{
+    let value = values.join(VALUES_SEPARATOR);
+    let _count_row_guard = COUNT_ROW_LOCK.lock().unwrap_or_else(|x| x.into_inner());
+    let mut stmt = connx.prepare(format!("SELECT last_device FROM '{VALUE_FREQUENCY_TABLE}' WHERE module=:module AND key=:key AND value=:value"))
+            .map_err(to_io_error)?;
+    stmt.bind(&[(":module", module), (":key", key), (":value", value.as_str())][..]).map_err(to_io_error)?;
+    let last_device: Option<i64> = match stmt.next() {
+        Ok(State::Row) => Some(stmt.read(0).unwrap_or_default()),
+        _ => None,
+    };
+    drop(stmt);
+    let (query, row_count) = match last_device {
+        Some(x) if x == device => return Ok(RowCount::Duplicate),
+        Some(_) => (format!("UPDATE '{VALUE_FREQUENCY_TABLE}' SET seen = seen + 1, last_device = :device WHERE module=:module AND key=:key AND value=:value"),
+                RowCount::Counted
+        ),
+        None => (format!("INSERT INTO '{VALUE_FREQUENCY_TABLE}' (module, key, value, seen, last_device) VALUES (:module, :key, :value, 1, :device)"),
+                RowCount::New
+        ),
+    };
+    let mut statement = connx.prepare(query).map_err(to_io_error)?;
+    statement.bind(&[(":module", module), (":key", key), (":value", value.as_str())][..]).map_err(to_io_error)?;
+    statement.bind((":device", device)).map_err(to_io_error)?;
+    while let Ok(State::Row) = statement.next() {}
+    Ok(row_count)
+}
+
+/// Number of baseline dumps the row 'key', 'values' of 'module' was seen on.
+pub(crate) fn seen_count(connx: &ConnectionThreadSafe,
+        module: &str,
+        key: &str,
+        values: &[String]) -> usize
+{
+    let value = values.join(VALUES_SEPARATOR);
+    let mut stmt = match connx.prepare(format!("SELECT seen FROM '{VALUE_FREQUENCY_TABLE}' WHERE module=:module AND key=:key AND value=:value")) {
+        Ok(x) => x,
+        Err(_) => return 0,
+    };
+    let _ = stmt.bind(&[(":module", module), (":key", key), (":value", value.as_str())][..]);
+    match stmt.next() {
+        Ok(State::Row) => stmt.read::<i64, _>(0).unwrap_or_default() as usize,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn baseline_connx() -> ConnectionThreadSafe {
+        let connx = sqlite::Connection::open_thread_safe(":memory:").unwrap();
+        create_baseline_tables(&connx).unwrap();
+        connx
+    }
+
+    #[test]
+    fn value_frequency_classes() {
+        assert_eq!(ValueFrequency::new(0, 8, DEFAULT_RARE_THRESHOLD).class, FrequencyClass::Never);
+        assert_eq!(ValueFrequency::new(1, 8, DEFAULT_RARE_THRESHOLD).class, FrequencyClass::Rare);
+        assert_eq!(ValueFrequency::new(2, 8, DEFAULT_RARE_THRESHOLD).class, FrequencyClass::Rare);
+        assert_eq!(ValueFrequency::new(3, 8, DEFAULT_RARE_THRESHOLD).class, FrequencyClass::Common);
+        assert_eq!(ValueFrequency::new(1, 1, 0.0).class, FrequencyClass::Common);
+        assert_eq!(ValueFrequency::new(1, 4, 0.25).to_string(), "rare (1/4)");
+    }
+
+    #[test]
+    fn count_row_new_counted_duplicate() {
+        let connx = baseline_connx();
+        let first = add_baseline_device(&connx, "dump_1").unwrap();
+        let second = add_baseline_device(&connx, "dump_2").unwrap();
+        assert_eq!((first, second), (1, 2));
+        assert_eq!(count_row(&connx, "getprop", "ro.debuggable", &["0"], first).unwrap(), RowCount::New);
+        assert_eq!(count_row(&connx, "getprop", "ro.debuggable", &["0"], first).unwrap(), RowCount::Duplicate);
+        assert_eq!(count_row(&connx, "getprop", "ro.debuggable", &["0"], second).unwrap(), RowCount::Counted);
+        assert_eq!(count_row(&connx, "getprop", "ro.debuggable", &["1"], second).unwrap(), RowCount::New);
+        assert_eq!(seen_count(&connx, "getprop", "ro.debuggable", &["0".to_string()]), 2);
+        assert_eq!(seen_count(&connx, "getprop", "ro.debuggable", &["1".to_string()]), 1);
+        assert_eq!(seen_count(&connx, "mount", "ro.debuggable", &["0".to_string()]), 0);
+        assert_eq!(baseline_devices(&connx), 2);
+    }
+
+    #[test]
+    fn count_row_joins_values() {
+        let connx = baseline_connx();
+        let device = add_baseline_device(&connx, "dump_1").unwrap();
+        assert_eq!(count_row(&connx, "mount", "/system", &["ext4", "ro"], device).unwrap(), RowCount::New);
+        assert_eq!(count_row(&connx, "mount", "/system", &["ext4", "rw"], device).unwrap(), RowCount::New);
+        assert_eq!(seen_count(&connx, "mount", "/system", &["ext4".to_string(), "ro".to_string()]), 1);
+        assert_eq!(seen_count(&connx, "mount", "/system", &["ext4ro".to_string()]), 0);
+    }
+
+    #[test]
+    fn count_row_from_parallel_workers() {
+        let connx = std::sync::Arc::new(baseline_connx());
+        let devices: Vec<i64> = (0..4).map(|x| add_baseline_device(&connx, &format!("dump_{x}")).unwrap()).collect();
+        let handles: Vec<_> = devices.into_iter()
+                .map(|device| {
+                    let connx = connx.clone();
+                    std::thread::spawn(move || count_row(&connx, "getprop", "ro.secure", &["1"], device).unwrap())
+                })
+                .collect();
+        let counts: Vec<RowCount> = handles.into_iter().map(|x| x.join().unwrap()).collect();
+        assert_eq!(counts.iter().filter(|x| **x == RowCount::New).count(), 1);
+        assert_eq!(seen_count(&connx, "getprop", "ro.secure", &["1".to_string()]), 4);
+    }
+}
@@ -0,0 +1,392 @@
+//! Builder
+//!
+//! Every option of an AndroidParser, set before it is built:
+//! threads, YARA rules and timeout, output directory, enabled modules,
+//! report formats and CSV delimiter.
+
+use std::{io::{Cursor, Error, ErrorKind}, path::{Path, PathBuf}, sync::Arc, env::current_dir, collections::HashSet};
+use sqlite::ConnectionThreadSafe;
+use yara::Rules;
+
+use super::{AndroidParser, AndroidParserError, DEFAULT_CSV_DELIMITER, DEFAULT_RARE_THRESHOLD, FileReporter, Normalizers, Observer, Parser, ParserRegistry, load_parser_definitions, ReferenceLibrary, ReportFormat, RunStats, SeverityModel, Suppressions, reference, reference_json, report, yara_customizer};
+
+/// Timeout of each YARA scan, in seconds, unless set with 'yara_timeout()'.
+pub const DEFAULT_YARA_TIMEOUT: i32 = 10;
+
+/// Options of an AndroidParser.
+///
+/// # Exemple
+///
+/// ```no_run
+/// use std::path::PathBuf;
+/// use android_sanity_checker::androidparser::{AndroidParserBuilder, ReportFormat};
+///
+/// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+///         .threads(4)
+///         .yara_timeout(30)
+///         .embedded_rules(false)
+///         .yara_rules(String::from("C:/yara"))
+///         .output_dir(String::from("C:/Reports"))
+///         .parsers_file(PathBuf::from("C:/asc/parsers.toml"))
+///         .modules(&["getprop", "mount", "binaries", "lsmod"])
+///         .report_formats(&[ReportFormat::JsonLines])
+///         .build()
+///         .unwrap();
+/// ```
+pub struct AndroidParserBuilder {
+    path_reference: String,
+    path_analyze: String,
+    path_yara: Option<String>,
+    path_output: Option<String>,
+    threads: Option<usize>,
+    yara_timeout: i32,
+    embedded_rules: bool,
+    load_reference: bool,
+    csv_delimiter: char,
+    report_formats: Vec<ReportFormat>,
+    modules: Option<Vec<String>>,
+    parser_registry: ParserRegistry,
+    parsers_files: Vec<PathBuf>,
+    normalizers: Normalizers,
+    severity_model: SeverityModel,
+    suppressions: Suppressions,
+    rare_threshold: f64,
+    observers: Vec<Arc<dyn Observer>>,
+}
+
+impl AndroidParserBuilder {
+    /// Options of an AndroidParser comparing the devices of 'path_analyze'
+    /// against 'path_reference' (see 'AndroidParser::new()').
+    pub fn new(path_reference: String,
+            path_analyze: String) -> Self
+    {
+        Self {
+            path_reference,
+            path_analyze,
+            path_yara: None,
+            path_output: None,
+            threads: None,
+            yara_timeout: DEFAULT_YARA_TIMEOUT,
+            embedded_rules: true,
+            load_reference: true,
+            csv_delimiter: DEFAULT_CSV_DELIMITER,
+            report_formats: vec![ReportFormat::Csv, ReportFormat::JsonLines],
+            modules: None,
+            parser_registry: ParserRegistry::builtin(),
+            parsers_files: vec![],
+            normalizers: Normalizers::builtin(),
+            severity_model: SeverityModel::default(),
+            suppressions: Suppressions::default(),
+            rare_threshold: DEFAULT_RARE_THRESHOLD,
+            observers: vec![],
+        }
+    }
+
+    /// Directory of custom YARA rules, compiled besides the embedded ones.
+    pub fn yara_rules(mut self,
+            path_yara: String) -> Self
+    {
+        self.path_yara = Some(path_yara);
+        self
+    }
+
+    /// Directory where the timestamped folder of the run is created (default: current directory).
+    pub fn output_dir(mut self,
+            path_output: String) -> Self
+    {
+        self.path_output = Some(path_output);
+        self
+    }
+
+    /// Number of worker threads (default: number of CPUs).
+    pub fn threads(mut self,
+            threads: usize) -> Self
+    {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Timeout of each YARA scan, in seconds, greater than 0.
+    pub fn yara_timeout(mut self,
+            seconds: i32) -> Self
+    {
+        self.yara_timeout = seconds;
+        self
+    }
+
+    /// Whether the YARA rules embedded in the tool are loaded (default: true).
+    pub fn embedded_rules(mut self,
+            enabled: bool) -> Self
+    {
+        self.embedded_rules = enabled;
+        self
+    }
+
+    /// Whether 'path_reference' is loaded as a reference (default: true).
+    /// Disabled to diff two dumps with 'diff_devices()', 'path_reference'
+    /// being then the first dump.
+    pub fn load_reference(mut self,
+            enabled: bool) -> Self
+    {
+        self.load_reference = enabled;
+        self
+    }
+
+    /// Delimiter of the CSV reports (default: ';'). A double quote or
+    /// a line break is refused by 'build()'.
+    pub fn csv_delimiter(mut self,
+            delimiter: char) -> Self
+    {
+        self.csv_delimiter = delimiter;
+        self
+    }
+
+    /// Formats of the findings reports (default: CSV and JSON Lines).
+    /// 'reported_devices.csv' and 'reported_errors.csv' are always written.
+    pub fn report_formats(mut self,
+            formats: &[ReportFormat]) -> Self
+    {
+        self.report_formats = formats.to_vec();
+        self
+    }
+
+    /// Only run these modules: parser names (see 'ParserRegistry::names()'),
+    /// and 'yara' for the YARA scan of the files no parser handles.
+    /// Every module is run unless set.
+    pub fn modules(mut self,
+            names: &[&str]) -> Self
+    {
+        self.modules = Some(names.iter().map(|x| x.to_string()).collect());
+        self
+    }
+
+    /// Add 'parser' to the built-in parsers (see 'AndroidParser::register_parser()').
+    pub fn parser<P: Parser + 'static>(mut self,
+            parser: P) -> Self
+    {
+        self.parser_registry.register(parser);
+        self
+    }
+
+    /// Add the parsers declared in the TOML file 'path' (see 'load_parser_definitions()'),
+    /// read when the parser is built so 'modules()' can enable them.
+    pub fn parsers_file(mut self,
+            path: PathBuf) -> Self
+    {
+        self.parsers_files.push(path);
+        self
+    }
+
+    /// Normalizers of the parsed values, applied the same way to the reference
+    /// and to the devices (default: 'Normalizers::builtin()').
+    pub fn normalizers(mut self,
+            normalizers: Normalizers) -> Self
+    {
+        self.normalizers = normalizers;
+        self
+    }
+
+    /// Severity of the findings, and risk score and verdict of the devices
+    /// (default: the built-in rules, see 'SeverityModel::from_file()').
+    pub fn severity_model(mut self,
+            severity_model: SeverityModel) -> Self
+    {
+        self.severity_model = severity_model;
+        self
+    }
+
+    /// Known noise, not reported as findings (see 'Suppressions::from_file()').
+    pub fn suppressions(mut self,
+            suppressions: Suppressions) -> Self
+    {
+        self.suppressions = suppressions;
+        self
+    }
+
+    /// Share of the dumps of a baseline reference up to which a matching value
+    /// is reported as rare (default: 'DEFAULT_RARE_THRESHOLD').
+    pub fn rare_threshold(mut self,
+            threshold: f64) -> Self
+    {
+        self.rare_threshold = threshold;
+        self
+    }
+
+    /// Send every event of the runs to 'observer' (see 'AndroidParser::subscribe()').
+    pub fn observer<O: Observer + 'static>(mut self,
+            observer: O) -> Self
+    {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Check the options, load the reference, compile the YARA rules
+    /// and create the folder of the run with its global reports.
+    pub fn build(mut self) -> Result<AndroidParser, AndroidParserError> {
+        if !(Path::new(&self.path_reference).try_exists()? & Path::new(&self.path_analyze).try_exists()?) {
+            return Err(Error::new(ErrorKind::NotFound,
+                    "File not Found").into()
+            );
+        }
+        report::check_delimiter(self.csv_delimiter)?;
+        if !(0.0..=1.0).contains(&self.rare_threshold) {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                    format!("Invalid rare threshold {}, expected a share between 0 and 1", self.rare_threshold)).into()
+            );
+        }
+        if self.yara_timeout <= 0 {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                    format!("Invalid YARA timeout {}, expected a number of seconds greater than 0", self.yara_timeout)).into()
+            );
+        }
+        for path in self.parsers_files.iter() {
+            load_parser_definitions(path)?.into_iter().for_each(|x| self.parser_registry.register(x));
+        }
+        let enabled_modules: Option<HashSet<String>> = match self.modules {
+            Some(modules) => {
+                let known_modules = self.parser_registry.names();
+                if let Some(unknown) = modules.iter().find(|x| x.as_str() != "yara" && !known_modules.contains(&x.as_str())) {
+                    return Err(Error::new(ErrorKind::InvalidInput,
+                            format!("Unknown module {unknown}, expected one of : yara, {}", known_modules.join(", "))).into()
+                    );
+                }
+                Some(modules.into_iter().collect())
+            },
+            None => None,
+        };
+
+        let mut yara_rules: Vec<Rules> = vec![];
+        if self.embedded_rules {
+            yara_rules.push(Rules::load_from_stream(
+                    Cursor::new(
+                    include_bytes!("../../resources/yara_precompiled.yara")
+            )
+            )?);
+        }
+        if let Some(yara_path) = self.path_yara {
+            yara_rules.push(yara_customizer::yara_rules_ingester(yara_customizer::yara_rules_finder(Path::new(&yara_path))?)?);
+        }
+
+        let connx: Arc<ConnectionThreadSafe> = Arc::new(sqlite::Connection::open_thread_safe(":memory:")?);
+        let mut reference_library: Option<ReferenceLibrary> = None;
+        if self.load_reference {
+            if Path::new(&self.path_reference).is_file() ||
+                    reference_json::is_reference_export(Path::new(&self.path_reference))
+            {
+                reference::load_any_reference(&connx, Path::new(&self.path_reference), &self.parser_registry)?;
+            }
+            else if ReferenceLibrary::is_library(Path::new(&self.path_reference)) {
+                reference_library = Some(ReferenceLibrary::open(Path::new(&self.path_reference))?);
+            }
+        }
+        if let Some(metadata) = reference::read_metadata(&connx) {
+            reference::check_normalizers(&metadata, &self.normalizers, Path::new(&self.path_reference))?;
+        }
+        if let Some(library) = reference_library.as_ref() {
+            for (path, metadata) in library.entries() {
+                reference::check_normalizers(metadata, &self.normalizers, path)?;
+            }
+        }
+
+        let output_dir = match self.path_output {
+            Some(x) => PathBuf::from(x),
+            None => current_dir()?,
+        };
+        let report_dir = report::create_run_dir(&output_dir,
+                &[Path::new(&self.path_reference), Path::new(&self.path_analyze)]
+        )?;
+        let severity_model = Arc::new(self.severity_model);
+        let file_reporter = FileReporter::new(report_dir,
+                self.csv_delimiter,
+                &self.report_formats,
+                severity_model.clone()
+        )?;
+
+        let mut thread_pool_builder = rayon::ThreadPoolBuilder::new();
+        if let Some(threads) = self.threads {
+            thread_pool_builder = thread_pool_builder.num_threads(threads);
+        }
+        let thread_pool = match thread_pool_builder.build() {
+            Ok(x) => x,
+            Err(err) => return Err(Error::other(err.to_string()).into()),
+        };
+
+        Ok(AndroidParser {
+            path_reference: self.path_reference,
+            path_analyze: self.path_analyze,
+            file_reporter: Arc::new(file_reporter),
+            observers: self.observers,
+            run_stats: RunStats::default(),
+            yara_rules,
+            yara_timeout: self.yara_timeout,
+            connx,
+            reference_library,
+            parser_registry: self.parser_registry,
+            normalizers: self.normalizers,
+            severity_model,
+            suppressions: self.suppressions,
+            rare_threshold: self.rare_threshold,
+            enabled_modules,
+            thread_pool,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Options diffing the dump of 'test_dir' with itself, without YARA rules,
+    /// the run folder being created into 'test_dir/reports'.
+    fn dump_builder(test_dir: &Path) -> AndroidParserBuilder {
+        let dump_dir = test_dir.join("dump");
+        std::fs::create_dir_all(&dump_dir).unwrap();
+        AndroidParserBuilder::new(dump_dir.to_string_lossy().to_string(), dump_dir.to_string_lossy().to_string())
+                .embedded_rules(false)
+                .load_reference(false)
+                .output_dir(test_dir.join("reports").to_string_lossy().to_string())
+    }
+
+    fn invalid_input(result: Result<AndroidParser, AndroidParserError>) -> bool {
+        matches!(result, Err(AndroidParserError::Io(err)) if err.kind() == ErrorKind::InvalidInput)
+    }
+
+    #[test]
+    fn build_refuses_invalid_options() {
+        let test_dir = std::env::temp_dir().join(format!("asc_builder_invalid_{}", std::process::id()));
+        assert!(invalid_input(dump_builder(&test_dir).csv_delimiter('"').build()));
+        assert!(invalid_input(dump_builder(&test_dir).csv_delimiter('\n').build()));
+        assert!(invalid_input(dump_builder(&test_dir).rare_threshold(1.5).build()));
+        assert!(invalid_input(dump_builder(&test_dir).rare_threshold(-0.1).build()));
+        assert!(invalid_input(dump_builder(&test_dir).yara_timeout(0).build()));
+        assert!(invalid_input(dump_builder(&test_dir).yara_timeout(-5).build()));
+        assert!(invalid_input(dump_builder(&test_dir).modules(&["getprop", "lsmod"]).build()));
+        assert!(!test_dir.join("reports").exists());
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn build_refuses_missing_paths() {
+        let test_dir = std::env::temp_dir().join(format!("asc_builder_missing_{}", std::process::id()));
+        let result = AndroidParserBuilder::new(test_dir.join("nothing").to_string_lossy().to_string(),
+                test_dir.join("nothing").to_string_lossy().to_string()
+        ).build();
+        assert!(matches!(result, Err(AndroidParserError::Io(err)) if err.kind() == ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn build_accepts_valid_options() {
+        let test_dir = std::env::temp_dir().join(format!("asc_builder_valid_{}", std::process::id()));
+        let android_parser = dump_builder(&test_dir)
+                .csv_delimiter(',')
+                .rare_threshold(0.2)
+                .yara_timeout(1)
+                .threads(2)
+                .modules(&["getprop", "yara"])
+                .build()
+                .unwrap();
+        assert!(android_parser.report_dir().starts_with(std::path::absolute(test_dir.join("reports")).unwrap()));
+        assert!(android_parser.report_dir().is_dir());
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+}
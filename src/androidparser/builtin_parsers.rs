@@ -0,0 +1,710 @@
+//! Built-in parsers
+//!
+//! Parsers of the usual ADB dump files, registered by default.
+
+use std::{io::BufRead, path::Path, sync::LazyLock};
+use regex::Regex;
+
+use super::{AndroidParserError, parser::{dump_name, ParsedEntries, Parser}, records::{BinaryEntry, DiskUsageEntry, DumpRecords, IdEntry, MountEntry, PackageListEntry, PermissionDefinition, ProcessEntry, Property, ServiceEntry, Setting}};
+
+/// 'columns' of the reference 'table' if 'parser' handles its dump files,
+/// the table being named after them.
+fn dump_columns(parser: &dyn Parser,
+        table: &str,
+        columns: &[&str]) -> Option<Vec<String>>
+{
+    parser.matches(Path::new(&format!("{table}.txt")))
+            .then(|| columns.iter().map(|x| x.to_string()).collect())
+}
+
+/// 'getprop.txt' : '[key]: [value]' lines.
+pub struct GetpropParser;
+
+impl Parser for GetpropParser {
+    fn name(&self) -> &str {
+        "getprop"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        dump_name(path).is_some_and(|x| x == "getprop")
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        dump_columns(self, table, &["key", "value"])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Properties(parse_getprop(read_buffer)))
+    }
+}
+
+/// 'settings_*.txt' and 'printenv.txt' : 'key=value' lines.
+pub struct SettingsParser;
+
+impl Parser for SettingsParser {
+    fn name(&self) -> &str {
+        "settings"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        dump_name(path).is_some_and(|x| x.starts_with("settings_") || x == "printenv")
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        dump_columns(self, table, &["key", "value"])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Settings(parse_settings(read_buffer)))
+    }
+}
+
+/// 'df_ah.txt' : filesystem, mount point, size and used space of each line.
+pub struct DfAhParser;
+
+impl Parser for DfAhParser {
+    fn name(&self) -> &str {
+        "df_ah"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        dump_name(path).is_some_and(|x| x == "df_ah")
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        dump_columns(self, table, &["filesystem", "mountpoint", "size", "used"])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::DiskUsage(parse_df_ah(read_buffer)))
+    }
+}
+
+/// 'services.txt' : service name and interface of each line.
+pub struct ServicesParser;
+
+impl Parser for ServicesParser {
+    fn name(&self) -> &str {
+        "services"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        dump_name(path).is_some_and(|x| x == "services")
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        dump_columns(self, table, &["key", "value"])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Services(parse_services(read_buffer)))
+    }
+}
+
+/// 'id.txt' : 'uid=', 'gid=' and 'groups=' with their values.
+pub struct IdParser;
+
+impl Parser for IdParser {
+    fn name(&self) -> &str {
+        "id"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        dump_name(path).is_some_and(|x| x == "id")
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        dump_columns(self, table, &["key", "value"])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Ids(parse_id(read_buffer)))
+    }
+}
+
+/// 'mount.txt' : device, mount point, type and options of each mount.
+pub struct MountParser;
+
+impl Parser for MountParser {
+    fn name(&self) -> &str {
+        "mount"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        dump_name(path).is_some_and(|x| x == "mount")
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        dump_columns(self, table, &["name", "mountpoint", "type", "options"])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Mounts(parse_mount(read_buffer)))
+    }
+}
+
+/// 'ps.txt' : user, pid, parent pid and command of each process.
+pub struct PsParser;
+
+impl Parser for PsParser {
+    fn name(&self) -> &str {
+        "ps"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        dump_name(path).is_some_and(|x| x == "ps")
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        dump_columns(self, table, &["uid", "pid", "ppid", "cmd"])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Processes(parse_ps(read_buffer)))
+    }
+}
+
+/// 'pm_list_*.txt' (but users and permissions) : 'kind:name' lines.
+pub struct PmListParser;
+
+impl Parser for PmListParser {
+    fn name(&self) -> &str {
+        "pm_list"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        dump_name(path).is_some_and(|x| x.starts_with("pm_list_") &&
+                !x.ends_with("users") &&
+                x != "pm_list_permissions-f"
+        )
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        dump_columns(self, table, &["key", "value"])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Packages(parse_list(read_buffer)))
+    }
+}
+
+/// 'pm_list_permissions-f.txt' : permission, package, label, description
+/// and protection level blocks.
+pub struct PermissionsListParser;
+
+impl Parser for PermissionsListParser {
+    fn name(&self) -> &str {
+        "pm_list_permissions"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        dump_name(path).is_some_and(|x| x == "pm_list_permissions-f")
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        dump_columns(self, table, &["permission", "package", "label", "description", "protectionlevel"])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Permissions(parse_permissions_list(read_buffer)))
+    }
+}
+
+/// Path of a file of '/system/bin', compiled once for every file walked through.
+static BINARY_PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\\/]system[\\/]bin[\\/](?P<bin_name>.*)$").unwrap()
+});
+
+/// Files of '/system/bin' : SHA256 sum of the binary.
+pub struct BinaryParser;
+
+impl Parser for BinaryParser {
+    fn name(&self) -> &str {
+        "binaries"
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        BINARY_PATH.is_match(path.to_str().unwrap_or_default())
+    }
+
+    fn table_name(&self,
+            _path: &Path) -> String
+    {
+        String::from("binaries_hashes")
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        (table == "binaries_hashes").then(|| vec![String::from("key"), String::from("value")])
+    }
+
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        Ok(self.records(path, read_buffer)?.into())
+    }
+
+    fn records(&self,
+            path: &Path,
+            _read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Binary(parse_binary(path)?))
+    }
+}
+
+pub(crate) fn parse_binary(path: &Path) -> Result<BinaryEntry, AndroidParserError> {
+    match BINARY_PATH.captures(path.to_str().unwrap_or_default()) {
+        Some(caps) => Ok(BinaryEntry {
+            name: caps.name("bin_name").map_or("".to_string(), |m| m.as_str().to_string()),
+            sha256_sum: sha256::try_digest(path)?,
+        }),
+        None => Err(AndroidParserError::parse(path.to_str().unwrap_or_default(),
+                "Not a binary of /system/bin")
+        ),
+    }
+}
+
+pub(crate) fn parse_getprop(read_buffer: &mut dyn BufRead) -> Vec<Property> {
+    let mut results: Vec<Property> = vec![];
+    let re = match Regex::new(r"^\[(?P<part1>.*)\]: \[(?P<part2>.*)\]$") {
+        Ok(x) => x,
+        Err(err) => panic!("{}", err),
+    };
+    for line in read_buffer.lines() {
+        let line_str = line.unwrap_or_default();
+        let catches = match re.captures(line_str.as_str()) {
+            Some(caps) => Property {
+                key: caps.name("part1").map_or("".to_string(), |m| String::from(m.as_str())),
+                value: caps.name("part2").map_or("".to_string(), |m| String::from(m.as_str())),
+            },
+            None => { continue; },
+        };
+        results.push(catches);
+    }
+    results
+}
+
+pub(crate) fn parse_settings(read_buffer: &mut dyn BufRead) -> Vec<Setting>
+{
+    let mut results = vec![];
+    for line in read_buffer.lines() {
+        let line_str = match line {
+            Ok(x) => x,
+            Err(_err) => { continue; },
+        };
+        let mut splited_str = line_str.split("=");
+        let val1: &str = splited_str.next().unwrap_or_default();
+        let val2: &str = splited_str.next().unwrap_or_default();
+        results.push(Setting {
+            key: val1.to_string(),
+            value: val2.to_string(),
+        });
+    }
+    results
+}
+
+pub(crate) fn parse_df_ah(read_buffer: &mut dyn BufRead) -> Vec<DiskUsageEntry>
+{
+    let mut results: Vec<DiskUsageEntry> = vec![];
+    for line in read_buffer.lines() {
+        let line_str = match line {
+            Ok(x) => x,
+            Err(_err) => { continue; },
+        };
+        let splited_str: Vec<&str> = line_str.split_whitespace().collect();
+        if splited_str.len() == 6 {
+            let first = splited_str.first().unwrap();
+            let last = splited_str.last().unwrap();
+            results.push(DiskUsageEntry {
+                filesystem: first.to_string(),
+                mounted_on: last.to_string(),
+                size: splited_str[1].to_string(),
+                used: splited_str[2].to_string(),
+            });
+        }
+    }
+    results
+}
+
+pub(crate) fn parse_id(read_buffer: &mut dyn BufRead) -> Vec<IdEntry>
+{
+    let mut results: Vec<IdEntry> = vec![];
+    for line in read_buffer.lines() {
+        let line_str = line.unwrap_or_default();
+        line_str.split_whitespace().for_each(|each_split| {
+            let coupled_values: Vec<String> = each_split.split("=").map(|x| x.to_string()).collect();
+            let group_name = match coupled_values.first() {
+                Some(x) => x,
+                None => "",
+            };
+            let gathered_users: Vec<String> = match coupled_values.last() {
+                Some(x) => x.split(",").map(|x| x.to_string()).collect(),
+                None => vec![],
+            };
+            results.push(IdEntry {
+                name: group_name.to_string(),
+                values: gathered_users,
+            });
+        });
+    }
+    results
+}
+
+pub(crate) fn parse_mount(read_buffer: &mut dyn BufRead) -> Vec<MountEntry>
+{
+    let mut results: Vec<MountEntry> = vec![];
+    let re = match Regex::new(r"^(?P<NAME>\S+)\s+on\s+(?P<MOUNTPOINT>.+)\s+type\s+(?P<TYPE>\S+)\s+\((?P<OPTIONS>\S+)\)$") {
+        Ok(x) => x,
+        Err(err) => panic!("{}", err),
+    };
+    for line in read_buffer.lines() {
+        let line_str = line.unwrap_or_default();
+        let caps = re.captures(line_str.as_str());
+        let values = match caps {
+            Some(caps) => MountEntry {
+                device: caps.name("NAME").map_or("".to_string(), |m| m.as_str().to_string()),
+                mount_point: caps.name("MOUNTPOINT").map_or("".to_string(), |m| m.as_str().to_string()),
+                fs_type: caps.name("TYPE").map_or("".to_string(), |m| m.as_str().to_string()),
+                options: caps.name("OPTIONS").map_or("".to_string(), |m| m.as_str().to_string()),
+            },
+            None => { continue; },
+        };
+        results.push(values);
+    }
+    results
+}
+
+pub(crate) fn parse_ps(read_buffer: &mut dyn BufRead) -> Vec<ProcessEntry>
+{
+    let mut results: Vec<ProcessEntry> = vec![];
+    let re = match Regex::new(r"^(?P<UID>\w+)\s+(?P<PID>\d+)\s+(?P<PPID>\d+)(\s+\S+){4}\s+(?P<CMD>\[?\w+(\/\d+(:\d+)?)?\]?)$") {
+        Ok(x) => x,
+        Err(err) => panic!("{}", err),
+    };
+    for line in read_buffer.lines() {
+        let line_str = line.unwrap_or_default();
+        let caps = re.captures(line_str.as_str());
+        let values = match caps {
+            Some(caps) => ProcessEntry {
+                user: caps.name("UID").map_or("".to_string(), |m| m.as_str().to_string()),
+                pid: caps.name("PID").map_or("".to_string(), |m| m.as_str().to_string()),
+                ppid: caps.name("PPID").map_or("".to_string(), |m| m.as_str().to_string()),
+                command: caps.name("CMD").map_or("".to_string(), |m| m.as_str().to_string()),
+            },
+            None => { continue; },
+        };
+        results.push(values);
+    }
+    results
+}
+
+pub(crate) fn parse_services(read_buffer: &mut dyn BufRead) -> Vec<ServiceEntry>
+{
+    let mut results: Vec<ServiceEntry> = vec![];
+    let re = match Regex::new(r"^\d+\s+(?P<SVCNAME>\S+): (?P<SVCVAL>\S+)$") {
+        Ok(x) => x,
+        Err(err) => panic!("{}", err),
+    };
+    for line in read_buffer.lines() {
+        let line_str = line.unwrap_or_default();
+        let caps = re.captures(line_str.as_str());
+        let values: (&str, &str) = match caps {
+            Some(caps) => (caps.name("SVCNAME").map_or("", |m| m.as_str()),
+                    caps.name("SVCVAL").map_or("", |m| m.as_str())
+            ),
+            None => { continue; },
+        };
+        results.push(ServiceEntry {
+            name: values.0.to_string(),
+            interface: values.1.to_string(),
+        });
+    }
+    results
+}
+
+pub(crate) fn parse_list(read_buffer: &mut dyn BufRead) -> Vec<PackageListEntry>
+{
+    let mut results: Vec<PackageListEntry> = vec![];
+    for line in read_buffer.lines() {
+        let line_str = line.unwrap_or_default();
+        let splitted_str: Vec<String> = line_str.split(":").map(|x| x.to_string()).collect();
+        results.push(PackageListEntry {
+            kind: splitted_str.first().unwrap().to_string(),
+            name: splitted_str.last().unwrap().to_string(),
+        });
+    }
+    results
+}
+
+pub(crate) fn parse_permissions_list(read_buffer: &mut dyn BufRead) -> Vec<PermissionDefinition>
+{
+    let mut results: Vec<PermissionDefinition> = vec![];
+    let mut block_counter: usize = 0;
+    let mut row_values: [String; 5] = Default::default();
+    for line in read_buffer.lines() {
+        let line_str = match line {
+            Ok(x) if x.starts_with("+ ") | x.starts_with("  ") => String::from(&x[2..]),
+            Ok(_) | Err(_) => continue,
+        };
+        let splitted_line: Vec<String> = line_str.split(":").map(|x| x.to_string()).collect();
+        row_values[block_counter] = splitted_line.last().unwrap().to_string();
+        if block_counter >= 4 {
+            let [permission, package, label, description, protection_level] = row_values;
+            results.push(PermissionDefinition { permission, package, label, description, protection_level });
+            block_counter = 0;
+            row_values = Default::default();
+        }
+        else {
+            block_counter += 1;
+        }
+    }
+    results
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn getprop_skips_the_lines_without_brackets() {
+        let properties = parse_getprop(&mut Cursor::new(
+                "[ro.debuggable]: [0]\n[ro.build.tags]: [release-keys]\ngarbage\n[ro.empty]: []\n"
+        ));
+        assert_eq!(properties, vec![
+            Property { key: String::from("ro.debuggable"), value: String::from("0") },
+            Property { key: String::from("ro.build.tags"), value: String::from("release-keys") },
+            Property { key: String::from("ro.empty"), value: String::new() },
+        ]);
+    }
+
+    #[test]
+    fn settings_read_the_key_and_value() {
+        let settings = parse_settings(&mut Cursor::new("adb_enabled=1\nwifi_on=\nflag\n"));
+        assert_eq!(settings.iter().map(|x| (x.key.as_str(), x.value.as_str())).collect::<Vec<_>>(),
+                vec![("adb_enabled", "1"), ("wifi_on", ""), ("flag", "")]
+        );
+    }
+
+    #[test]
+    fn df_ah_keeps_the_six_columns_lines_only() {
+        let entries = parse_df_ah(&mut Cursor::new(
+                "Filesystem Size Used Avail Use% Mounted on\n/dev/block/dm-0 3.5G 3.4G 12M 100% /\ntmpfs 1.8G 1.2M 1.8G 1% /dev\n"
+        ));
+        assert_eq!(entries, vec![
+            DiskUsageEntry { filesystem: String::from("/dev/block/dm-0"), mounted_on: String::from("/"), size: String::from("3.5G"), used: String::from("3.4G") },
+            DiskUsageEntry { filesystem: String::from("tmpfs"), mounted_on: String::from("/dev"), size: String::from("1.8G"), used: String::from("1.2M") },
+        ]);
+    }
+
+    #[test]
+    fn id_splits_the_fields_and_their_values() {
+        let entries = parse_id(&mut Cursor::new("uid=0(root) gid=0(root) groups=0(root),1004(input),1007(log)\n"));
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0], IdEntry { name: String::from("uid"), values: vec![String::from("0(root)")] });
+        assert_eq!(entries[2].name, "groups");
+        assert_eq!(entries[2].values, vec!["0(root)", "1004(input)", "1007(log)"]);
+    }
+
+    #[test]
+    fn mount_reads_the_device_mount_point_type_and_options() {
+        let entries = parse_mount(&mut Cursor::new(
+                "/dev/block/dm-0 on / type ext4 (ro,seclabel,relatime)\nnot a mount line\ntmpfs on /mnt/user space type tmpfs (rw,nosuid)\n"
+        ));
+        assert_eq!(entries, vec![
+            MountEntry { device: String::from("/dev/block/dm-0"), mount_point: String::from("/"), fs_type: String::from("ext4"), options: String::from("ro,seclabel,relatime") },
+            MountEntry { device: String::from("tmpfs"), mount_point: String::from("/mnt/user space"), fs_type: String::from("tmpfs"), options: String::from("rw,nosuid") },
+        ]);
+    }
+
+    #[test]
+    fn ps_reads_the_user_pids_and_command() {
+        let entries = parse_ps(&mut Cursor::new(
+                "USER PID PPID VSZ RSS WCHAN ADDR S NAME\nroot 1 0 10857652 12120 do_epoll_wait S init\nroot 12 2 0 0 worker_thread I [kworker/0:1]\n"
+        ));
+        assert_eq!(entries, vec![
+            ProcessEntry { user: String::from("root"), pid: String::from("1"), ppid: String::from("0"), command: String::from("init") },
+            ProcessEntry { user: String::from("root"), pid: String::from("12"), ppid: String::from("2"), command: String::from("[kworker/0:1]") },
+        ]);
+    }
+
+    #[test]
+    fn services_read_the_name_and_interface() {
+        let entries = parse_services(&mut Cursor::new(
+                "Found 2 services:\n0\tactivity: [android.app.IActivityManager]\n1\tnfc: [android.nfc.INfcAdapter]\n"
+        ));
+        assert_eq!(entries, vec![
+            ServiceEntry { name: String::from("activity"), interface: String::from("[android.app.IActivityManager]") },
+            ServiceEntry { name: String::from("nfc"), interface: String::from("[android.nfc.INfcAdapter]") },
+        ]);
+    }
+
+    #[test]
+    fn list_splits_the_kind_and_name() {
+        let entries = parse_list(&mut Cursor::new("package:com.android.phone\nfeature:android.hardware.nfc\n"));
+        assert_eq!(entries, vec![
+            PackageListEntry { kind: String::from("package"), name: String::from("com.android.phone") },
+            PackageListEntry { kind: String::from("feature"), name: String::from("android.hardware.nfc") },
+        ]);
+    }
+
+    #[test]
+    fn permissions_list_reads_blocks_of_five_lines() {
+        let entries = parse_permissions_list(&mut Cursor::new(concat!(
+            "All Permissions:\n\n",
+            "+ permission:android.permission.INTERNET\n",
+            "  package:android\n",
+            "  label:have full network access\n",
+            "  description:Allows the app to create network sockets.\n",
+            "  protectionLevel:normal\n",
+            "+ permission:android.permission.CAMERA\n",
+            "  package:android\n",
+        )));
+        assert_eq!(entries, vec![PermissionDefinition {
+            permission: String::from("android.permission.INTERNET"),
+            package: String::from("android"),
+            label: String::from("have full network access"),
+            description: String::from("Allows the app to create network sockets."),
+            protection_level: String::from("normal"),
+        }]);
+    }
+
+    #[test]
+    fn binary_parser_handles_the_files_of_system_bin_only() {
+        assert!(BinaryParser.matches(Path::new("/dump/S21/system/bin/sh")));
+        assert!(BinaryParser.matches(Path::new("C:\\Smartphones\\S21\\system\\bin\\toybox")));
+        assert!(!BinaryParser.matches(Path::new("/dump/S21/system/lib/libc.so")));
+        assert!(parse_binary(Path::new("/dump/S21/getprop.txt")).is_err());
+    }
+}
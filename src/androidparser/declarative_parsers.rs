@@ -0,0 +1,307 @@
+//! Declarative parsers
+//!
+//! Parsers declared in a TOML file instead of Rust code: which dump files
+//! they handle, a line regex with named captures, the key capture and the
+//! compared value captures. The reference table and the comparison are
+//! chosen from the number of value captures.
+//!
+//! ```toml
+//! [[parser]]
+//! name = "lsmod"
+//! file = '^lsmod\.txt$'
+//! line = '^(?P<module>\S+)\s+(?P<size>\d+)\s+(?P<used_by>\d+)'
+//! key = "module"
+//! values = ["size"]
+//! ```
+
+use std::{io::{BufRead, Error}, path::Path};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::{AndroidParserError, parser::{ParsedEntries, Parser}};
+
+/// A parser as declared in the TOML file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ParserDefinition {
+    /// Name of the parser, also the reference table unless 'table' is set.
+    pub name: String,
+    /// Regex matched against the file name of the dump files.
+    pub file: String,
+    /// Regex matched against each line, with named captures.
+    pub line: String,
+    /// Capture holding the key of the line.
+    pub key: String,
+    /// Captures holding the compared values of the key: 1 value is compared
+    /// as 'getprop', 3 values as 'mount', 4 values as 'pm_list_permissions-f',
+    /// and any other count as a single value joined by a space.
+    pub values: Vec<String>,
+    /// Reference table, instead of 'name'.
+    #[serde(default)]
+    pub table: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ParserDefinitions {
+    #[serde(default, rename = "parser")]
+    parsers: Vec<ParserDefinition>,
+}
+
+/// A parser built from a 'ParserDefinition'.
+pub struct DeclarativeParser {
+    definition: ParserDefinition,
+    file_regex: Regex,
+    line_regex: Regex,
+}
+
+impl DeclarativeParser {
+    /// Compile the regexes of 'definition', checking the key and value captures exist.
+    pub fn new(definition: ParserDefinition) -> Result<Self, AndroidParserError> {
+        let file_regex = match Regex::new(&definition.file) {
+            Ok(x) => x,
+            Err(err) => return Err(AndroidParserError::parse(&definition.name,
+                    &format!("Invalid file regex => {err}"))
+            ),
+        };
+        let line_regex = match Regex::new(&definition.line) {
+            Ok(x) => x,
+            Err(err) => return Err(AndroidParserError::parse(&definition.name,
+                    &format!("Invalid line regex => {err}"))
+            ),
+        };
+        let capture_names: Vec<&str> = line_regex.capture_names().flatten().collect();
+        if let Some(missing) = std::iter::once(&definition.key)
+                .chain(definition.values.iter())
+                .find(|x| !capture_names.contains(&x.as_str()))
+        {
+            return Err(AndroidParserError::parse(&definition.name,
+                    &format!("No capture named '{missing}' in the line regex"))
+            );
+        }
+        Ok(Self { definition, file_regex, line_regex })
+    }
+
+    pub fn definition(&self) -> &ParserDefinition {
+        &self.definition
+    }
+}
+
+impl Parser for DeclarativeParser {
+    fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    fn matches(&self,
+            path: &Path) -> bool
+    {
+        path.file_name().is_some_and(|x| self.file_regex.is_match(&x.to_string_lossy()))
+    }
+
+    fn table_name(&self,
+            _path: &Path) -> String
+    {
+        self.definition.table.clone().unwrap_or(self.definition.name.clone())
+    }
+
+    fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        if table != self.table_name(Path::new("")) {
+            return None;
+        }
+        match self.definition.values.len() {
+            3 | 4 => Some(std::iter::once(self.definition.key.clone())
+                    .chain(self.definition.values.iter().cloned())
+                    .collect()
+            ),
+            _ => Some(vec![String::from("key"), String::from("value")]),
+        }
+    }
+
+    fn parse(&self,
+            _path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+    {
+        let mut rows: Vec<(String, Vec<String>)> = vec![];
+        for line in read_buffer.lines() {
+            let line_str = line?;
+            let caps = match self.line_regex.captures(line_str.as_str()) {
+                Some(x) => x,
+                None => { continue; },
+            };
+            rows.push(( caps.name(&self.definition.key).map_or("".to_string(), |m| m.as_str().to_string()),
+                    self.definition.values.iter()
+                            .map(|x| caps.name(x).map_or("".to_string(), |m| m.as_str().to_string()))
+                            .collect()
+            ));
+        }
+        let key = self.definition.key.clone();
+        let values = &self.definition.values;
+        match values.len() {
+            1 => Ok(ParsedEntries::KeyValue(rows.into_iter()
+                    .map(|(x, mut y)| (x, y.remove(0)))
+                    .collect()
+            )),
+            3 => Ok(ParsedEntries::Key3Values {
+                headers: (key, values[0].clone(), values[1].clone(), values[2].clone()),
+                entries: rows.into_iter()
+                        .map(|(x, y)| (x, y[0].clone(), y[1].clone(), y[2].clone()))
+                        .collect(),
+            }),
+            4 => Ok(ParsedEntries::Block5Values {
+                headers: (key, values[0].clone(), values[1].clone(), values[2].clone(), values[3].clone()),
+                entries: rows.into_iter()
+                        .map(|(x, y)| [x, y[0].clone(), y[1].clone(), y[2].clone(), y[3].clone()])
+                        .collect(),
+            }),
+            _ => Ok(ParsedEntries::KeyValue(rows.into_iter()
+                    .map(|(x, y)| (x, y.join(" ")))
+                    .collect()
+            )),
+        }
+    }
+}
+
+/// Read the parsers declared in the TOML file 'path'.
+///
+/// # Exemple
+///
+/// ```no_run
+/// use std::path::Path;
+/// use android_sanity_checker::androidparser::{load_parser_definitions, AndroidParserBuilder};
+///
+/// let mut android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+///         .build()
+///         .unwrap();
+/// for parser in load_parser_definitions(Path::new("C:/asc/parsers.toml")).unwrap() {
+///     android_parser.register_parser(parser);
+/// }
+/// ```
+pub fn load_parser_definitions(path: &Path) -> Result<Vec<DeclarativeParser>, AndroidParserError> {
+    let content = std::fs::read_to_string(path).map_err(|err| Error::new(err.kind(),
+            format!("Parsers file {} => {err}", path.display()))
+    )?;
+    let definitions: ParserDefinitions = match toml::from_str(&content) {
+        Ok(x) => x,
+        Err(err) => return Err(AndroidParserError::parse(path.to_str().unwrap_or_default(),
+                &err.to_string())
+        ),
+    };
+    definitions.parsers.into_iter()
+            .map(DeclarativeParser::new)
+            .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LSMOD_PARSER: &str = r#"
+[[parser]]
+name = "lsmod"
+file = '^lsmod\.txt$'
+line = '^(?P<module>\S+)\s+(?P<size>\d+)\s+(?P<used_by>\d+)'
+key = "module"
+values = ["size"]
+"#;
+
+    fn parsers_file(name: &str,
+            content: &str) -> std::path::PathBuf
+    {
+        let path = std::env::temp_dir().join(format!("asc_declarative_test_{}_{name}.toml", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn definition(line: &str,
+            values: &[&str]) -> ParserDefinition
+    {
+        ParserDefinition {
+            name: String::from("custom"),
+            file: String::from(r"^custom\.txt$"),
+            line: line.to_string(),
+            key: String::from("key"),
+            values: values.iter().map(|x| x.to_string()).collect(),
+            table: None,
+        }
+    }
+
+    #[test]
+    fn load_and_parse() {
+        let path = parsers_file("lsmod", LSMOD_PARSER);
+        let parsers = load_parser_definitions(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(parsers.len(), 1);
+        let parser = &parsers[0];
+        assert_eq!(parser.name(), "lsmod");
+        assert!(parser.matches(Path::new("/dumps/S21/lsmod.txt")));
+        assert!(!parser.matches(Path::new("/dumps/S21/lsmod.txt.bak")));
+        assert_eq!(parser.table_name(Path::new("/dumps/S21/lsmod.txt")), "lsmod");
+        let mut read_buffer = "Module Size Used by\nwlan 4096 0\nexfat 2048 1 sdcard\n".as_bytes();
+        assert_eq!(parser.parse(Path::new("lsmod.txt"), &mut read_buffer).unwrap(),
+                ParsedEntries::KeyValue(vec![
+                    (String::from("wlan"), String::from("4096")),
+                    (String::from("exfat"), String::from("2048")),
+                ])
+        );
+    }
+
+    #[test]
+    fn load_without_parser() {
+        let path = parsers_file("empty", "");
+        assert!(load_parser_definitions(&path).unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_invalid_files() {
+        assert!(load_parser_definitions(Path::new("/nonexistent/asc_parsers.toml")).is_err());
+        let path = parsers_file("invalid_toml", "[[parser]]\nname = ");
+        assert!(load_parser_definitions(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+        let path = parsers_file("missing_field", "[[parser]]\nname = \"lsmod\"\n");
+        assert!(load_parser_definitions(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+        let path = parsers_file("missing_capture", &LSMOD_PARSER.replace(r#"values = ["size"]"#, r#"values = ["size", "state"]"#));
+        assert!(load_parser_definitions(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn invalid_regexes() {
+        assert!(DeclarativeParser::new(definition(r"^(?P<key>\S+) (?P<value>\S+)", &["value"])).is_ok());
+        assert!(DeclarativeParser::new(definition(r"^(?P<key>\S+ (?P<value>\S+)", &["value"])).is_err());
+        assert!(DeclarativeParser::new(ParserDefinition { file: String::from("("), ..definition(r"^(?P<key>\S+) (?P<value>\S+)", &["value"]) }).is_err());
+        assert!(DeclarativeParser::new(definition(r"^(?P<name>\S+) (?P<value>\S+)", &["value"])).is_err());
+    }
+
+    #[test]
+    fn entries_by_values_count() {
+        let parse = |definition: ParserDefinition, content: &str| -> ParsedEntries {
+            let mut read_buffer = content.as_bytes();
+            DeclarativeParser::new(definition).unwrap().parse(Path::new("custom.txt"), &mut read_buffer).unwrap()
+        };
+        let three = definition(r"^(?P<key>\S+) (?P<a>\S+) (?P<b>\S+) (?P<c>\S+)$", &["a", "b", "c"]);
+        assert_eq!(parse(three, "k 1 2 3\n"), ParsedEntries::Key3Values {
+            headers: (String::from("key"), String::from("a"), String::from("b"), String::from("c")),
+            entries: vec![(String::from("k"), String::from("1"), String::from("2"), String::from("3"))],
+        });
+        let four = definition(r"^(?P<key>\S+) (?P<a>\S+) (?P<b>\S+) (?P<c>\S+) (?P<d>\S+)$", &["a", "b", "c", "d"]);
+        assert_eq!(parse(four, "k 1 2 3 4\n"), ParsedEntries::Block5Values {
+            headers: (String::from("key"), String::from("a"), String::from("b"), String::from("c"), String::from("d")),
+            entries: vec![[String::from("k"), String::from("1"), String::from("2"), String::from("3"), String::from("4")]],
+        });
+        let two = definition(r"^(?P<key>\S+) (?P<a>\S+) (?P<b>\S+)$", &["a", "b"]);
+        assert_eq!(parse(two, "k 1 2\nnot matching\n"), ParsedEntries::KeyValue(vec![(String::from("k"), String::from("1 2"))]));
+        let table = ParserDefinition { table: Some(String::from("custom_table")), ..definition(r"^(?P<key>\S+)$", &[]) };
+        assert_eq!(DeclarativeParser::new(table).unwrap().table_name(Path::new("custom.txt")), "custom_table");
+    }
+
+    #[test]
+    fn columns_of_the_declared_table() {
+        let parser = DeclarativeParser::new(definition(r"^(?P<key>\S+) (?P<a>\S+) (?P<b>\S+) (?P<c>\S+)$", &["a", "b", "c"])).unwrap();
+        assert_eq!(parser.columns("custom"), Some(vec![String::from("key"), String::from("a"), String::from("b"), String::from("c")]));
+        assert_eq!(parser.columns("getprop"), None);
+        let parser = DeclarativeParser::new(definition(r"^(?P<key>\S+) (?P<a>\S+)$", &["a"])).unwrap();
+        assert_eq!(parser.columns("custom"), Some(vec![String::from("key"), String::from("value")]));
+    }
+}
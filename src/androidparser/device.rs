@@ -0,0 +1,130 @@
+//! Devices
+//!
+//! An analysis directory holds one or many device dumps. Each device
+//! is analyzed against its own reference connection.
+
+use std::{collections::HashSet, path::{Path, PathBuf}, sync::{Arc, Mutex}};
+use sqlite::ConnectionThreadSafe;
+use serde::{Deserialize, Serialize};
+
+use super::{AndroidParser, builtin_parsers, reference_json::ModuleWildcards};
+
+/// Sub directories of an ADB dump identifying a device root.
+const DEVICE_MARKERS: [&str; 6] = ["backup", "dumpsys", "info", "live", "package_manager", "system"];
+
+/// A device being analyzed, and the reference it is compared against.
+pub(crate) struct DeviceContext {
+    pub root: PathBuf,
+    pub connx: Arc<ConnectionThreadSafe>,
+    /// Binaries of '/system/bin' found on the device, matching the reference or not.
+    pub seen_binaries: Mutex<HashSet<String>>,
+    /// Dumps the reference was built from, the values frequencies being
+    /// reported only for a baseline of many.
+    pub baseline_devices: usize,
+    /// Wildcard rows of the reference, loaded once for the device.
+    pub wildcards: ModuleWildcards,
+}
+
+/// Rows of a reference module compared against a dump file of the device,
+/// its other rows being then reported as removed.
+#[derive(Debug, Default)]
+pub(crate) struct ComparedRows {
+    /// Reference rows matched by a row of the device.
+    pub matched_rowids: HashSet<i64>,
+    /// Keys whose device rows differ from the reference, or are accepted by a wildcard row.
+    pub differing_keys: HashSet<String>,
+}
+
+impl DeviceContext {
+    pub fn root_str(&self) -> String {
+        self.root.to_string_lossy().to_string()
+    }
+}
+
+/// Identity of a device, read from its 'getprop.txt'.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceIdentity {
+    /// 'ro.build.fingerprint' of the device.
+    pub build_fingerprint: String,
+    /// 'ro.product.model' of the device.
+    pub product_model: String,
+}
+
+/// Recursively find device roots under 'path'. A directory is a device root
+/// if it holds one of the usual ADB dump sub directories (system, info, live...).
+/// If none is found, 'path' itself is taken as the only device.
+pub(crate) fn find_device_roots(path: &Path) -> Vec<PathBuf> {
+    let mut device_roots: Vec<PathBuf> = vec![];
+    find_device_roots_rec(path, &mut device_roots);
+    if device_roots.is_empty() {
+        device_roots.push(path.to_path_buf());
+    }
+    device_roots.sort();
+    device_roots
+}
+
+fn find_device_roots_rec(path: &Path,
+        device_roots: &mut Vec<PathBuf>)
+{
+    let sub_dirs: Vec<PathBuf> = match path.read_dir() {
+        Ok(read_dir) => read_dir.filter_map(|each_dir| each_dir.ok())
+                .filter(|each_entry| each_entry.file_type().is_ok_and(|x| x.is_dir()))
+                .map(|each_entry| each_entry.path())
+                .collect(),
+        Err(_) => return,
+    };
+    if sub_dirs.iter().any(|x| x.file_name().is_some_and(|name| DEVICE_MARKERS.contains(&name.to_str().unwrap_or_default()))) {
+        device_roots.push(path.to_path_buf());
+    }
+    else {
+        sub_dirs.iter().for_each(|x| find_device_roots_rec(x, device_roots));
+    }
+}
+
+/// Number of files under 'path', recursively.
+pub(crate) fn count_files(path: &Path) -> usize {
+    match path.read_dir() {
+        Ok(read_dir) => read_dir.filter_map(|each_dir| each_dir.ok())
+                .map(|each_entry| match each_entry.file_type() {
+                    Ok(x) if x.is_dir() => count_files(&each_entry.path()),
+                    Ok(x) if x.is_file() => 1,
+                    _ => 0,
+                })
+                .sum(),
+        Err(_) => 0,
+    }
+}
+
+/// Find the first 'getprop.txt' under 'path'.
+fn find_getprop(path: &Path) -> Option<PathBuf> {
+    let mut sub_dirs: Vec<PathBuf> = vec![];
+    for each_entry in path.read_dir().ok()?.filter_map(|each_dir| each_dir.ok()) {
+        if each_entry.file_type().is_ok_and(|x| x.is_dir()) {
+            sub_dirs.push(each_entry.path());
+        }
+        else if each_entry.file_name() == "getprop.txt" {
+            return Some(each_entry.path());
+        }
+    }
+    sub_dirs.sort();
+    sub_dirs.iter().find_map(|x| find_getprop(x))
+}
+
+impl AndroidParser {
+    /// Read the device identity from the 'getprop.txt' found under 'device_root'.
+    pub(crate) fn read_device_identity(&self,
+            device_root: &Path) -> Option<DeviceIdentity>
+    {
+        let getprop_path = find_getprop(device_root)?;
+        let mut buf_reader = self.create_bufreader(&getprop_path).ok()?;
+        let mut identity = DeviceIdentity::default();
+        builtin_parsers::parse_getprop(&mut buf_reader).into_iter().for_each(|property| {
+            match property.key.as_str() {
+                "ro.build.fingerprint" => identity.build_fingerprint = property.value,
+                "ro.product.model" => identity.product_model = property.value,
+                _ => (),
+            };
+        });
+        Some(identity)
+    }
+}
@@ -0,0 +1,528 @@
+//! Device diff
+//!
+//! Two dumps compared against each other, neither being the reference:
+//! a device at two dates, or two suspects. Every registered parser is run
+//! on both dumps, and the rows of every module and binary are diffed both
+//! ways into 'reported_diff.csv' and 'device_diff.json'.
+//! Two saved or exported references (old and new firmware) are diffed the
+//! same way, wildcard rows included, into 'reported_firmware_diff.csv' and
+//! 'firmware_diff.json'.
+
+use std::{collections::{BTreeMap, BTreeSet}, fmt, fs::File, io::{BufWriter, Error, ErrorKind, Write}, path::Path, sync::Mutex};
+use serde::{Deserialize, Serialize};
+
+use super::{AndroidParser, AndroidParserError, CsvWriter, Event, ParsedEntries, ParserRegistry, ReferenceMetadata, RunPhase, device, findings::EXPECTED_SEPARATOR, reference, reference_json, report};
+
+/// Rows of a dump, by module then key.
+type ModuleRows = BTreeMap<String, BTreeMap<String, BTreeSet<Vec<String>>>>;
+/// Module of a dump file, and its rows (key, values).
+type FileRows = (String, Vec<(String, Vec<String>)>);
+
+/// How a key differs between the two dumps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    /// The key is only on the first dump.
+    OnlyInA,
+    /// The key is only on the second dump.
+    OnlyInB,
+    /// The key is on both dumps, with other value(s).
+    Changed,
+}
+
+impl fmt::Display for DiffKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffKind::OnlyInA => write!(f, "only_in_a"),
+            DiffKind::OnlyInB => write!(f, "only_in_b"),
+            DiffKind::Changed => write!(f, "changed"),
+        }
+    }
+}
+
+/// A key differing between the two dumps, with the values of every row of each.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiffEntry {
+    /// Module of the key: 'getprop', 'mount', 'binaries'...
+    pub module: String,
+    pub key: String,
+    pub kind: DiffKind,
+    /// Values of the rows of the key on the first dump, empty if only in B.
+    pub a: Vec<Vec<String>>,
+    /// Values of the rows of the key on the second dump, empty if only in A.
+    pub b: Vec<Vec<String>>,
+}
+
+/// Symmetric diff of two dumps, or of two saved references.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceDiff {
+    /// Root directory of the first dump, or path of the first reference.
+    pub device_a: String,
+    /// Root directory of the second dump, or path of the second reference.
+    pub device_b: String,
+    /// Differing keys, by module then key.
+    pub entries: Vec<DiffEntry>,
+    /// Files which could not be parsed, written into 'reported_errors.csv'.
+    pub errors: usize,
+}
+
+impl DeviceDiff {
+    /// Entries of 'kind'.
+    pub fn count(&self,
+            kind: DiffKind) -> usize
+    {
+        self.entries.iter().filter(|x| x.kind == kind).count()
+    }
+
+    /// Entries of each module, by kind.
+    pub fn modules(&self) -> BTreeMap<String, BTreeMap<DiffKind, usize>> {
+        let mut modules: BTreeMap<String, BTreeMap<DiffKind, usize>> = BTreeMap::new();
+        self.entries.iter().for_each(|x| {
+            *modules.entry(x.module.clone()).or_default().entry(x.kind).or_default() += 1;
+        });
+        modules
+    }
+}
+
+impl AndroidParser {
+    /// Diff the dump of the reference path (A) and the one of the analysis path (B)
+    /// both ways, with every registered parser and the normalizers, without any
+    /// reference. The diff is written into 'reported_diff.csv' and 'device_diff.json'
+    /// of the folder of the run, and files which cannot be parsed into 'reported_errors.csv'.
+    /// The parser is built with 'load_reference(false)', the first dump not being a reference.
+    /// Observers receive the 'RunPhase::Diff' run, its parsed files and errors.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::{AndroidParserBuilder, DiffKind};
+    ///
+    /// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_january"), String::from("C:/Smartphones/S21_june"))
+    ///         .load_reference(false)
+    ///         .embedded_rules(false)
+    ///         .build()
+    ///         .unwrap();
+    /// let device_diff = android_parser.diff_devices().unwrap();
+    /// println!("{} changed key(s)", device_diff.count(DiffKind::Changed));
+    /// ```
+    pub fn diff_devices(&self) -> Result<DeviceDiff, AndroidParserError> {
+        self.run_stats.reset();
+        let device_a = Path::new(&self.path_reference);
+        let device_b = Path::new(&self.path_analyze);
+        device_a.read_dir()?;
+        device_b.read_dir()?;
+        self.emit(Event::RunStarted {
+            phase: RunPhase::Diff,
+            devices: 2,
+            files: device::count_files(device_a) + device::count_files(device_b),
+        });
+        self.run_stats.add_device();
+        let rows_a = self.collect_rows(device_a);
+        self.run_stats.add_device();
+        let rows_b = self.collect_rows(device_b);
+        let device_diff = DeviceDiff {
+            device_a: device_a.to_string_lossy().to_string(),
+            device_b: device_b.to_string_lossy().to_string(),
+            entries: diff_rows(&rows_a, &rows_b),
+            errors: self.run_stats.summary().errors,
+        };
+        write_diff_reports(self.report_dir(),
+                self.file_reporter.delimiter(),
+                &device_diff,
+                "reported_diff.csv",
+                "device_diff.json"
+        )?;
+        self.flush_reports()?;
+        self.emit(Event::RunFinished { phase: RunPhase::Diff, summary: self.run_stats.summary() });
+        Ok(device_diff)
+    }
+
+    /// Rows of every dump file handled by a parser under the dump 'root'.
+    fn collect_rows(&self,
+            root: &Path) -> ModuleRows
+    {
+        let module_rows: Mutex<ModuleRows> = Mutex::new(BTreeMap::new());
+        self.collect_rows_rec(&root.to_string_lossy(), root, &module_rows);
+        module_rows.into_inner().unwrap()
+    }
+
+    fn collect_rows_rec(&self,
+            root: &str,
+            path: &Path,
+            module_rows: &Mutex<ModuleRows>)
+    {
+        let read_dir = match path.read_dir() {
+            Ok(x) => x,
+            Err(err) => {
+                self.report_error(root, path.to_str().unwrap_or_default(), &err.into());
+                return;
+            },
+        };
+        self.thread_pool.scope(|s| {
+            read_dir.filter_map(|each_dir| each_dir.ok()).for_each(|each_entry| {
+                let each_path = each_entry.path();
+                if each_path.is_dir() {
+                    self.collect_rows_rec(root, &each_path, module_rows);
+                }
+                else if each_path.is_file() &&
+                        self.find_parser(&each_path).is_some()
+                {
+                    s.spawn(move |_| {
+                        match self.parse_rows(&each_path) {
+                            Ok((module, rows)) => {
+                                self.run_stats.add_file();
+                                {
+                                    let mut module_rows = module_rows.lock().unwrap();
+                                    let keys = module_rows.entry(module).or_default();
+                                    rows.into_iter().for_each(|(key, values)| {
+                                        keys.entry(key).or_default().insert(values);
+                                    });
+                                }
+                                self.emit(Event::FileParsed { device: root.to_string(), file: each_path.to_string_lossy().to_string() });
+                            },
+                            Err(err) => self.report_error(root, each_path.to_str().unwrap_or_default(), &err),
+                        };
+                    });
+                }
+                else if each_path.is_file() {
+                    self.emit(Event::FileSkipped { device: root.to_string(), file: each_path.to_string_lossy().to_string() });
+                }
+            });
+        });
+    }
+
+    /// Module and normalized rows of the dump file 'path'.
+    fn parse_rows(&self,
+            path: &Path) -> Result<FileRows, AndroidParserError>
+    {
+        let parser = match self.find_parser(path) {
+            Some(x) => x,
+            None => return Ok((String::new(), vec![])),
+        };
+        let table = parser.table_name(path);
+        let mut buf_reader = self.create_bufreader(path)?;
+        let entries = self.normalizers.normalize(&table, parser.parse(path, &mut buf_reader)?);
+        let module = match entries {
+            ParsedEntries::BinaryHash { .. } => String::from("binaries"),
+            _ => table,
+        };
+        Ok((module, entries.into_rows()))
+    }
+}
+
+/// Diff the references 'reference_a' (old firmware) and 'reference_b'
+/// (new firmware), each saved ('.sqlite' file) or exported (JSON directory),
+/// both ways: changed properties, services, permissions, '/system/bin'
+/// hashes and every other module, to review what an update legitimately
+/// changed before approving the new reference. The wildcard rows of a module
+/// are diffed as the module '<module>/wildcards'. The diff is written into
+/// 'reported_firmware_diff.csv' and 'firmware_diff.json' of 'report_dir'
+/// (see 'create_run_dir()'), without any parser nor YARA rule, the columns of
+/// an exported reference being checked against the built-in parsers only.
+/// Both references must have been built with the same normalizers.
+///
+/// # Exemple
+///
+/// ```no_run
+/// use std::path::Path;
+/// use android_sanity_checker::androidparser::{create_run_dir, diff_references};
+///
+/// let report_dir = create_run_dir(Path::new("C:/Reports"), &[]).unwrap();
+/// let firmware_diff = diff_references(Path::new("C:/asc/S21_january.sqlite"), Path::new("C:/asc/S21_june"), &report_dir, ';').unwrap();
+/// for entry in firmware_diff.entries.iter().filter(|x| x.module == "getprop") {
+///     println!("{} => {:?} / {:?}", entry.key, entry.a, entry.b);
+/// }
+/// ```
+pub fn diff_references(reference_a: &Path,
+        reference_b: &Path,
+        report_dir: &Path,
+        delimiter: char) -> Result<DeviceDiff, AndroidParserError>
+{
+    report::check_delimiter(delimiter)?;
+    check_same_normalizers(reference_a, reference_b)?;
+    let rows_a = read_reference_rows(reference_a)?;
+    let rows_b = read_reference_rows(reference_b)?;
+    let device_diff = DeviceDiff {
+        device_a: reference_a.to_string_lossy().to_string(),
+        device_b: reference_b.to_string_lossy().to_string(),
+        entries: diff_rows(&rows_a, &rows_b),
+        errors: 0,
+    };
+    write_diff_reports(report_dir,
+            delimiter,
+            &device_diff,
+            "reported_firmware_diff.csv",
+            "firmware_diff.json"
+    )?;
+    Ok(device_diff)
+}
+
+/// Refuse to diff references built with other normalizers, their values
+/// differing then by their normalization only. A reference saved before the
+/// normalizers fingerprint is diffed anyway.
+fn check_same_normalizers(reference_a: &Path,
+        reference_b: &Path) -> Result<(), Error>
+{
+    let fingerprint = |path: &Path| -> String {
+        ReferenceMetadata::from_file(path).map(|x| x.normalizers).unwrap_or_default()
+    };
+    match (fingerprint(reference_a), fingerprint(reference_b)) {
+        (a, b) if a.is_empty() || b.is_empty() || a == b => Ok(()),
+        _ => Err(Error::new(ErrorKind::InvalidInput,
+                format!("References {} and {} were built with other normalizers, expected the same --normalize file (or none)", reference_a.display(), reference_b.display()))
+        ),
+    }
+}
+
+/// Write 'device_diff' into the CSV report 'csv_name' and the JSON file
+/// 'json_name' of 'report_dir'.
+fn write_diff_reports(report_dir: &Path,
+        delimiter: char,
+        device_diff: &DeviceDiff,
+        csv_name: &str,
+        json_name: &str) -> Result<(), Error>
+{
+    write_diff(&report_dir.join(csv_name),
+            device_diff,
+            delimiter
+    )?;
+    let mut buf_writer = BufWriter::new(File::create(report_dir.join(json_name))?);
+    serde_json::to_writer_pretty(&mut buf_writer, device_diff).map_err(Error::from)?;
+    buf_writer.flush()
+}
+
+/// Rows of every module of the saved or exported reference 'path', with
+/// its wildcard rows as the module '<module>/wildcards'.
+fn read_reference_rows(path: &Path) -> Result<ModuleRows, AndroidParserError> {
+    let connx = sqlite::Connection::open_thread_safe(":memory:")?;
+    reference::load_any_reference(&connx, path, &ParserRegistry::builtin())?;
+    let module_name = |table: &str| -> String {
+        match table {
+            "binaries_hashes" => String::from("binaries"),
+            _ => table.to_string(),
+        }
+    };
+    let mut module_rows: ModuleRows = BTreeMap::new();
+    for table in reference::module_tables(&connx)? {
+        let keys = module_rows.entry(module_name(&table)).or_default();
+        reference::read_table_rows(&connx, &table)?.into_iter().for_each(|(key, values)| {
+            keys.entry(key).or_default().insert(values);
+        });
+    }
+    for (table, wildcards) in reference_json::load_wildcards(&connx) {
+        let keys = module_rows.entry(format!("{}/wildcards", module_name(&table))).or_default();
+        wildcards.into_iter().for_each(|(key, values)| {
+            keys.entry(key).or_default().insert(values);
+        });
+    }
+    Ok(module_rows)
+}
+
+/// Keys of 'rows_a' and 'rows_b' whose rows differ.
+fn diff_rows(rows_a: &ModuleRows,
+        rows_b: &ModuleRows) -> Vec<DiffEntry>
+{
+    let empty_keys = BTreeMap::new();
+    let empty_rows = BTreeSet::new();
+    let modules: BTreeSet<&String> = rows_a.keys().chain(rows_b.keys()).collect();
+    let mut entries: Vec<DiffEntry> = vec![];
+    for module in modules {
+        let keys_a = rows_a.get(module).unwrap_or(&empty_keys);
+        let keys_b = rows_b.get(module).unwrap_or(&empty_keys);
+        let keys: BTreeSet<&String> = keys_a.keys().chain(keys_b.keys()).collect();
+        for key in keys {
+            let a = keys_a.get(key).unwrap_or(&empty_rows);
+            let b = keys_b.get(key).unwrap_or(&empty_rows);
+            let kind = match (a.is_empty(), b.is_empty()) {
+                (false, true) => DiffKind::OnlyInA,
+                (true, false) => DiffKind::OnlyInB,
+                _ if a != b => DiffKind::Changed,
+                _ => continue,
+            };
+            entries.push(DiffEntry {
+                module: module.clone(),
+                key: key.clone(),
+                kind,
+                a: a.iter().cloned().collect(),
+                b: b.iter().cloned().collect(),
+            });
+        }
+    }
+    entries
+}
+
+/// Write 'device_diff' into the CSV report 'path', the values of the
+/// many rows of a key being separated by EXPECTED_SEPARATOR.
+fn write_diff(path: &Path,
+        device_diff: &DeviceDiff,
+        delimiter: char) -> Result<(), Error>
+{
+    let mut csv_writer = CsvWriter::create(path,
+            &["module", "key", "status", "a_values", "b_values"],
+            delimiter
+    )?;
+    let join_rows = |rows: &[Vec<String>]| -> String {
+        rows.iter()
+                .map(|x| x.join(" "))
+                .collect::<Vec<String>>()
+                .join(EXPECTED_SEPARATOR)
+    };
+    for entry in device_diff.entries.iter() {
+        csv_writer.write_record(&[
+            entry.module.clone(),
+            entry.key.clone(),
+            entry.kind.to_string(),
+            join_rows(&entry.a),
+            join_rows(&entry.b),
+        ])?;
+    }
+    csv_writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::androidparser::AndroidParserBuilder;
+
+    fn module_rows(rows: &[(&str, &str, &[&str])]) -> ModuleRows {
+        let mut module_rows: ModuleRows = BTreeMap::new();
+        rows.iter().for_each(|(module, key, values)| {
+            module_rows.entry(module.to_string()).or_default()
+                    .entry(key.to_string()).or_default()
+                    .insert(values.iter().map(|x| x.to_string()).collect());
+        });
+        module_rows
+    }
+
+    #[test]
+    fn diff_rows_identical() {
+        let rows = module_rows(&[("getprop", "ro.debuggable", &["0"]), ("mount", "/system", &["ext4", "ro"])]);
+        assert!(diff_rows(&rows, &rows).is_empty());
+        assert!(diff_rows(&BTreeMap::new(), &BTreeMap::new()).is_empty());
+    }
+
+    #[test]
+    fn diff_rows_both_ways() {
+        let rows_a = module_rows(&[
+            ("getprop", "ro.debuggable", &["0"]),
+            ("getprop", "ro.secure", &["1"]),
+            ("getprop", "ro.build.tags", &["release-keys"]),
+        ]);
+        let rows_b = module_rows(&[
+            ("getprop", "ro.debuggable", &["1"]),
+            ("getprop", "ro.build.tags", &["release-keys"]),
+            ("binaries", "su", &["0123"]),
+        ]);
+        let entries = diff_rows(&rows_a, &rows_b);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0], DiffEntry {
+            module: String::from("binaries"),
+            key: String::from("su"),
+            kind: DiffKind::OnlyInB,
+            a: vec![],
+            b: vec![vec![String::from("0123")]],
+        });
+        assert_eq!((entries[1].key.as_str(), entries[1].kind), ("ro.debuggable", DiffKind::Changed));
+        assert_eq!(entries[1].a, vec![vec![String::from("0")]]);
+        assert_eq!(entries[1].b, vec![vec![String::from("1")]]);
+        assert_eq!((entries[2].key.as_str(), entries[2].kind), ("ro.secure", DiffKind::OnlyInA));
+        assert!(entries[2].b.is_empty());
+        let reversed = diff_rows(&rows_b, &rows_a);
+        assert_eq!(reversed.iter().map(|x| x.kind).collect::<Vec<DiffKind>>(),
+                vec![DiffKind::OnlyInA, DiffKind::Changed, DiffKind::OnlyInB]
+        );
+    }
+
+    #[test]
+    fn diff_rows_many_rows_per_key() {
+        let rows_a = module_rows(&[("ps", "zygote", &["root"]), ("ps", "zygote", &["system"])]);
+        let rows_b = module_rows(&[("ps", "zygote", &["system"]), ("ps", "zygote", &["root"])]);
+        assert!(diff_rows(&rows_a, &rows_b).is_empty());
+        let rows_b = module_rows(&[("ps", "zygote", &["root"])]);
+        let entries = diff_rows(&rows_a, &rows_b);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, DiffKind::Changed);
+        assert_eq!(entries[0].a.len(), 2);
+    }
+
+    #[test]
+    fn device_diff_counts() {
+        let rows_a = module_rows(&[("getprop", "ro.debuggable", &["0"]), ("mount", "/data", &["f2fs"])]);
+        let rows_b = module_rows(&[("getprop", "ro.debuggable", &["1"]), ("getprop", "ro.secure", &["1"])]);
+        let device_diff = DeviceDiff { entries: diff_rows(&rows_a, &rows_b), ..Default::default() };
+        assert_eq!(device_diff.count(DiffKind::Changed), 1);
+        assert_eq!(device_diff.count(DiffKind::OnlyInA), 1);
+        assert_eq!(device_diff.count(DiffKind::OnlyInB), 1);
+        assert_eq!(device_diff.modules().get("getprop").and_then(|x| x.get(&DiffKind::Changed)), Some(&1));
+        assert_eq!(device_diff.modules().get("mount").and_then(|x| x.get(&DiffKind::OnlyInA)), Some(&1));
+    }
+
+    #[test]
+    fn diff_devices_emits_the_run_of_both_dumps() {
+        let test_dir = std::env::temp_dir().join(format!("asc_diff_devices_{}", std::process::id()));
+        let (dump_a, dump_b) = (test_dir.join("january"), test_dir.join("june"));
+        std::fs::create_dir_all(&dump_a).unwrap();
+        std::fs::create_dir_all(&dump_b).unwrap();
+        std::fs::write(dump_a.join("getprop.txt"), "[ro.debuggable]: [0]\n[ro.secure]: [1]\n").unwrap();
+        std::fs::write(dump_b.join("getprop.txt"), "[ro.debuggable]: [1]\n[ro.secure]: [1]\n").unwrap();
+        std::fs::write(dump_b.join("notes.md"), "not a dump").unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let android_parser = AndroidParserBuilder::new(dump_a.to_string_lossy().to_string(), dump_b.to_string_lossy().to_string())
+                .load_reference(false)
+                .embedded_rules(false)
+                .output_dir(test_dir.join("reports").to_string_lossy().to_string())
+                .observer(sender)
+                .build()
+                .unwrap();
+        let device_diff = android_parser.diff_devices().unwrap();
+        assert_eq!(device_diff.device_a, dump_a.to_string_lossy());
+        assert_eq!(device_diff.entries.len(), 1);
+        assert_eq!((device_diff.entries[0].key.as_str(), device_diff.entries[0].kind), ("ro.debuggable", DiffKind::Changed));
+        assert!(android_parser.report_dir().join("reported_diff.csv").is_file());
+
+        let events: Vec<Event> = receiver.try_iter().collect();
+        assert!(matches!(events.first(), Some(Event::RunStarted { phase: RunPhase::Diff, devices: 2, files: 3 })));
+        assert_eq!(events.iter().filter(|x| matches!(x, Event::FileParsed { .. })).count(), 2);
+        assert_eq!(events.iter().filter(|x| matches!(x, Event::FileSkipped { .. })).count(), 1);
+        assert!(!events.iter().any(|x| matches!(x, Event::Error { .. })));
+        match events.last() {
+            Some(Event::RunFinished { phase: RunPhase::Diff, summary }) => {
+                assert_eq!(summary.devices, 2);
+                assert_eq!(summary.files, 2);
+            },
+            _ => panic!("the diff run is not finished"),
+        };
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    /// A saved reference 'path' of one 'getprop' row, built with the 'normalizers' fingerprint.
+    fn saved_reference(path: &Path,
+            debuggable: &str,
+            normalizers: &str)
+    {
+        let connx = sqlite::Connection::open_thread_safe(path).unwrap();
+        connx.execute(format!("CREATE TABLE getprop (key TEXT, value TEXT); INSERT INTO getprop VALUES ('ro.debuggable', '{debuggable}')")).unwrap();
+        reference::write_metadata(&connx, &ReferenceMetadata { normalizers: normalizers.to_string(), ..Default::default() }).unwrap();
+    }
+
+    #[test]
+    fn diff_references_refuses_other_normalizers() {
+        let test_dir = std::env::temp_dir().join(format!("asc_diff_references_{}", std::process::id()));
+        let report_dir = test_dir.join("reports");
+        std::fs::create_dir_all(&report_dir).unwrap();
+        let (old, new, legacy, other) = (test_dir.join("old.sqlite"), test_dir.join("new.sqlite"), test_dir.join("legacy.sqlite"), test_dir.join("other.sqlite"));
+        saved_reference(&old, "0", "1111");
+        saved_reference(&new, "1", "1111");
+        saved_reference(&legacy, "1", "");
+        saved_reference(&other, "1", "2222");
+
+        let firmware_diff = diff_references(&old, &new, &report_dir, ';').unwrap();
+        assert_eq!(firmware_diff.entries.len(), 1);
+        assert_eq!((firmware_diff.entries[0].key.as_str(), firmware_diff.entries[0].kind), ("ro.debuggable", DiffKind::Changed));
+        assert!(report_dir.join("reported_firmware_diff.csv").is_file());
+        assert!(diff_references(&old, &legacy, &report_dir, ';').is_ok());
+        let result = diff_references(&old, &other, &report_dir, ';');
+        assert!(matches!(result, Err(AndroidParserError::Io(err)) if err.kind() == ErrorKind::InvalidInput));
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+}
@@ -0,0 +1,86 @@
+//! Errors
+//!
+//! Every failure of the AndroidParser, whether it aborts a run or is
+//! collected per file into the errors report.
+
+use std::{fmt, io};
+
+/// Error of the AndroidParser.
+#[derive(Debug)]
+pub enum AndroidParserError {
+    /// Reading a dump file, writing a report...
+    Io(io::Error),
+    /// A dump file or a path which cannot be understood.
+    Parse {
+        path: String,
+        message: String,
+    },
+    /// Building or querying a reference.
+    Sqlite(sqlite::Error),
+    /// Compiling YARA rules or scanning with them.
+    Yara(yara::Error),
+}
+
+impl AndroidParserError {
+    pub fn parse(path: &str,
+            message: &str) -> Self
+    {
+        AndroidParserError::Parse { path: path.to_string(), message: message.to_string() }
+    }
+
+    /// Short name of the error kind, as written into the errors report.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AndroidParserError::Io(_) => "io",
+            AndroidParserError::Parse { .. } => "parse",
+            AndroidParserError::Sqlite(_) => "sqlite",
+            AndroidParserError::Yara(_) => "yara",
+        }
+    }
+}
+
+impl fmt::Display for AndroidParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AndroidParserError::Io(err) => write!(f, "{err}"),
+            AndroidParserError::Parse { path, message } => write!(f, "{path} => {message}"),
+            AndroidParserError::Sqlite(err) => write!(f, "SQLite => {err}"),
+            AndroidParserError::Yara(err) => write!(f, "YARA => {err}"),
+        }
+    }
+}
+
+impl std::error::Error for AndroidParserError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AndroidParserError::Io(err) => Some(err),
+            AndroidParserError::Parse { .. } => None,
+            AndroidParserError::Sqlite(err) => Some(err),
+            AndroidParserError::Yara(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for AndroidParserError {
+    fn from(err: io::Error) -> Self {
+        AndroidParserError::Io(err)
+    }
+}
+
+impl From<sqlite::Error> for AndroidParserError {
+    fn from(err: sqlite::Error) -> Self {
+        AndroidParserError::Sqlite(err)
+    }
+}
+
+impl From<yara::Error> for AndroidParserError {
+    fn from(err: yara::Error) -> Self {
+        AndroidParserError::Yara(err)
+    }
+}
+
+impl From<yara::YaraError> for AndroidParserError {
+    fn from(err: yara::YaraError) -> Self {
+        AndroidParserError::Yara(yara::Error::from(err))
+    }
+}
@@ -0,0 +1,140 @@
+//! Events
+//!
+//! What 'go_ref()', 'go_parse()' and 'diff_devices()' are doing, emitted live to every
+//! observer: progress, findings and errors. The file reports are written
+//! by one of these observers.
+
+use std::{path::PathBuf, sync::mpsc::Sender};
+
+use super::{DeviceIdentity, Finding, ReferenceMatch, RunSummary};
+
+/// Phase of a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunPhase {
+    /// 'go_ref()', building the reference.
+    Reference,
+    /// 'go_parse()', comparing the devices against the reference.
+    Analysis,
+    /// 'diff_devices()', diffing two dumps.
+    Diff,
+}
+
+/// Something done during a run.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// A run starts, over 'devices' devices holding 'files' files
+    /// (the reference counting as one device).
+    RunStarted {
+        phase: RunPhase,
+        devices: usize,
+        files: usize,
+    },
+    /// A device is about to be analyzed, against the reference 'reference'
+    /// (empty if no reference of the library matches it).
+    DeviceStarted {
+        device: String,
+        /// Folder of the device reports.
+        report_dir: PathBuf,
+        identity: DeviceIdentity,
+        reference: String,
+        reference_match: ReferenceMatch,
+        /// Number of files of the device.
+        files: usize,
+    },
+    /// The dump 'file' of 'device' is about to be compared, its findings
+    /// having the columns 'header'.
+    ModuleStarted {
+        device: String,
+        file: String,
+        header: Vec<String>,
+    },
+    /// The dump 'file' was parsed (and compared, during the analysis).
+    FileParsed {
+        device: String,
+        file: String,
+    },
+    /// The 'file' of 'device', of 'bytes' bytes, was scanned with the YARA rules.
+    FileScanned {
+        device: String,
+        file: String,
+        bytes: u64,
+    },
+    /// No module handles the 'file' of 'device'.
+    FileSkipped {
+        device: String,
+        file: String,
+    },
+    /// A finding, having 'values_count' values beside its key.
+    Finding {
+        finding: Finding,
+        values_count: usize,
+    },
+    /// A finding suppressed as known noise, for 'reason'.
+    FindingSuppressed {
+        finding: Finding,
+        reason: String,
+    },
+    /// The 'file' of 'device' could not be parsed, scanned or compared.
+    Error {
+        device: String,
+        file: String,
+        kind: &'static str,
+        message: String,
+    },
+    DeviceFinished {
+        device: String,
+    },
+    RunFinished {
+        phase: RunPhase,
+        summary: RunSummary,
+    },
+}
+
+/// Receive the events of the runs, from the worker threads.
+///
+/// # Exemple
+///
+/// ```no_run
+/// use android_sanity_checker::androidparser::{AndroidParserBuilder, Event, Observer};
+///
+/// struct FindingPrinter;
+///
+/// impl Observer for FindingPrinter {
+///     fn on_event(&self, event: &Event) {
+///         if let Event::Finding { finding, .. } = event {
+///             println!("{} {} => {}", finding.device, finding.key, finding.kind);
+///         }
+///     }
+/// }
+///
+/// let mut android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+///         .build()
+///         .unwrap();
+/// android_parser.subscribe(FindingPrinter);
+/// ```
+pub trait Observer: Send + Sync {
+    fn on_event(&self,
+            event: &Event);
+}
+
+/// Events sent through a channel, to be received by another thread.
+///
+/// # Exemple
+///
+/// ```no_run
+/// use android_sanity_checker::androidparser::{AndroidParserBuilder, Event};
+///
+/// let mut android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+///         .build()
+///         .unwrap();
+/// let (sender, receiver) = std::sync::mpsc::channel::<Event>();
+/// android_parser.subscribe(sender);
+/// std::thread::spawn(move || receiver.iter().for_each(|event| println!("{event:?}")));
+/// ```
+impl Observer for Sender<Event> {
+    fn on_event(&self,
+            event: &Event)
+    {
+        let _ = self.send(event.clone());
+    }
+}
@@ -0,0 +1,157 @@
+//! Findings
+//!
+//! Every difference between an analyzed device and its reference,
+//! and every YARA match, as a structured finding serialized into
+//! the JSON Lines report (one finding per line).
+
+use std::{collections::BTreeMap, fmt};
+use serde::{Deserialize, Serialize};
+use yara::{MetadataValue, Rule};
+
+use super::{Severity, ValueFrequency};
+
+/// Separator between the values of many reference rows sharing the same key.
+pub const EXPECTED_SEPARATOR: &str = "|";
+
+/// How an entry of the device differs from the reference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// The key is not in the reference.
+    Added,
+    /// The key is in the reference, with other value(s).
+    Changed,
+    /// The key of the reference is not on the device.
+    Removed,
+    /// A file of the device matched YARA rules.
+    YaraMatch,
+    /// No reference of the library matches the device.
+    NoReference,
+    /// The value is in the baseline reference, but was seen on few of its dumps.
+    Rare,
+}
+
+impl FindingKind {
+    /// 'Changed' if the key had value(s) in the reference, 'Added' otherwise.
+    pub fn from_expected<T>(expected: &[T]) -> Self {
+        match expected.is_empty() {
+            true => FindingKind::Added,
+            false => FindingKind::Changed,
+        }
+    }
+}
+
+impl fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FindingKind::Added => write!(f, "added"),
+            FindingKind::Changed => write!(f, "changed"),
+            FindingKind::Removed => write!(f, "removed"),
+            FindingKind::YaraMatch => write!(f, "yara_match"),
+            FindingKind::NoReference => write!(f, "no_reference"),
+            FindingKind::Rare => write!(f, "rare"),
+        }
+    }
+}
+
+/// A YARA rule matching a finding, with its metadata.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct YaraRuleMatch {
+    pub rule: String,
+    pub namespace: String,
+    pub tags: Vec<String>,
+    pub metadata: BTreeMap<String, serde_json::Value>,
+}
+
+impl YaraRuleMatch {
+    pub(crate) fn from_rule(rule: &Rule) -> Self {
+        let mut metadata: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+        rule.metadatas.iter().for_each(|x| {
+            let value = match &x.value {
+                MetadataValue::Integer(y) => serde_json::Value::from(*y),
+                MetadataValue::String(y) => serde_json::Value::from(*y),
+                MetadataValue::Boolean(y) => serde_json::Value::from(*y),
+            };
+            metadata.insert(x.identifier.to_string(), value);
+        });
+        Self {
+            rule: rule.identifier.to_string(),
+            namespace: rule.namespace.to_string(),
+            tags: rule.tags.iter().map(|x| x.to_string()).collect(),
+            metadata,
+        }
+    }
+
+    /// Metadata values whose identifier contains one of 'identifiers', as '[value]...'.
+    pub fn metadata_str(&self,
+            identifiers: &[&str]) -> String
+    {
+        self.metadata.iter()
+                .filter(|(key, _)| identifiers.iter().any(|x| key.contains(x)))
+                .map(|(_, value)| match value {
+                    serde_json::Value::String(x) => format!("[{x}]"),
+                    x => format!("[{x}]"),
+                })
+                .collect()
+    }
+}
+
+/// A difference between a device and its reference.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    /// Root directory of the device.
+    pub device: String,
+    /// Module (parsed dump file) of the finding: 'getprop', 'mount', 'binaries'...
+    pub module: String,
+    /// File of the device the finding comes from.
+    pub file: String,
+    pub kind: FindingKind,
+    pub key: String,
+    /// Values of the device beside the key, empty if removed.
+    pub observed: Vec<String>,
+    /// Values of every reference row with the same key.
+    pub expected: Vec<Vec<String>>,
+    pub yara_matches: Vec<YaraRuleMatch>,
+    /// Severity given by the 'SeverityModel' of the run.
+    #[serde(default)]
+    pub severity: Severity,
+    /// How often the observed values (the expected ones if removed) were
+    /// seen in the reference, None unless it is a baseline of many dumps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<ValueFrequency>,
+}
+
+impl Finding {
+    /// Matching YARA rules as '[rule1][rule2]'.
+    pub fn yara_rules_names(&self) -> String {
+        self.yara_matches.iter()
+                .map(|x| format!("[{}]", x.rule))
+                .collect()
+    }
+
+    /// Fields of the finding for the module CSV reports: file, key, the
+    /// 'values_count' observed values, the 'values_count' expected values
+    /// (many reference rows being separated by EXPECTED_SEPARATOR), status,
+    /// yara_match, yara_rulename, severity and frequency.
+    pub fn to_csv_record(&self,
+            values_count: usize) -> Vec<String>
+    {
+        let mut record: Vec<String> = vec![self.file.clone(), self.key.clone()];
+        (0..values_count).for_each(|index| {
+            record.push(self.observed.get(index).cloned().unwrap_or_default());
+        });
+        (0..values_count).for_each(|index| {
+            record.push(self.expected.iter()
+                    .map(|x| x.get(index).map_or("", |y| y.as_str()))
+                    .collect::<Vec<&str>>()
+                    .join(EXPECTED_SEPARATOR)
+            );
+        });
+        record.push(self.kind.to_string());
+        record.push((!self.yara_matches.is_empty()).to_string());
+        record.push(self.yara_rules_names());
+        record.push(self.severity.to_string());
+        record.push(self.frequency.map_or(String::new(), |x| x.to_string()));
+        record
+    }
+}
@@ -3,137 +3,473 @@
 //! AndroidParser is a a crate getting a path filename and
 //! is able to run a referencing to a volatile DB or
 //! comparing against this DB.
+//! The reference DB can be saved to a file and loaded back later,
+//! or exported to JSON to be edited and imported back.
 
-use std::{io::{Cursor, BufRead, BufReader, BufWriter, Error, ErrorKind, Write}, fs::{File, OpenOptions}, path::Path, sync::{Arc, Mutex}, env::current_dir};
+use std::{io::{BufReader, Error, ErrorKind}, fs::{File, OpenOptions}, path::{Path, PathBuf}, sync::{Arc, LazyLock, Mutex}, collections::{HashMap, HashSet}};
 use rayon::ThreadPool;
 use regex::Regex;
-use sqlite::{ConnectionThreadSafe, State};
+use sqlite::{ConnectionThreadSafe, State, Statement};
 use yara::{Rules, Scanner};
-use sha256;
-use console::style;
+
+mod baseline;
+mod builder;
+mod builtin_parsers;
+mod declarative_parsers;
+mod device;
+mod diff;
+mod error;
+mod events;
+mod findings;
+mod normalize;
+mod outliers;
+mod parser;
+mod records;
+mod reference;
+mod reference_json;
+mod report;
+mod run;
+mod severity;
+mod summary;
+mod suppression;
+use baseline::RowCount;
+use device::{ComparedRows, DeviceContext};
+pub use baseline::{DEFAULT_RARE_THRESHOLD, FrequencyClass, ValueFrequency};
+pub use builder::{AndroidParserBuilder, DEFAULT_YARA_TIMEOUT};
+pub use declarative_parsers::{load_parser_definitions, DeclarativeParser, ParserDefinition};
+pub use device::DeviceIdentity;
+pub use diff::{diff_references, DeviceDiff, DiffEntry, DiffKind};
+pub use error::AndroidParserError;
+pub use events::{Event, Observer, RunPhase};
+pub use findings::{Finding, FindingKind, YaraRuleMatch};
+pub use normalize::{Normalization, NormalizerDefinition, Normalizers};
+pub use outliers::Outlier;
+pub use parser::{dump_name, ParsedEntries, Parser, ParserRegistry};
+pub use records::{parse_dump, parse_dump_file, BinaryEntry, DiskUsageEntry, DumpRecords, IdEntry, MountEntry, PackageListEntry, PermissionDefinition, ProcessEntry, Property, ServiceEntry, Setting};
+pub use reference::{ReferenceLibrary, ReferenceMatch, ReferenceMetadata};
+pub use reference_json::{is_reference_export, ModuleDocument, ReferenceDocument, REFERENCE_INDEX_FILE, WILDCARD};
+use report::FileReporter;
+pub use report::{create_run_dir, CsvWriter, DEFAULT_CSV_DELIMITER, ReportFormat};
+use run::RunStats;
+pub use run::RunSummary;
+pub use severity::{Severity, SeverityModel, SeverityRule, SeverityWeights, Verdict, VerdictThresholds};
+pub use summary::{rank_devices, DeviceSummary, ModuleCounts};
+pub use suppression::{SuppressionRule, Suppressions};
+
+/// Files under '/system/bin', whose ELF YARA matches are expected.
+static SYSTEM_BIN_PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\\/]system[\\/]bin.*$").unwrap()
+});
 
 pub struct AndroidParser {
     path_analyze: String,
     path_reference: String,
-    yara_report_file_mutexed: Arc<Mutex<BufWriter<File>>>,
-    binaries_report_file_mutexed: Arc<Mutex<BufWriter<File>>>,
+    /// Observer writing the reports into the folder of the run.
+    file_reporter: Arc<FileReporter>,
+    /// Other observers of the events.
+    observers: Vec<Arc<dyn Observer>>,
+    run_stats: RunStats,
     yara_rules: Vec<Rules>,
+    /// Timeout of each YARA scan, in seconds.
+    yara_timeout: i32,
     connx: Arc<ConnectionThreadSafe>,
+    reference_library: Option<ReferenceLibrary>,
+    /// Parsers of the dump files, shared by 'go_ref()' and 'go_parse()'.
+    parser_registry: ParserRegistry,
+    /// Normalizers of the parsed values, before the reference DB and the comparison.
+    normalizers: Normalizers,
+    /// Severity of the findings, and risk score of the devices.
+    severity_model: Arc<SeverityModel>,
+    /// Known noise, not reported as findings.
+    suppressions: Suppressions,
+    /// Share of the dumps of a baseline reference up to which a matching value is rare.
+    rare_threshold: f64,
+    /// Names of the parsers (and 'yara' for the scan of the other files) run, None for all.
+    enabled_modules: Option<HashSet<String>>,
     thread_pool: ThreadPool
 }
 
 impl AndroidParser {
     /// Create a new AndroidParser. Taking a file path, verifying it and returning a valid AndroidParser.
+    /// 'path_reference' is either the reference directory (White ADB Dump),
+    /// a reference previously saved with 'save_reference()', or a directory
+    /// of saved references (a library).
+    /// Reports are written into a timestamped folder created inside 'path_output'
+    /// (the current directory if None), which must not be inside 'path_reference'
+    /// nor 'path_analyze': the evidence directories are never written to.
     /// 
     /// # Exemple
     /// 
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::AndroidParser;
+    ///
+    /// let android_parser = AndroidParser::new(String::from("C:/Smartphones/S21_white"),
+    ///         String::from("C:/Smartphones/Analyze"),
+    ///         None,
+    ///         Some(String::from("C:/Reports"))
+    /// ).unwrap();
     /// ```
-    /// let file_path = path.Path::new("C:/Smartphones/S21/infos/getprops.txt");
-    /// 
-    /// let android_parser = AndroidParser::new(file_path);
     pub fn new(path_reference: String,
             path_analyze: String,
-            path_yara: Option<String>) -> Result<Self, Error>
+            path_yara: Option<String>,
+            path_output: Option<String>) -> Result<Self, AndroidParserError>
     {
-        match Path::new(&path_reference).try_exists().unwrap() & Path::new(&path_analyze).try_exists().unwrap() {
-            true => {
-                let yara_default_rules = Rules::load_from_stream(
-                        Cursor::new(
-                        include_bytes!("../../resources/yara_precompiled.yara")
-                )
-                ).unwrap();
-                let mut yara_rules: Vec<Rules> = vec![yara_default_rules];
-                if let Some(yara_path) = path_yara {
-                    yara_rules.push(yara_customizer::yara_rules_ingester(yara_customizer::yara_rules_finder(Path::new(&yara_path))));
-                }
+        let mut builder = AndroidParserBuilder::new(path_reference, path_analyze);
+        if let Some(yara_path) = path_yara {
+            builder = builder.yara_rules(yara_path);
+        }
+        if let Some(output_path) = path_output {
+            builder = builder.output_dir(output_path);
+        }
+        builder.build()
+    }
 
-                let connx: Arc<ConnectionThreadSafe> = match sqlite::Connection::open_thread_safe(":memory:") {
-                    Ok(x) => Arc::new(x),
-                    Err(err) => panic!("{err}"),
-                };
-                
-                let yara_report_file_mutexed: Arc<Mutex<BufWriter<File>>> = match OpenOptions::new()
-                        .read(true)
-                        .write(true)
-                        .append(true)
-                        .create(true)
-                        .open(Path::new(format!("{}/reported_yara_matches.csv",
-                                current_dir().unwrap().to_str().unwrap()).as_str())
-                        )
-                {
-                    Ok(file_handler) => Arc::new(Mutex::new(BufWriter::new(file_handler))),
-                    Err(err) => panic!("{}", err.to_string()),
-                };
-                let mut guarded_writer = yara_report_file_mutexed.lock().unwrap();
-                let _ = guarded_writer.write_all("filename;yara_rulename;yara_rule_description\n".as_bytes());
-                drop(guarded_writer);
-
-                let binaries_report_file_mutexed: Arc<Mutex<BufWriter<File>>> = match OpenOptions::new()
-                        .read(true)
-                        .write(true)
-                        .append(true)
-                        .create(true)
-                        .open(Path::new(format!("{}/reported_binaries.csv",
-                                current_dir().unwrap().to_str().unwrap()).as_str())
-                        )
-                {
-                    Ok(file_handler) => Arc::new(Mutex::new(BufWriter::new(file_handler))),
-                    Err(err) => panic!("{}", err.to_string()),
-                };
-                let mut guarded_writer = binaries_report_file_mutexed.lock().unwrap();
-                let _ = guarded_writer.write_all("filename;sha256_sum;yara_match;yara_rulename\n".as_bytes());
-                drop(guarded_writer);
+    /// Compare every device found under 'path_analyze' against the reference,
+    /// or against the library reference matching its build fingerprint or product model,
+    /// scanning with the YARA rules what differs from it.
+    /// Devices without matching reference are reported into 'reported_devices.csv'.
+    /// Each finding is 'added', 'changed' or 'removed', with the expected reference value(s).
+    /// Every finding is also written into 'reported_findings.jsonl', one JSON object per line.
+    /// A file which cannot be parsed or scanned is written into 'reported_errors.csv',
+    /// and the analysis goes on. Only an unreadable analysis directory or reports
+    /// which cannot be written stop the run.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::AndroidParserBuilder;
+    ///
+    /// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+    ///         .build()
+    ///         .unwrap();
+    /// android_parser.go_ref().unwrap();
+    /// let summary = android_parser.go_parse().unwrap();
+    /// println!("{} finding(s), {} error(s)", summary.findings, summary.errors);
+    /// ```
+    pub fn go_parse(&self) -> Result<RunSummary, AndroidParserError> {
+        self.run_stats.reset();
+        Path::new(&self.path_analyze).read_dir()?;
+        let device_roots: Vec<(PathBuf, usize)> = device::find_device_roots(Path::new(&self.path_analyze)).into_iter()
+                .map(|x| {
+                    let files = device::count_files(&x);
+                    (x, files)
+                })
+                .collect();
+        self.emit(Event::RunStarted {
+            phase: RunPhase::Analysis,
+            devices: device_roots.len(),
+            files: device_roots.iter().map(|x| x.1).sum(),
+        });
+        let mut loaded_references: HashMap<PathBuf, Arc<ConnectionThreadSafe>> = HashMap::new();
+        device_roots.into_iter().for_each(|(device_root, files)| {
+            self.run_stats.add_device();
+            let identity = self.read_device_identity(&device_root).unwrap_or_default();
+            let (connx, reference_name, reference_match) = match &self.reference_library {
+                Some(reference_library) => match reference_library.select(&identity) {
+                    (Some((reference_path, _)), reference_match) => {
+                        let (connx, reference_match) = match loaded_references.get(reference_path) {
+                            Some(x) => (Some(x.clone()), reference_match),
+                            None => match sqlite::Connection::open_thread_safe(":memory:")
+                                    .map_err(AndroidParserError::from)
+                                    .and_then(|x| reference::load_reference(&x, reference_path).map(|_| x).map_err(AndroidParserError::from))
+                            {
+                                Ok(x) => {
+                                    let x = Arc::new(x);
+                                    loaded_references.insert(reference_path.clone(), x.clone());
+                                    (Some(x), reference_match)
+                                },
+                                Err(err) => {
+                                    self.report_error(device_root.to_str().unwrap_or_default(),
+                                            reference_path.to_str().unwrap_or_default(),
+                                            &err
+                                    );
+                                    (None, ReferenceMatch::LoadFailed)
+                                },
+                            },
+                        };
+                        (connx, reference_path.to_str().unwrap_or_default().to_string(), reference_match)
+                    },
+                    (None, reference_match) => (None, String::new(), reference_match),
+                },
+                None => (Some(self.connx.clone()), self.path_reference.clone(), ReferenceMatch::Single),
+            };
+            let report_dir = match device_root.strip_prefix(&self.path_analyze) {
+                Ok(x) if !x.as_os_str().is_empty() => self.report_dir().join(x),
+                _ => self.report_dir().join(device_root.file_name().unwrap_or_default()),
+            };
+            self.emit(Event::DeviceStarted {
+                device: device_root.to_string_lossy().to_string(),
+                report_dir,
+                identity: identity.clone(),
+                reference: reference_name,
+                reference_match,
+                files,
+            });
+            match connx {
+                Some(connx) => {
+                    let baseline_devices = baseline::baseline_devices(&connx);
+                    let wildcards = reference_json::load_wildcards(&connx);
+                    let device = DeviceContext { root: device_root.clone(), connx, seen_binaries: Mutex::new(HashSet::new()), baseline_devices, wildcards };
+                    self.parse_path(String::from(device_root.to_str().unwrap_or_default()), &device);
+                    self.report_missing_binaries(&device);
+                },
+                None if reference_match == ReferenceMatch::LoadFailed => (),
+                None => {
+                    self.report_finding(Finding {
+                        device: device_root.to_string_lossy().to_string(),
+                        module: String::from("reference"),
+                        file: String::new(),
+                        kind: FindingKind::NoReference,
+                        key: String::from("ro.build.fingerprint"),
+                        observed: vec![identity.build_fingerprint.clone(), identity.product_model.clone()],
+                        expected: vec![],
+                        yara_matches: vec![],
+                        severity: Severity::default(),
+                        frequency: None,
+                    }, 0);
+                },
+            };
+            self.emit(Event::DeviceFinished { device: device_root.to_string_lossy().to_string() });
+        });
+        self.flush_reports()?;
+        self.emit(Event::RunFinished { phase: RunPhase::Analysis, summary: self.run_stats.summary() });
+        Ok(self.run_stats.summary())
+    }
 
-                let thread_pool = rayon::ThreadPoolBuilder::new().build().unwrap();
+    /// Parse the dump files of 'path_reference' into the reference of the analysis.
+    /// Nothing is done if the reference was loaded from a saved file or is a library.
+    /// A file which cannot be referenced is written into 'reported_errors.csv',
+    /// and the referencing goes on.
+    /// A reference directory holding many dumps (device roots) is a baseline:
+    /// every distinct row of the dumps is referenced, with the number of dumps
+    /// it was seen on, so the analysis tells rare values from common ones.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::AndroidParserBuilder;
+    ///
+    /// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+    ///         .build()
+    ///         .unwrap();
+    /// let summary = android_parser.go_ref().unwrap();
+    /// println!("{} file(s) referenced, {} error(s)", summary.files, summary.errors);
+    /// ```
+    pub fn go_ref(&self) -> Result<RunSummary, AndroidParserError> {
+        self.run_stats.reset();
+        if self.is_reference_loaded() || self.is_reference_library() {
+            return Ok(self.run_stats.summary());
+        }
+        self.reference_dumps(&self.path_reference)
+    }
 
-                return Ok(Self {path_reference, path_analyze, yara_report_file_mutexed, binaries_report_file_mutexed, yara_rules, connx, thread_pool})
-            },
-            false => return Err(Error::new(
-                    ErrorKind::NotFound,
-                    "File not Found")
-            ),
-        };
+    /// Reference every device of the analysis directory as a fleet baseline,
+    /// in place of 'go_ref()': 'go_parse()' then reports as rare the values
+    /// held by only one or a small minority of the devices (see 'rare_threshold()'
+    /// of the builder), gathered by key into 'reported_outliers.csv'.
+    /// No white reference is needed, the reference path being ignored.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::AndroidParserBuilder;
+    ///
+    /// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/Fleet"), String::from("C:/Smartphones/Fleet"))
+    ///         .output_dir(String::from("C:/Reports"))
+    ///         .build()
+    ///         .unwrap();
+    /// android_parser.go_fleet().unwrap();
+    /// android_parser.go_parse().unwrap();
+    /// for outlier in android_parser.outliers() {
+    ///     println!("{} {} => {:?}", outlier.module, outlier.key, outlier.devices);
+    /// }
+    /// ```
+    pub fn go_fleet(&self) -> Result<RunSummary, AndroidParserError> {
+        self.run_stats.reset();
+        if self.is_reference_loaded() || self.is_reference_library() {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                    "The fleet baseline cannot replace a saved reference nor a library").into()
+            );
+        }
+        reference::clear_reference(&self.connx)?;
+        self.reference_dumps(&self.path_analyze)
+    }
+
+    /// Reference every dump (device root) under 'path', as a baseline if many.
+    fn reference_dumps(&self,
+            path: &str) -> Result<RunSummary, AndroidParserError>
+    {
+        Path::new(path).read_dir()?;
+        let baseline_roots = device::find_device_roots(Path::new(path));
+        self.emit(Event::RunStarted {
+            phase: RunPhase::Reference,
+            devices: baseline_roots.len(),
+            files: device::count_files(Path::new(path)),
+        });
+        baseline::create_baseline_tables(&self.connx)?;
+        for baseline_root in baseline_roots.iter() {
+            self.run_stats.add_device();
+            let baseline_root = baseline_root.to_string_lossy().to_string();
+            let baseline_device = baseline::add_baseline_device(&self.connx, &baseline_root)?;
+            self.parse_ref(&baseline_root,
+                    baseline_device,
+                    baseline_root.clone()
+            );
+        }
+        self.flush_reports()?;
+        self.emit(Event::RunFinished { phase: RunPhase::Reference, summary: self.run_stats.summary() });
+        Ok(self.run_stats.summary())
     }
 
-    /// Using the 'self.path_filename', this method will select a parser to
-    /// find the way to extract parameters and compare it with the given Sqlite DB 'connx'.
-    /// if it is not as the Ref, will use the given Yara scanner vector 'yara_checker'
-    /// to check if there is malicious entries.
+    /// Replace the internal thread pool by a new one of 'threads' workers.
+    /// Both 'go_ref()' and 'go_parse()' will then run on this pool.
     /// 
     /// # Exemple
     /// 
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::AndroidParser;
+    ///
+    /// let mut android_parser = AndroidParser::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"), None, None).unwrap();
+    /// android_parser.set_threads(4).unwrap();
     /// ```
-    /// let connection = match sqlite::Connection::open_thread_safe(":memory:") {
-    ///     Ok(x) => Arc::new(x),
-    ///     Err(err) => panic!("{err}"),
-    /// };
-    /// 
-    /// let yara_rules = Rules::load_from_stream(Cursor::new(include_bytes!("../resources/yara_precompiled.yara"))).unwrap();
-    /// let yara_scanners = vec![yara_rules.scanner().unwrap()];
-    /// 
-    /// android_parser.go_parse(connection.clone(), yara_scanners);
-    pub fn go_parse(&self) {
-        self.parse_path(self.path_analyze.clone());
+    pub fn set_threads(&mut self,
+            threads: usize) -> Result<(), AndroidParserError>
+    {
+        match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(x) => {
+                self.thread_pool = x;
+                Ok(())
+            },
+            Err(err) => Err(Error::other(err.to_string()).into()),
+        }
     }
 
-    /// Using 'self.path_filename', and the 'connx' given, will parse the file to extract datas from concerned
-    /// file, and organize them into de DB 'connx'.
+    /// Use 'delimiter' instead of ';' between the fields of the CSV reports.
+    /// The global reports are created again with their header.
     /// 
-    /// # Exemples
+    /// # Exemple
     /// 
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::AndroidParser;
+    ///
+    /// let mut android_parser = AndroidParser::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"), None, None).unwrap();
+    /// android_parser.set_csv_delimiter(',').unwrap();
+    /// ```
+    pub fn set_csv_delimiter(&mut self,
+            delimiter: char) -> Result<(), AndroidParserError>
+    {
+        self.file_reporter = Arc::new(FileReporter::new(self.file_reporter.report_dir().to_path_buf(),
+                delimiter,
+                self.file_reporter.report_formats(),
+                self.file_reporter.severity_model()
+        )?);
+        Ok(())
+    }
+
+    /// Folder of the run, where every report is written.
+    pub fn report_dir(&self) -> &Path {
+        self.file_reporter.report_dir()
+    }
+
+    /// Summaries of the devices of the last 'go_parse()', from the most to the
+    /// least deviating one, as written into 'reported_fleet.csv'.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::AndroidParserBuilder;
+    ///
+    /// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+    ///         .build()
+    ///         .unwrap();
+    /// android_parser.go_parse().unwrap();
+    /// for device_summary in android_parser.device_summaries() {
+    ///     println!("{} => {} deviation(s)", device_summary.device, device_summary.deviations());
+    /// }
     /// ```
-    /// let connection = match sqlite::Connection::open_thread_safe(":memory:") {
-    ///     Ok(x) => Arc::new(x),
-    ///     Err(err) => panic!("{err}"),
-    /// };
-    /// let android_parser = match AndroidParser::new(path.Path::new("C:/Smartphones/S21/infos/getprops.txt")) {
-    ///     Ok(x) => {
-    ///         x.go_ref(connection);
-    ///     },
-    ///     Err(err) => eprintln!("{err}"),
-    /// };
-    pub fn go_ref(&self) {
-        self.parse_ref(self.path_reference.clone());
+    pub fn device_summaries(&self) -> Vec<DeviceSummary> {
+        self.file_reporter.fleet_summaries()
+    }
+
+    /// Rare values of the last 'go_parse()' against a baseline, from the rarest,
+    /// with the devices holding them, as written into 'reported_outliers.csv'.
+    pub fn outliers(&self) -> Vec<Outlier> {
+        self.file_reporter.outliers()
+    }
+
+    /// Send every event of the next runs to 'observer', besides the file reports.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::{AndroidParserBuilder, Event};
+    ///
+    /// let mut android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+    ///         .build()
+    ///         .unwrap();
+    /// let (sender, receiver) = std::sync::mpsc::channel::<Event>();
+    /// android_parser.subscribe(sender);
+    /// android_parser.go_parse().unwrap();
+    /// println!("{} event(s)", receiver.try_iter().count());
+    /// ```
+    pub fn subscribe<O: Observer + 'static>(&mut self,
+            observer: O)
+    {
+        self.observers.push(Arc::new(observer));
+    }
+
+    /// Add 'parser' to the parsers of the reference and of the analyzed devices.
+    /// It takes precedence over the built-in parsers handling the same files.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use android_sanity_checker::androidparser::{load_parser_definitions, AndroidParserBuilder};
+    ///
+    /// let mut android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+    ///         .build()
+    ///         .unwrap();
+    /// for parser in load_parser_definitions(Path::new("C:/Smartphones/parsers.toml")).unwrap() {
+    ///     android_parser.register_parser(parser);
+    /// }
+    /// android_parser.go_ref().unwrap();
+    /// ```
+    pub fn register_parser<P: Parser + 'static>(&mut self,
+            parser: P)
+    {
+        self.parser_registry.register(parser);
+    }
+
+    /// Register every parser declared in the TOML file 'path' (see 'load_parser_definitions()').
+    /// Returns the number of registered parsers. Registered after the build,
+    /// they cannot be enabled with 'modules()': prefer 'AndroidParserBuilder::parsers_file()'.
+    pub fn register_parsers_file(&mut self,
+            path: &Path) -> Result<usize, AndroidParserError>
+    {
+        let parsers = load_parser_definitions(path)?;
+        let count = parsers.len();
+        parsers.into_iter().for_each(|x| self.parser_registry.register(x));
+        Ok(count)
+    }
+
+    /// Parsers of the dump files.
+    pub fn parser_registry(&self) -> &ParserRegistry {
+        &self.parser_registry
+    }
+
+    /// Whether the module 'name' (a parser name, or 'yara') is run.
+    fn module_enabled(&self,
+            name: &str) -> bool
+    {
+        self.enabled_modules.as_ref().is_none_or(|x| x.contains(name))
+    }
+
+    /// The enabled parser handling the dump file 'path', if any.
+    fn find_parser(&self,
+            path: &Path) -> Option<&dyn Parser>
+    {
+        self.parser_registry.find_enabled(path, |x| self.module_enabled(x))
     }
 
     fn create_bufreader(&self,
@@ -146,231 +482,200 @@ impl AndroidParser {
                 .open(path)
         ;
         match file_handler {
-            Ok(x) => return Ok(BufReader::new(x)),
-            Err(err) => return Err(err),
-        };
-    }
-
-    fn create_bufwriter(&self, 
-            path: &str) -> Result<BufWriter<File>, Error>
-    {
-        let re = match regex::Regex::new(r"^(?P<REPORTPATH>\w:\\.*\\).*\\(?P<REPORTNAME>.*)\.txt$") {
-            Ok(x) => x,
-            Err(err) => panic!("{}", err),
-        };
-        if let Some(caps) = re.captures(path) {
-            let report_filename = format!("{}{}.csv",
-                    caps.name("REPORTPATH").map_or("".to_string(), |m| String::from(m.as_str())),
-                    caps.name("REPORTNAME").map_or("".to_string(), |m| String::from(m.as_str()))
-            );
-            let file = match OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .append(true)
-                    .create(true)
-                    .open(Path::new(report_filename.as_str()))
-            {
-                Ok(x) => x,
-                Err(err) => panic!("{}", err),
-            };
-            return Ok(BufWriter::new(file))
-        }
-        else if let Some(caps) = Regex::new(r"^(?P<REPORTPATH>\w:[\\/].*[\\/])system[\\/]bin[\\/](?P<REPORTNAME>.*)$").unwrap()
-                .captures(path)
-        {
-            let report_filename = format!("{}{}",
-                    caps.name("REPORTPATH").map_or("".to_string(), |m| String::from(m.as_str())),
-                    String::from("binaries.csv")
-            );
-            let file = match OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .append(true)
-                    .create(true)
-                    .open(Path::new(report_filename.as_str()))
-            {
-                Ok(x) => x,
-                Err(err) => panic!("{err}"),
-            };
-            return Ok(BufWriter::new(file))
-        }
-        else {
-            return Err(Error::new(ErrorKind::InvalidData,
-                    "Error occured while creating the File buffer to write report")
-            )
+            Ok(x) => Ok(BufReader::new(x)),
+            Err(err) => Err(err),
         }
     }
 
     fn parse_path(&self,
-            path: String
+            path: String,
+            device: &DeviceContext
     ) {
         let path = Path::new(path.as_str());
-        let validator_flag = match path.try_exists() {
-            Ok(x)=> x,
-            Err(err) => panic!("{} {}",
-                    style("[ERROR]").bold().dim().red(),
-                    err.to_string()),
+        let read_dir = match path.read_dir() {
+            Ok(x) => x,
+            Err(err) => {
+                self.report_error(&device.root_str(), path.to_str().unwrap_or_default(), &err.into());
+                return;
+            },
         };
-        if validator_flag && path.is_dir() == true {
-            if let Ok(read_dir) = path.read_dir() {
-                self.thread_pool.scope(|s| {
-                    read_dir.into_iter().for_each(|each_dir| {
-                        if let Ok(each_entry) = each_dir {
-                            if each_entry.file_type().unwrap().is_dir() {
-                                s.spawn(move |_| {
-                                    self.parse_path(String::from(each_entry.path().to_str().unwrap())); 
-                                });
-                            }
-                            else if each_entry.file_type().unwrap().is_file() &&
-                                    ( each_entry.path().extension().is_some_and(|x| x.to_str().unwrap() == "txt") ||
-                                    Regex::new(r"[\\/]system[\\/]bin[\\/].*$").unwrap().is_match(each_entry.path().to_str().unwrap()) )
-                            {
-                                let mut vec_yara_scanner: Vec<Scanner> = vec![];
-                                self.yara_rules.iter().for_each(|yara_rules| {
-                                    let mut yara_scanner = yara_rules.scanner().unwrap();
-                                    yara_scanner.set_timeout(10);
-                                    yara_scanner.set_flags(yara::ScanFlags::REPORT_RULES_MATCHING);
-                                    vec_yara_scanner.push(yara_scanner);
-                                });
-                                s.spawn(move |_| {
-                                    let _ = match self.create_bufreader(each_entry.path().as_path()) {
-                                        Ok(x) => self.android_file_selector(x,
-                                            String::from(each_entry.path().to_str().unwrap())
-                                        ),
-                                        Err(err) => panic!("{err}")
-                                    };
-
-                                });
-                            }
-                            else {
-                                if let Ok(Some(_)) = infer::get_from_path(each_entry.path().to_str().unwrap()) {
-                                    let mut vec_yara_scanner: Vec<Scanner> = vec![];
-                                    let mut matched_rules: (String, String, String) = (String::new(), String::new(), String::new());
-                                    self.yara_rules.iter().for_each(|yara_rules| {
-                                        let mut yara_scanner = yara_rules.scanner().unwrap();
-                                        yara_scanner.set_timeout(10);
-                                        yara_scanner.set_flags(yara::ScanFlags::REPORT_RULES_MATCHING);
-                                        vec_yara_scanner.push(yara_scanner);
-                                    });
-                                    let local_yara_report_file_mutexed = self.yara_report_file_mutexed.clone();
-                                    s.spawn( move |_| {
-                                        vec_yara_scanner.iter_mut().for_each(|yara_scanner| {
-                                            if let Ok(yara_matches) = yara_scanner.scan_file(each_entry.path()){
-                                                if !yara_matches.is_empty() {
-                                                    yara_matches.into_iter().for_each(|x| {
-                                                        if !matched_rules.0.contains(x.identifier) {
-                                                            if !( x.identifier.eq("with_sqlite") & each_entry.path().extension().is_some_and(|x| x.to_str().unwrap() == "db") ) &
-                                                                    !( (x.identifier.eq("ft_jar") | x.identifier.eq("ft_zip")) & each_entry.path().extension().is_some_and(|x| x.to_str().unwrap() == "apk") ) &
-                                                                    !( x.identifier.eq("ft_zip") & each_entry.path().extension().is_some_and(|x| x.to_str().unwrap() == "zip") ) &
-                                                                    !( x.identifier.eq("ft_gzip") & each_entry.path().extension().is_some_and(|x| x.to_str().unwrap() == "gz") ) & 
-                                                                    !( (x.identifier.eq("ft_elf") | x.identifier.eq("executable_elf32") | x.identifier.eq("executable_elf64")) & Regex::new(r"[\\/]system[\\/]bin.*$").unwrap().is_match(each_entry.path().to_str().unwrap()) ) {
-                                                                let mut tmp_desc = String::new();
-                                                                let mut tmp_ref = String::new();
-                                                                x.metadatas.iter().for_each(|y| {
-                                                                    if y.identifier.contains("desc") || 
-                                                                            y.identifier.contains("description")
-                                                                    {
-                                                                        let str = format!("{:?}", y.value);
-                                                                        tmp_desc.push_str(&format!("[{}]", &str[8..str.len()-2]));
-                                                                    }
-                                                                    if y.identifier.eq("url") ||
-                                                                            y.identifier.eq("reference")
-                                                                    {
-                                                                        let str = format!("{:?}", y.value);
-                                                                        tmp_ref.push_str(&format!("[{}]", &str[8..str.len()-2]));
-                                                                    }
-                                                                });
-                                                                if tmp_desc.is_empty() {
-                                                                    matched_rules.1.push_str("[---]");
-                                                                }
-                                                                else {
-                                                                    matched_rules.1.push_str(&tmp_desc);
-                                                                }
-                                                                if tmp_ref.is_empty() {
-                                                                    matched_rules.2.push_str("[---]");
-                                                                }
-                                                                else {
-                                                                    matched_rules.2.push_str(&tmp_ref);
-                                                                }
-                                                                matched_rules.0.push_str(format!("[{}]", x.identifier).as_str());
-                                                            }
-                                                        }
-                                                    });
-                                                }
-                                            };
-                                        });
-                                        if !matched_rules.0.is_empty() {
-                                            let mut garded_writer = local_yara_report_file_mutexed.lock().unwrap();
-                                            let _ = garded_writer.write_all(format!("{};{};{};{}\n",
-                                                    each_entry.path().to_str().unwrap(),
-                                                    matched_rules.0,
-                                                    matched_rules.1,
-                                                    matched_rules.2
-                                                ).as_bytes()
-                                            );
-                                        }
-                                    });
-                                }
-                            }
+        self.thread_pool.scope(|s| {
+            read_dir.into_iter().for_each(|each_dir| {
+                let each_entry = match each_dir {
+                    Ok(x) => x,
+                    Err(err) => {
+                        self.report_error(&device.root_str(), path.to_str().unwrap_or_default(), &err.into());
+                        return;
+                    },
+                };
+                let file_type = match each_entry.file_type() {
+                    Ok(x) => x,
+                    Err(err) => {
+                        self.report_error(&device.root_str(), each_entry.path().to_str().unwrap_or_default(), &err.into());
+                        return;
+                    },
+                };
+                if file_type.is_dir() {
+                    s.spawn(move |_| {
+                        self.parse_path(String::from(each_entry.path().to_str().unwrap_or_default()), device);
+                    });
+                }
+                else if file_type.is_file() &&
+                        self.find_parser(&each_entry.path()).is_some()
+                {
+                    s.spawn(move |_| {
+                        let file_path = String::from(each_entry.path().to_str().unwrap_or_default());
+                        match self.create_bufreader(each_entry.path().as_path())
+                                .map_err(AndroidParserError::from)
+                                .and_then(|x| self.android_file_selector(x, file_path.clone(), device))
+                        {
+                            Ok(true) => {
+                                self.run_stats.add_file();
+                                self.emit(Event::FileParsed { device: device.root_str(), file: file_path });
+                            },
+                            Ok(false) => self.emit(Event::FileSkipped { device: device.root_str(), file: file_path }),
+                            Err(err) => self.report_error(&device.root_str(), &file_path, &err),
+                        };
+                    });
+                }
+                else if self.module_enabled("yara") &&
+                        matches!(infer::get_from_path(each_entry.path()), Ok(Some(_)))
+                {
+                    s.spawn( move |_| {
+                        let each_path = each_entry.path();
+                        let extension = each_path.extension().map_or(String::new(), |x| x.to_string_lossy().to_string());
+                        let yara_matches: Vec<YaraRuleMatch> = self.collect_error(&device.root_str(),
+                                each_path.to_str().unwrap_or_default(),
+                                self.yara_scan_file(&each_path)
+                        ).into_iter().filter(|x| {
+                            !( x.rule.eq("with_sqlite") & extension.eq("db") ) &
+                                    !( (x.rule.eq("ft_jar") | x.rule.eq("ft_zip")) & extension.eq("apk") ) &
+                                    !( x.rule.eq("ft_zip") & extension.eq("zip") ) &
+                                    !( x.rule.eq("ft_gzip") & extension.eq("gz") ) &
+                                    !( (x.rule.eq("ft_elf") | x.rule.eq("executable_elf32") | x.rule.eq("executable_elf64")) & SYSTEM_BIN_PATH.is_match(each_path.to_str().unwrap_or_default()) )
+                        }).collect();
+                        if !yara_matches.is_empty() {
+                            let finding = Finding {
+                                device: device.root_str(),
+                                module: String::from("yara"),
+                                file: String::from(each_path.to_str().unwrap_or_default()),
+                                kind: FindingKind::YaraMatch,
+                                key: String::from(each_path.to_str().unwrap_or_default()),
+                                observed: vec![],
+                                expected: vec![],
+                                yara_matches,
+                                severity: Severity::default(),
+                                frequency: None,
+                            };
+                            self.report_finding(finding, 0);
                         }
+                        self.emit(Event::FileScanned {
+                            device: device.root_str(),
+                            file: String::from(each_path.to_str().unwrap_or_default()),
+                            bytes: each_entry.metadata().map_or(0, |x| x.len()),
+                        });
                     });
-                });
-            }
-        }
+                }
+                else if file_type.is_file() {
+                    self.emit(Event::FileSkipped { device: device.root_str(), file: String::from(each_entry.path().to_str().unwrap_or_default()) });
+                }
+            });
+        });
     }
 
+    /// Reference every dump file under 'path', of the baseline dump 'baseline_root'
+    /// numbered 'baseline_device'.
     fn parse_ref(&self,
+            baseline_root: &str,
+            baseline_device: i64,
             path: String)
     {
-    let path = Path::new(path.as_str());
-    let validator_flag = match path.try_exists() {
-        Ok(x)=> x,
-        Err(err) => panic!("{} {}",
-                style("[ERROR]").bold().dim().red(),
-                err.to_string()),
-    };
-    if validator_flag && path.is_dir() {
-        if let Ok(read_dir) = path.read_dir() {
-            rayon::scope(|s| {
-                for each_dir in read_dir {
-                    if let Ok(each_entry) = each_dir {
-                        if each_entry.file_type().unwrap().is_dir() {
-                            self.parse_ref(String::from(each_entry.path().to_str().unwrap()))
-                        }
-                        else if each_entry.file_type().unwrap().is_file() &&
-                                ( each_entry.path().extension().is_some_and(|x| x.to_str().unwrap() == "txt") ||
-                                Regex::new(r"[\\/]system[\\/]bin[\\/].*$").unwrap().is_match(each_entry.path().to_str().unwrap()) )
+        let path = Path::new(path.as_str());
+        let read_dir = match path.read_dir() {
+            Ok(x) => x,
+            Err(err) => {
+                self.report_error(baseline_root, path.to_str().unwrap_or_default(), &err.into());
+                return;
+            },
+        };
+        self.thread_pool.scope(|s| {
+            for each_dir in read_dir {
+                let each_entry = match each_dir {
+                    Ok(x) => x,
+                    Err(err) => {
+                        self.report_error(baseline_root, path.to_str().unwrap_or_default(), &err.into());
+                        continue;
+                    },
+                };
+                let file_type = match each_entry.file_type() {
+                    Ok(x) => x,
+                    Err(err) => {
+                        self.report_error(baseline_root, each_entry.path().to_str().unwrap_or_default(), &err.into());
+                        continue;
+                    },
+                };
+                if file_type.is_dir() {
+                    self.parse_ref(baseline_root,
+                            baseline_device,
+                            String::from(each_entry.path().to_str().unwrap_or_default())
+                    )
+                }
+                else if file_type.is_file() &&
+                        self.find_parser(&each_entry.path()).is_some()
+                {
+                    s.spawn(move |_| {
+                        let file_path = String::from(each_entry.path().to_str().unwrap_or_default());
+                        match self.create_bufreader(each_entry.path().as_path())
+                                .map_err(AndroidParserError::from)
+                                .and_then(|buf_reader| self.android_file_reference(file_path.clone(), buf_reader, baseline_device))
                         {
-                            s.spawn(move |_| {
-                                let _ = match self.create_bufreader(each_entry.path().as_path()){
-                                    Ok(buf_reader) => self.android_file_reference(String::from(each_entry.path().to_str().unwrap()),
-                                            buf_reader),
-                                    Err(err) => println!("{} {}",
-                                            style("[Error]").bold().dim().red(),
-                                            err),
-                                };
-                            });
-                        }
-                    }
+                            Ok(true) => {
+                                self.run_stats.add_file();
+                                self.emit(Event::FileParsed { device: baseline_root.to_string(), file: file_path });
+                            },
+                            Ok(false) => self.emit(Event::FileSkipped { device: baseline_root.to_string(), file: file_path }),
+                            Err(err) => self.report_error(baseline_root, &file_path, &err),
+                        };
+                    });
                 }
-            });
-        }
+                else if file_type.is_file() {
+                    self.emit(Event::FileSkipped { device: baseline_root.to_string(), file: String::from(each_entry.path().to_str().unwrap_or_default()) });
+                }
+            }
+        });
+    }
+
+    /// Whether the row 'key', 'values' of the reference 'table' is inserted, once
+    /// counted into the baseline statistics: only on its first dump.
+    fn count_ref_row(&self,
+            table: &str,
+            key: &str,
+            values: &[&str],
+            baseline_device: i64) -> Result<bool, AndroidParserError>
+    {
+        Ok(baseline::count_row(&self.connx, table, key, values, baseline_device)? == RowCount::New)
     }
-}
 
     fn create_key_value_table_ref(&self,
             table_to_create: String,
-            entries: Vec<(String, String)>
-    ){
-        let query_table = format!("CREATE TABLE '{}' (key TEXT, value TEXT)",
+            entries: Vec<(String, String)>,
+            baseline_device: i64
+    ) -> Result<(), AndroidParserError> {
+        let query_table = format!("CREATE TABLE IF NOT EXISTS '{}' (key TEXT, value TEXT)",
                 table_to_create
         );
-        let _ = self.connx.execute(query_table);
+        self.connx.execute(query_table)?;
         let query_insert = format!("INSERT INTO '{}' (key, value) VALUES (:key, :value)",
                 table_to_create
         );
-        let mut statement = self.connx.prepare(query_insert).unwrap();
-        entries.iter().for_each(|couple| {
+        let mut new_entries: Vec<(String, String)> = vec![];
+        for couple in entries.into_iter() {
+            if self.count_ref_row(&table_to_create, &couple.0, &[&couple.1], baseline_device)? {
+                new_entries.push(couple);
+            }
+        }
+        let mut statement = self.connx.prepare(query_insert)?;
+        new_entries.iter().for_each(|couple| {
             let _ = statement.bind(&[
                 (":key", couple.0.as_str()),
                 (":value", couple.1.as_str()),
@@ -378,211 +683,128 @@ impl AndroidParser {
             while let Ok(State::Row) = statement.next() {}
             let _ = statement.reset();
         });
+        Ok(())
     }
 
     fn compare_key_value(&self,
+            device: &DeviceContext,
             file_path: String,
             entries: Vec<(String, String)>,
             table_to_select: String
-    ){
-        let query = format!("SELECT * FROM '{}' WHERE key=:key",
-                table_to_select);
-        let mut stmt = self.connx.prepare(query.as_str()).unwrap();
-        let mut buf_writer = match self.create_bufwriter(&file_path) {
-            Ok(x) => x,
-            Err(err) => panic!("{}", err),
-        };
-        let _ = buf_writer.write_all("file_name;setting_name;setting_config;yara_match;yara_rulename\n".as_bytes());
+    ) -> Result<(), AndroidParserError> {
+        let query = format!("{} WHERE key=:key",
+                select_module_rows(&device.connx, &table_to_select, "key")?
+        );
+        let mut stmt = device.connx.prepare(query.as_str())?;
+        self.emit(Event::ModuleStarted {
+            device: device.root_str(),
+            file: file_path.clone(),
+            header: ["file_name", "setting_name", "setting_config", "expected_setting_config", "status", "yara_match", "yara_rulename", "severity", "frequency"].map(String::from).to_vec(),
+        });
+        let mut compared_rows = ComparedRows::default();
         entries.into_iter().for_each(|each_entry| {
-            let _ = stmt.bind((":key",
-                    each_entry.0.as_str())
-            );
-            let mut flag: bool = false;
-            while let Ok(State::Row) = stmt.next() {
-                let value: String = stmt.read(1).unwrap();
-                if value == each_entry.1.to_string() {
-                    flag = true;
-                }
+            if let Some(finding) = self.compare_row(device, &file_path, &mut stmt, &table_to_select, (each_entry.0, vec![each_entry.1]), &mut compared_rows) {
+                self.report_device_finding(device, &table_to_select, finding, 1);
             }
-            let _ = stmt.reset();
-            if !flag {
-                let mut matched_rules_names = String::new();
-                flag = false;
-                let mut yara_checker: Vec<Scanner> = vec![];
-                self.yara_rules.iter().for_each(|a_rule| yara_checker.push(a_rule.scanner().unwrap()));
-                yara_checker.iter_mut().for_each(|yara_scanner| {
-                    if let Ok(yara_matches) = yara_scanner.scan_mem(format!("{} {}",
-                                each_entry.0,
-                                each_entry.1
-                            )
-                            .as_bytes()
-                    ){
-                        if !yara_matches.is_empty() {
-                            if !flag { flag = true; }
-                            yara_matches.into_iter().for_each(|x| {
-                                // println!("[REPORT] Yara matched => {}\n=>\t{}", x.identifier, self.path_filename);
-                                if !matched_rules_names.contains(format!("[{}]",
-                                        x.identifier).as_str()
-                                ){
-                                    matched_rules_names.push_str(format!("[{}]",
-                                                x.identifier
-                                            )
-                                        .as_str()
-                                    );
-                                }
-                            });
-                        }
-                    };
-                });
-                if flag {
-                    let _ = buf_writer.write_all(format!("{};{};{};true;{}\n",
-                                file_path.as_str(),
-                                each_entry.0,
-                                each_entry.1,
-                                matched_rules_names.as_str()
-                            )
-                            .as_bytes()
-                    );
-                }
-                else {
-                    let _ = buf_writer.write_all(format!("{};{};{};false;\n",
-                                file_path.as_str(),
-                                each_entry.0,
-                                each_entry.1
-                            )
-                            .as_bytes()
-                    );
-                }
-            }
-        })
+        });
+        self.report_missing_rows(device,
+                &file_path,
+                &table_to_select,
+                "key",
+                &compared_rows
+        );
+        Ok(())
     }
     
     fn create_key_xvalues_table_ref(&self,
             table_to_create: String,
-            entries: Vec<Vec<(String, Vec<String>)>>
-    ){
-        let query_table = format!("CREATE TABLE '{}' (key TEXT, value TEXT)",
+            entries: Vec<Vec<(String, Vec<String>)>>,
+            baseline_device: i64
+    ) -> Result<(), AndroidParserError> {
+        let query_table = format!("CREATE TABLE IF NOT EXISTS '{}' (key TEXT, value TEXT)",
                 table_to_create);
-        let _ = self.connx.execute(query_table);
+        self.connx.execute(query_table)?;
         let query_insert = format!("INSERT INTO '{}' (key, value) VALUES (:key, :value)",
                 table_to_create);
-        let mut statement = self.connx.prepare(query_insert).unwrap();
-        entries.into_iter().for_each(|high_block| {
-            high_block.into_iter().for_each(|mid_block| {
-                mid_block.1.into_iter().for_each(|each_value| {
-                    let _ = statement.bind(&[(":key", mid_block.0.as_str()),
-                            (":value", each_value.as_str())]
-                            [..]
-                    );
-                    while let Ok(State::Row) = statement.next() {}
-                    let _ = statement.reset();
-                });
-            });
+        let mut new_entries: Vec<(String, String)> = vec![];
+        for mid_block in entries.into_iter().flatten() {
+            for each_value in mid_block.1.into_iter() {
+                if self.count_ref_row(&table_to_create, &mid_block.0, &[&each_value], baseline_device)? {
+                    new_entries.push((mid_block.0.clone(), each_value));
+                }
+            }
+        }
+        let mut statement = self.connx.prepare(query_insert)?;
+        new_entries.into_iter().for_each(|couple| {
+            let _ = statement.bind(&[(":key", couple.0.as_str()),
+                    (":value", couple.1.as_str())]
+                    [..]
+            );
+            while let Ok(State::Row) = statement.next() {}
+            let _ = statement.reset();
         });
+        Ok(())
     }
 
     fn compare_key_xvalues(&self,
+            device: &DeviceContext,
             file_path: String,
             entries: Vec<Vec<(String, Vec<String>)>>, 
             table_to_select: String
-    ){
-        let query = format!("SELECT * FROM {} WHERE key=:key",
-                table_to_select
+    ) -> Result<(), AndroidParserError> {
+        let query = format!("{} WHERE key=:key",
+                select_module_rows(&device.connx, &table_to_select, "key")?
         );
-        let mut stmt = self.connx.prepare(query).unwrap();
-        let mut buf_writer = match self.create_bufwriter(&file_path) {
-            Ok(x) => x,
-            Err(err) => panic!("{}", err),
-        };
-        let _ = buf_writer.write_all("file_name;setting_name;setting_config;yara_match;yara_rulename\n".as_bytes());
-        entries.into_iter().for_each(|high_block| {
-            high_block.into_iter().for_each(|mid_block| {
-                let mut ref_values: Vec<String> = vec![];
-                let _ = stmt.bind((":key",
-                        mid_block.0.as_str())
-                );
-                while let Ok(State::Row) = stmt.next() {
-                    let a_value: String = stmt.read(1).unwrap();
-                    ref_values.push(a_value);
+        let mut stmt = device.connx.prepare(query)?;
+        self.emit(Event::ModuleStarted {
+            device: device.root_str(),
+            file: file_path.clone(),
+            header: ["file_name", "setting_name", "setting_config", "expected_setting_config", "status", "yara_match", "yara_rulename", "severity", "frequency"].map(String::from).to_vec(),
+        });
+        let mut compared_rows = ComparedRows::default();
+        entries.into_iter().flatten().for_each(|mid_block| {
+            mid_block.1.into_iter().for_each(|each_value| {
+                if let Some(finding) = self.compare_row(device, &file_path, &mut stmt, &table_to_select, (mid_block.0.clone(), vec![each_value]), &mut compared_rows) {
+                    self.report_device_finding(device, &table_to_select, finding, 1);
                 }
-                let _ = stmt.reset();
-                mid_block.1.into_iter().for_each(|each_value| {
-                    if !ref_values.contains(&each_value) {
-                        let mut matched_rules_names = String::new();
-                        let mut flag: bool = false;
-                        let mut yara_checker: Vec<Scanner> = vec![];
-                        self.yara_rules.iter().for_each(|a_rule| yara_checker.push(a_rule.scanner().unwrap()));
-                        yara_checker.iter_mut().for_each(|yara_scanner| {
-                            if let Ok(yara_matches) = yara_scanner.scan_mem(format!("{} {}",
-                                        mid_block.0,
-                                        each_value
-                                    )
-                                    .as_bytes()
-                            ){
-                                if !yara_matches.is_empty() {
-                                    if !flag { flag = true; }
-                                    yara_matches.into_iter().for_each(|x| {
-                                        // println!("[REPORT] Yara matched => {}\n=>\t{}", x.identifier, self.path_filename);
-                                        if !matched_rules_names.contains(format!("[{}]",
-                                                    x.identifier
-                                                )
-                                                .as_str()
-                                        ){
-                                            matched_rules_names.push_str(format!("[{}]",
-                                                        x.identifier
-                                                    )
-                                                    .as_str()
-                                            );
-                                        }
-                                    });
-                                }
-                            };
-                        });
-                        if flag {
-                            let _ = buf_writer.write_all(format!("{};{};{};true;{}\n",
-                                        file_path.as_str(),
-                                        mid_block.0,
-                                        each_value,
-                                        matched_rules_names.as_str()
-                                    )
-                                    .as_bytes()
-                            );
-                        }
-                        else {
-                            let _ = buf_writer.write_all(format!("{};{};{};false;\n",
-                                        file_path.as_str(),
-                                        mid_block.0,
-                                        each_value
-                                    )
-                                    .as_bytes()
-                            );
-                        }
-                    }
-                });
             });
         });
+        self.report_missing_rows(device,
+                &file_path,
+                &table_to_select,
+                "key",
+                &compared_rows
+        );
+        Ok(())
     }
 
     fn create_key_3values_table_ref(&self,
             table_to_create: String,
             entries: Vec<(String, String, String, String)>,
-            headers: (String, String, String, String)
-    ){
-        let query_table = format!("CREATE TABLE '{table_to_create}' ({} TEXT, {} TEXT, {} TEXT, {} TEXT)",
+            headers: (String, String, String, String),
+            baseline_device: i64
+    ) -> Result<(), AndroidParserError> {
+        let query_table = format!("CREATE TABLE IF NOT EXISTS '{table_to_create}' ({} TEXT, {} TEXT, {} TEXT, {} TEXT)",
                 headers.0,
                 headers.1,
                 headers.2,
                 headers.3
         );
-        let _ = self.connx.execute(query_table);
+        self.connx.execute(query_table)?;
         let query_insert = format!("INSERT INTO '{table_to_create}' ({}, {}, {}, {}) VALUES (:key, :val1, :val2, :val3)",
                 headers.0,
                 headers.1,
                 headers.2,
                 headers.3
         );
-        let mut statement = self.connx.prepare(query_insert).unwrap();
-        entries.into_iter().for_each(|a_volume| {
+        let mut new_entries: Vec<(String, String, String, String)> = vec![];
+        for a_volume in entries.into_iter() {
+            if self.count_ref_row(&table_to_create, &a_volume.0, &[&a_volume.1, &a_volume.2, &a_volume.3], baseline_device)? {
+                new_entries.push(a_volume);
+            }
+        }
+        let mut statement = self.connx.prepare(query_insert)?;
+        new_entries.into_iter().for_each(|a_volume| {
             let _ = statement.bind(&[(":key", a_volume.0.as_str()),
                     (":val1", a_volume.1.as_str()),
                     (":val2", a_volume.2.as_str()),
@@ -592,122 +814,55 @@ impl AndroidParser {
             while let Ok(State::Row) = statement.next() {}
             let _ = statement.reset();
         });
+        Ok(())
     }
 
     fn compare_key_3values(&self,
+            device: &DeviceContext,
             file_path: String,
             table_to_select: String,
             entries: Vec<(String, String, String, String)>,
             header: String
-    ){
-        let query = format!("SELECT * FROM '{}' WHERE {}=:key",
-                table_to_select,
+    ) -> Result<(), AndroidParserError> {
+        let query = format!("{} WHERE {}=:key",
+                select_module_rows(&device.connx, &table_to_select, &header)?,
                 header
         );
-        let mut stmt = self.connx.prepare(query).unwrap();
-        let mut buf_writer = match self.create_bufwriter(&file_path) {
-            Ok(x) => x,
-            Err(err) => panic!("{}", err),
-        };
-        let _ = buf_writer.write_all(format!("file_name;{};{};{};{};yara_match;yara_rulename\n",
-                    stmt.column_name(0).unwrap_or("setting_name"),
-                    stmt.column_name(1).unwrap_or("setting_config1"),
-                    stmt.column_name(2).unwrap_or("setting_config2"),
-                    stmt.column_name(3).unwrap_or("setting_config3")
-                )
-                .as_bytes()
-        );
+        let mut stmt = device.connx.prepare(query)?;
+        self.emit(Event::ModuleStarted {
+            device: device.root_str(),
+            file: file_path.clone(),
+            header: module_header(&stmt, 3),
+        });
+        let mut compared_rows = ComparedRows::default();
         entries.into_iter().for_each(|each_entry| {
-            let mut flag: bool = false;
-            let _ = stmt.bind((":key",
-                    each_entry.0.as_str()
-            ));
-            while let Ok(State::Row) = stmt.next() {
-                let values: (String, String, String) = (stmt.read(1).unwrap(),
-                        stmt.read(2).unwrap(),
-                        stmt.read(3).unwrap()
-                );
-                if each_entry.1 == values.0 &&
-                        each_entry.2 == values.1 &&
-                        each_entry.3 == values.2
-                {
-                    flag = true;
-                }
+            if let Some(finding) = self.compare_row(device, &file_path, &mut stmt, &table_to_select, (each_entry.0, vec![each_entry.1, each_entry.2, each_entry.3]), &mut compared_rows) {
+                self.report_device_finding(device, &table_to_select, finding, 3);
             }
-            if !flag {
-                let mut matched_rules_names = String::new();
-                flag = false;
-                let mut yara_checker: Vec<Scanner> = vec![];
-                self.yara_rules.iter().for_each(|a_rule| yara_checker.push(a_rule.scanner().unwrap()));
-                yara_checker.iter_mut().for_each(|yara_scanner| {
-                    if let Ok(yara_matches) = yara_scanner.scan_mem(format!("{} {} {} {}",
-                                each_entry.0,
-                                each_entry.1,
-                                each_entry.2,
-                                each_entry.3
-                            )
-                            .as_bytes()
-                    ){
-                        if !yara_matches.is_empty() {
-                            if !flag { flag = true; }
-                            yara_matches.into_iter().for_each(|x| {
-                                // println!("[REPORT] Yara matched => {}\n=>\t{}", x.identifier, self.path_filename);
-                                if !matched_rules_names.contains(format!("[{}]",
-                                            x.identifier
-                                        )
-                                        .as_str()
-                                ){
-                                    matched_rules_names.push_str(format!("[{}]",
-                                                x.identifier
-                                            )
-                                            .as_str()
-                                    );
-                                }
-                            });
-                        }
-                    };
-                });
-                if flag {
-                    let _ = buf_writer.write_all(format!("{};{};{};{};{};true;{}\n",
-                                file_path.as_str(),
-                                each_entry.0,
-                                each_entry.1,
-                                each_entry.2,
-                                each_entry.3,
-                                matched_rules_names.as_str()
-                            )
-                            .as_bytes()
-                    );
-                }
-                else {
-                    let _ = buf_writer.write_all(format!("{};{};{};{};{};false;\n",
-                                file_path.as_str(),
-                                each_entry.0,
-                                each_entry.1,
-                                each_entry.2,
-                                each_entry.3
-                            )
-                            .as_bytes()
-                    );
-                }
-            }
-            let _ = stmt.reset();
         });
+        self.report_missing_rows(device,
+                &file_path,
+                &table_to_select,
+                &header,
+                &compared_rows
+        );
+        Ok(())
     }
 
     fn create_5values_block_table_ref(&self,
             table_to_create: String,
             entries: Vec<[String; 5]>,
-            headers: (String, String, String, String, String)
-    ){
-        let query_table = format!("CREATE TABLE '{table_to_create}' ({} TEXT, {} TEXT, {} TEXT, {} TEXT, {} TEXT)",
+            headers: (String, String, String, String, String),
+            baseline_device: i64
+    ) -> Result<(), AndroidParserError> {
+        let query_table = format!("CREATE TABLE IF NOT EXISTS '{table_to_create}' ({} TEXT, {} TEXT, {} TEXT, {} TEXT, {} TEXT)",
                 headers.0,
                 headers.1,
                 headers.2,
                 headers.3,
                 headers.4
         );
-        let _ = self.connx.execute(query_table);
+        self.connx.execute(query_table)?;
         let query_insert = format!("INSERT INTO '{table_to_create}' ({0}, {1}, {2}, {3}, {4}) VALUES (:{0}, :{1}, :{2}, :{3}, :{4})",
                 headers.0,
                 headers.1,
@@ -715,8 +870,14 @@ impl AndroidParser {
                 headers.3,
                 headers.4
         );
-        let mut statement = self.connx.prepare(query_insert).unwrap();
-        entries.into_iter().for_each(|block| {
+        let mut new_entries: Vec<[String; 5]> = vec![];
+        for block in entries.into_iter() {
+            if self.count_ref_row(&table_to_create, &block[0], &[&block[1], &block[2], &block[3], &block[4]], baseline_device)? {
+                new_entries.push(block);
+            }
+        }
+        let mut statement = self.connx.prepare(query_insert)?;
+        new_entries.into_iter().for_each(|block| {
             // blocks -> permission, package, label, description, protectionLevel
             let _ = statement.bind_iter([
                     (format!(":{}", headers.0).as_str(), block[0].as_str()),
@@ -728,609 +889,564 @@ impl AndroidParser {
             while let Ok(State::Row) = statement.next() {}
             let _ = statement.reset();
         });
+        Ok(())
     }
 
     fn compare_5values_block(&self,
+            device: &DeviceContext,
             file_path: String,
             entries: Vec<[String; 5]>,
             table_to_select: String,
             header: String
-    ){
-        let query = format!("SELECT * FROM '{}' WHERE {}=:key",
-                table_to_select,
+    ) -> Result<(), AndroidParserError> {
+        let query = format!("{} WHERE {}=:key",
+                select_module_rows(&device.connx, &table_to_select, &header)?,
                 header
         );
-        let mut stmt = self.connx.prepare(query).unwrap();
-        let mut buf_writer = match self.create_bufwriter(&file_path) {
-            Ok(x) => x,
-            Err(err) => panic!("{}", err),
-        };
-        let _ = buf_writer.write_all(format!("file_name;{};{};{};{};{};yara_match;yara_rulename\n",
-                    stmt.column_name(0).unwrap_or("setting_config1"),
-                    stmt.column_name(1).unwrap_or("setting_config2"),
-                    stmt.column_name(2).unwrap_or("setting_config3"),
-                    stmt.column_name(3).unwrap_or("setting_config4"),
-                    stmt.column_name(4).unwrap_or("setting_config5")
-                )
-                .as_bytes()
-        );
+        let mut stmt = device.connx.prepare(query)?;
+        self.emit(Event::ModuleStarted {
+            device: device.root_str(),
+            file: file_path.clone(),
+            header: module_header(&stmt, 4),
+        });
+        let mut compared_rows = ComparedRows::default();
         entries.into_iter().for_each(|blocks| {
-            let mut flag: bool = false;
-            let _ = stmt.bind((":key",
-                    blocks[0].as_str()
-            ));
-            while let Ok(State::Row) = stmt.next() {
-                let values: (String, String, String, String) = (stmt.read(1).unwrap(),
-                        stmt.read(2).unwrap(),
-                        stmt.read(3).unwrap(),
-                        stmt.read(4).unwrap()
-                );
-                if values.0 == blocks[1] &&
-                        values.1 == blocks[2] &&
-                        values.2 == blocks[3] &&
-                        values.3 == blocks[4]
-                {
-                    flag = true;
-                }
-            }
-            if !flag {
-                let mut matched_rules_names = String::new();
-                flag = false;
-                let mut yara_checker: Vec<Scanner> = vec![];
-                self.yara_rules.iter().for_each(|a_rule| yara_checker.push(a_rule.scanner().unwrap()));
-                yara_checker.iter_mut().for_each(|yara_scanner| {
-                    if let Ok(yara_matches) = yara_scanner.scan_mem(format!("{} {} {} {} {}",
-                                blocks[0],
-                                blocks[1],
-                                blocks[2],
-                                blocks[3],
-                                blocks[4]
-                            )
-                            .as_bytes()
-                    ){
-                        if !yara_matches.is_empty() {
-                            if !flag { flag = true; }
-                            yara_matches.into_iter().for_each(|x| {
-                                if !matched_rules_names.contains(format!("[{}]",
-                                            x.identifier
-                                        )
-                                        .as_str()
-                                ){
-                                    matched_rules_names.push_str(format!("[{}]", 
-                                                x.identifier
-                                            )
-                                            .as_str()
-                                    );
-                                }
-                            });
-                        }
-                    };
-                });
-                if flag {
-                    let _ = buf_writer.write_all(format!("{};{};{};{};{};{};true;{}\n",
-                                file_path.as_str(),
-                                blocks[0],
-                                blocks[1],
-                                blocks[2],
-                                blocks[3],
-                                blocks[4],
-                                matched_rules_names.as_str()
-                            )
-                            .as_bytes()
-                    );
-                }
-                else {
-                    let _ = buf_writer.write_all(format!("{};{};{};{};{};{};false;\n",
-                                file_path.as_str(),
-                                blocks[0],
-                                blocks[1],
-                                blocks[2],
-                                blocks[3],
-                                blocks[4]
-                            )
-                            .as_bytes()
-                    );
-                }
+            let [key, values @ ..] = blocks;
+            if let Some(finding) = self.compare_row(device, &file_path, &mut stmt, &table_to_select, (key, values.to_vec()), &mut compared_rows) {
+                self.report_device_finding(device, &table_to_select, finding, 4);
             }
-            let _ = stmt.reset();
         });
+        self.report_missing_rows(device,
+                &file_path,
+                &table_to_select,
+                &header,
+                &compared_rows
+        );
+        Ok(())
     }
 
-    fn compare_binary_hash(&self,
-            file_path: String,
-            entries: (String, String),
-            // table_to_select: String,
-            // header: String
-    ){
-        let table_to_select: String = String::from("binaries_hashes");
-        let query = format!("SELECT * FROM '{}' WHERE key=:key",
-                table_to_select);
-        let mut stmt = self.connx.prepare(query.as_str()).unwrap();
+    /// Compare the 'row' (key, values) of the dump file 'file_path' against the
+    /// rows of the same key of the reference 'table' of 'device', selected by
+    /// 'stmt' ('select_module_rows()' WHERE <key column>=:key). A matching row is
+    /// reported if rare, and a row accepted by a wildcard row is not reported,
+    /// both being recorded into 'compared_rows'. The finding of any other row
+    /// is returned, with the YARA matches of the row, to be reported.
+    fn compare_row(&self,
+            device: &DeviceContext,
+            file_path: &str,
+            stmt: &mut Statement,
+            table: &str,
+            row: (String, Vec<String>),
+            compared_rows: &mut ComparedRows
+    ) -> Option<Finding> {
+        let (key, values) = row;
         let _ = stmt.bind((":key",
-                entries.0.as_str())
-        );
+                key.as_str()
+        ));
         let mut flag: bool = false;
+        let mut expected_values: Vec<Vec<String>> = vec![];
         while let Ok(State::Row) = stmt.next() {
-            let value: String = stmt.read(1).unwrap();
-            if value == entries.1.to_string() {
+            let row_values: Vec<String> = (2..values.len() + 2).map(|index| stmt.read(index).unwrap_or_default())
+                    .collect();
+            if row_values == values {
                 flag = true;
+                compared_rows.matched_rowids.insert(stmt.read(0).unwrap_or_default());
             }
+            expected_values.push(row_values);
         }
         let _ = stmt.reset();
-        if !flag {
-            let mut matched_rules_names = String::new();
-            flag = false;
-            let mut yara_checker: Vec<Scanner> = vec![];
-            self.yara_rules.iter().for_each(|a_rule| yara_checker.push(a_rule.scanner().unwrap()));
-            yara_checker.iter_mut().for_each(|yara_scanner| {
-                if let Ok(yara_matches) = yara_scanner.scan_mem(format!("{} {}",
-                        entries.0,
-                        entries.1).as_bytes()
-                ){
-                    if !yara_matches.is_empty() {
-                        if !flag { flag = true; }
-                        yara_matches.into_iter().for_each(|x| {
-                            if !matched_rules_names.contains(format!("[{}]",
-                                    x.identifier).as_str()
-                            ){
-                                matched_rules_names.push_str(format!("[{}]",
-                                            x.identifier
-                                        )
-                                    .as_str()
-                                );
-                            }
-                        });
-                    }
-                };
-                if let Ok(yara_matches) = yara_scanner.scan_file(Path::new(&file_path)){
-                    if !yara_matches.is_empty() {
-                        if !flag { flag = true; }
-                        yara_matches.into_iter().for_each(|x| {
-                            if !matched_rules_names.contains(format!("[{}]",
-                                    x.identifier).as_str()
-                            ){
-                                matched_rules_names.push_str(format!("[{}]",
-                                            x.identifier
-                                        )
-                                    .as_str()
-                                );
-                            }
-                        });
-                    }
-                };
-            });
-            let mut guarded_writer = self.binaries_report_file_mutexed.lock().unwrap();
-            if flag {
-                let _ = guarded_writer.write_all(format!("{};{};true;{}\n",
-                            file_path.as_str(),
-                            entries.1,
-                            matched_rules_names.as_str()
-                        )
-                        .as_bytes()
-                );
-            }
-            else {
-                let _ = guarded_writer.write_all(format!("{};{};false;\n",
-                            file_path.as_str(),
-                            entries.1
+        if flag {
+            self.report_rare_row(device,
+                    file_path,
+                    table,
+                    &key,
+                    &values,
+                    &expected_values
+            );
+            return None;
+        }
+        compared_rows.differing_keys.insert(key.clone());
+        if reference_json::wildcard_accepts(&device.wildcards, table, &key, &values) {
+            return None;
+        }
+        let yara_matches = self.collect_error(&device.root_str(),
+                file_path,
+                self.yara_scan_mem(format!("{} {}",
+                            key,
+                            values.join(" ")
                         )
                         .as_bytes()
-                );
-            }
-        }
+                )
+        );
+        Some(Finding {
+            device: device.root_str(),
+            module: match table {
+                "binaries_hashes" => String::from("binaries"),
+                x => x.to_string(),
+            },
+            file: file_path.to_string(),
+            kind: FindingKind::from_expected(&expected_values),
+            key,
+            observed: values,
+            expected: expected_values,
+            yara_matches,
+            severity: Severity::default(),
+            frequency: None,
+        })
     }
 
-    fn parse_getprop(&self, read_buffer: BufReader<File>) -> Vec<(String, String)> {
-        let mut results: Vec<(String, String)> = vec![];
-        let re = match Regex::new(r"^\[(?P<part1>.*)\]: \[(?P<part2>.*)\]$") {
+    /// Report every row of the reference 'table_to_select' which was not matched
+    /// by the device, and whose key was neither reported as changed nor accepted
+    /// by a wildcard row, as removed.
+    /// Observed values are left empty, expected values are the reference row.
+    /// Rows missing from some dumps of a baseline are not reported.
+    /// Its columns are selected by name, 'key_column' first.
+    fn report_missing_rows(&self,
+            device: &DeviceContext,
+            file_path: &str,
+            table_to_select: &str,
+            key_column: &str,
+            compared_rows: &ComparedRows
+    ){
+        let query = match select_module_rows(&device.connx, table_to_select, key_column) {
             Ok(x) => x,
-            Err(err) => panic!("{}", err),
+            Err(_) => return,
         };
-        for line in read_buffer.lines() {
-            let line_str = match line {
-                Ok(x) => x,
-                Err(_err) => String::from(""),
-            };
-            let catches: (String, String) = match re.captures(line_str.as_str()) {
-                Some(caps) => (caps.name("part1").map_or("".to_string(), |m| String::from(m.as_str())),
-                        caps.name("part2").map_or("".to_string(), |m| String::from(m.as_str()))
-                ),
-                None => { continue; },
-            };
-            results.push(catches);
+        let mut stmt = match device.connx.prepare(query) {
+            Ok(x) => x,
+            Err(_) => return,
+        };
+        let columns_count = stmt.column_count();
+        while let Ok(State::Row) = stmt.next() {
+            let rowid: i64 = stmt.read(0).unwrap_or_default();
+            let key: String = stmt.read(1).unwrap_or_default();
+            let values: Vec<String> = (2..columns_count).map(|index| stmt.read::<String, _>(index).unwrap_or_default())
+                    .collect();
+            if !compared_rows.matched_rowids.contains(&rowid) &&
+                    !compared_rows.differing_keys.contains(&key) &&
+                    !self.is_baseline_variation(device, table_to_select, &key, &values)
+            {
+                let finding = Finding {
+                    device: device.root_str(),
+                    module: table_to_select.to_string(),
+                    file: file_path.to_string(),
+                    kind: FindingKind::Removed,
+                    key,
+                    observed: vec![],
+                    expected: vec![values],
+                    yara_matches: vec![],
+                    severity: Severity::default(),
+                    frequency: None,
+                };
+                self.report_device_finding(device, table_to_select, finding, columns_count - 2);
+            }
         }
-        results
     }
-    
-    fn parse_settings(&self,
-            read_buffer: BufReader<File>) -> Vec<(String, String)>
+
+    /// Report every binary of the reference which was not found on the device,
+    /// unless missing from some dumps of a baseline.
+    fn report_missing_binaries(&self,
+            device: &DeviceContext)
     {
-        let mut results = vec![];
-        for line in read_buffer.lines() {
-            let line_str = match line {
-                Ok(x) => x,
-                Err(_err) => { continue; },
-            };
-            let mut splited_str = line_str.split("=");
-            let val1: &str = match splited_str.next() {
-                Some(x) => x,
-                None => "",
-            };
-            let val2: &str = match splited_str.next() {
-                Some(x) => x,
-                None => "",
-            };
-            results.push((val1.to_string(),
-                    val2.to_string())
-            );
+        let query = String::from("SELECT key, value FROM 'binaries_hashes'");
+        let mut stmt = match device.connx.prepare(query) {
+            Ok(x) => x,
+            Err(_) => return,
+        };
+        let seen_binaries = device.seen_binaries.lock().unwrap();
+        while let Ok(State::Row) = stmt.next() {
+            let bin_name: String = stmt.read(0).unwrap_or_default();
+            let sha256_sum: String = stmt.read(1).unwrap_or_default();
+            if !seen_binaries.contains(&bin_name) &&
+                    !self.is_baseline_variation(device, "binaries_hashes", &bin_name, std::slice::from_ref(&sha256_sum))
+            {
+                let finding = Finding {
+                    device: device.root_str(),
+                    module: String::from("binaries"),
+                    file: device.root.join("system").join("bin").join(&bin_name).to_str().unwrap_or_default().to_string(),
+                    kind: FindingKind::Removed,
+                    key: bin_name,
+                    observed: vec![],
+                    expected: vec![vec![sha256_sum]],
+                    yara_matches: vec![],
+                    severity: Severity::default(),
+                    frequency: None,
+                };
+                self.report_device_finding(device, "binaries_hashes", finding, 1);
+            }
         }
-        results
     }
-    
-    fn parse_df_ah(&self,
-            read_buffer: BufReader<File>) -> Vec<(String, String)>
-    {
-        let mut results: Vec<(String, String)> = vec![];
-        for line in read_buffer.lines() {
-            let line_str = match line {
-                Ok(x) => x,
-                Err(_err) => { continue; },
-            };
-            let splited_str: Vec<&str> = line_str.split_whitespace().into_iter().collect();
-            if splited_str.len() == 6 {
-                let first = splited_str.first().unwrap();
-                let last = splited_str.last().unwrap();
-                results.push((first.to_string(),
-                        last.to_string())
-                );
+
+    fn compare_binary_hash(&self,
+            device: &DeviceContext,
+            file_path: String,
+            entries: (String, String),
+    ) -> Result<(), AndroidParserError> {
+        let table_to_select: String = String::from("binaries_hashes");
+        let query = format!("{} WHERE key=:key",
+                select_module_rows(&device.connx, &table_to_select, "key")?
+        );
+        let mut stmt = device.connx.prepare(query.as_str())?;
+        device.seen_binaries.lock().unwrap().insert(entries.0.clone());
+        // missing binaries are reported by 'report_missing_binaries()', once every file is seen
+        let mut finding = match self.compare_row(device, &file_path, &mut stmt, &table_to_select, (entries.0, vec![entries.1]), &mut ComparedRows::default()) {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        self.collect_error(&device.root_str(),
+                &file_path,
+                self.yara_scan_file(Path::new(&file_path))
+        ).into_iter().for_each(|x| {
+            if !finding.yara_matches.iter().any(|y| y.rule == x.rule) {
+                finding.yara_matches.push(x);
             }
-        }
-        results
+        });
+        self.report_device_finding(device, &table_to_select, finding, 1);
+        Ok(())
     }
-    
-    fn parse_id(&self,
-            read_buffer: BufReader<File>) -> Vec<Vec<(String, Vec<String>)>>
+
+    /// Frequency of the row 'key', 'values' of the reference 'table' of 'device',
+    /// None unless its reference is a baseline of many dumps.
+    fn value_frequency(&self,
+            device: &DeviceContext,
+            table: &str,
+            key: &str,
+            values: &[String]) -> Option<ValueFrequency>
     {
-        let mut results: Vec<Vec<(String, Vec<String>)>> = vec![];
-        for line in read_buffer.lines() {
-            let line_str = match line {
-                Ok(x) => x,
-                Err(_err) => String::from(""),
-            };
-            let splited_str = line_str.split_whitespace();
-            splited_str.into_iter().for_each(|each_split| {
-                let coupled_values: Vec<String> = each_split.split("=").map(|x| x.to_string()).collect();
-                let mut temp_vec: Vec<(String, Vec<String>)> = vec![];
-                let group_name = match coupled_values.first() {
-                    Some(x) => x,
-                    None => "",
-                };
-                let gathered_users: Vec<String> = match coupled_values.last() {
-                    Some(x) => x.to_string().split(",").map(|x| x.to_string()).collect(),
-                    None => vec![],
-                };
-                temp_vec.push(( group_name.to_string(),
-                        gathered_users )
-                );
-                results.push(temp_vec);
-            });
+        match device.baseline_devices > 1 {
+            true => Some(ValueFrequency::new(baseline::seen_count(&device.connx, table, key, values),
+                    device.baseline_devices,
+                    self.rare_threshold
+            )),
+            false => None,
         }
-        results
     }
-    
-    fn parse_mount(&self,
-            read_buffer: BufReader<File>) -> Vec<(String, String, String, String)>
+
+    /// Whether the row 'key', 'values' of the reference 'table' of 'device' is
+    /// missing from some dumps of its baseline, its absence being then no removal.
+    fn is_baseline_variation(&self,
+            device: &DeviceContext,
+            table: &str,
+            key: &str,
+            values: &[String]) -> bool
     {
-        let mut results: Vec<(String, String, String, String)> = vec![];
-        for line in read_buffer.lines() {
-            let line_str = match line {
-                Ok(x) => x,
-                Err(_err) => String::from(""),
-            };
+        self.value_frequency(device, table, key, values)
+                .is_some_and(|x| x.seen < x.baseline)
+    }
 
-            let re =  match Regex::new(r"^(?P<NAME>\S+)\s+on\s+(?P<MOUNTPOINT>.+)\s+type\s+(?P<TYPE>\S+)\s+\((?P<OPTIONS>\S+)\)$") {
-                Ok(x) => x,
-                Err(err) => panic!("{}", err),
-            };
-            let caps = re.captures(line_str.as_str());
-            let values: (String, String, String, String) = match caps {
-                Some(caps) => (caps.name("NAME").map_or("".to_string(), |m| m.as_str().to_string()),
-                    caps.name("MOUNTPOINT").map_or("".to_string(), |m| m.as_str().to_string()),
-                    caps.name("TYPE").map_or("".to_string(), |m| m.as_str().to_string()),
-                    caps.name("OPTIONS").map_or("".to_string(), |m| m.as_str().to_string())
-                ),
-                None => { continue; },
-            };
-            results.push(values);
-        }
-        results
+    /// Report the row 'key', 'observed' of the device, matching a row of the
+    /// reference 'table', as rare if few dumps of the baseline hold it.
+    fn report_rare_row(&self,
+            device: &DeviceContext,
+            file_path: &str,
+            table: &str,
+            key: &str,
+            observed: &[String],
+            expected_values: &[Vec<String>]
+    ){
+        let frequency = match self.value_frequency(device, table, key, observed) {
+            Some(x) if x.class == FrequencyClass::Rare => x,
+            _ => return,
+        };
+        let finding = Finding {
+            device: device.root_str(),
+            module: match table {
+                "binaries_hashes" => String::from("binaries"),
+                x => x.to_string(),
+            },
+            file: file_path.to_string(),
+            kind: FindingKind::Rare,
+            key: key.to_string(),
+            observed: observed.to_vec(),
+            expected: expected_values.to_vec(),
+            yara_matches: vec![],
+            severity: Severity::default(),
+            frequency: Some(frequency),
+        };
+        self.report_finding(finding, observed.len());
     }
-    
-    fn parse_ps(&self,
-            read_buffer: BufReader<File>) -> Vec<(String, String, String, String)>
+
+    /// Report 'finding' of the reference 'table' of 'device', with the frequency
+    /// of its observed values (of its expected ones if removed) in the baseline.
+    fn report_device_finding(&self,
+            device: &DeviceContext,
+            table: &str,
+            mut finding: Finding,
+            values_count: usize
+    ){
+        finding.frequency = match finding.kind {
+            FindingKind::Added | FindingKind::Changed => self.value_frequency(device, table, &finding.key, &finding.observed),
+            FindingKind::Removed => finding.expected.first()
+                    .and_then(|x| self.value_frequency(device, table, &finding.key, x)),
+            _ => finding.frequency,
+        };
+        self.report_finding(finding, values_count);
+    }
+
+    /// Count 'finding', having 'values_count' values beside its key, and emit it
+    /// with its severity. Every 'compare_*' reports through here, so a suppressed
+    /// finding is only counted and emitted as such.
+    fn report_finding(&self,
+            mut finding: Finding,
+            values_count: usize
+    ){
+        finding.severity = self.severity_model.severity(&finding);
+        match self.suppressions.suppression(&finding) {
+            Some(reason) => {
+                self.run_stats.add_suppressed();
+                self.emit(Event::FindingSuppressed { finding, reason });
+            },
+            None => {
+                self.run_stats.add_finding();
+                self.emit(Event::Finding { finding, values_count });
+            },
+        };
+    }
+
+    /// Send 'event' to the file reports and to every observer,
+    /// then emit the failures of the file reports as errors.
+    fn emit(&self,
+            event: Event)
     {
-        let mut results: Vec<(String, String, String, String)> = vec![];
-        for line in read_buffer.lines() {
-            let line_str = match line {
-                Ok(x) => x,
-                Err(_err) => String::from(""),
-            };
-            let re =  match Regex::new(r"^(?P<UID>\w+)\s+(?P<PID>\d+)\s+(?P<PPID>\d+)(\s+\S+){4}\s+(?P<CMD>\[?\w+(\/\d+(:\d+)?)?\]?)$") {
-                Ok(x) => x,
-                Err(err) => panic!("{}", err),
-            };
-            let caps = re.captures(line_str.as_str());
-            let values: (String, String, String, String) = match caps {
-                Some(caps) => (caps.name("UID").map_or("".to_string(), |m| m.as_str().to_string()),
-                    caps.name("PID").map_or("".to_string(), |m| m.as_str().to_string()),
-                    caps.name("PPID").map_or("".to_string(), |m| m.as_str().to_string()),
-                    caps.name("CMD").map_or("".to_string(), |m| m.as_str().to_string())
-                ),
-                None => { continue; },
-            };
-            results.push(values);
+        self.file_reporter.on_event(&event);
+        self.observers.iter().for_each(|x| x.on_event(&event));
+        for failure in self.file_reporter.take_failures() {
+            self.run_stats.add_error();
+            self.emit(failure);
         }
-        results
     }
-    
-    fn parse_services(&self,
-            read_buffer: BufReader<File>) -> Vec<(String, String)>
+
+    fn yara_scanners(&self) -> Vec<Scanner<'_>> {
+        let mut vec_yara_scanner: Vec<Scanner> = vec![];
+        self.yara_rules.iter().for_each(|yara_rules| {
+            if let Ok(mut yara_scanner) = yara_rules.scanner() {
+                yara_scanner.set_timeout(self.yara_timeout);
+                yara_scanner.set_flags(yara::ScanFlags::REPORT_RULES_MATCHING);
+                vec_yara_scanner.push(yara_scanner);
+            }
+        });
+        vec_yara_scanner
+    }
+
+    /// Scan 'data' with every YARA ruleset, each matching rule being reported once.
+    fn yara_scan_mem(&self,
+            data: &[u8]) -> Result<Vec<YaraRuleMatch>, AndroidParserError>
     {
-        let mut results: Vec<(String, String)> = vec![];
-        for line in read_buffer.lines() {
-            let line_str = match line {
-                Ok(x) => x,
-                Err(_err) => String::from(""),
-            };
-            let re = match Regex::new(r"^\d+\s+(?P<SVCNAME>\S+): (?P<SVCVAL>\S+)$") {
-                Ok(x) => x,
-                Err(err) => panic!("{}", err),
-            };
-            let caps = re.captures(line_str.as_str());
-            let values: (&str, &str) = match caps {
-                Some(caps) => (caps.name("SVCNAME").map_or("", |m| m.as_str()),
-                        caps.name("SVCVAL").map_or("", |m| m.as_str())
-                ),
-                None => { continue; },
-            };
-            results.push(( values.0.to_string(),
-                    values.1.to_string() )
-            );
+        let mut yara_matched: Vec<YaraRuleMatch> = vec![];
+        for yara_scanner in self.yara_scanners().iter_mut() {
+            yara_scanner.scan_mem(data)?.iter().for_each(|x| {
+                if !yara_matched.iter().any(|y| y.rule == x.identifier) {
+                    yara_matched.push(YaraRuleMatch::from_rule(x));
+                }
+            });
         }
-        results
+        Ok(yara_matched)
     }
-    
-    fn parse_list(&self,
-            read_buffer: BufReader<File>) -> Vec<(String, String)>
+
+    /// Scan the file 'path' with every YARA ruleset, each matching rule being reported once.
+    fn yara_scan_file(&self,
+            path: &Path) -> Result<Vec<YaraRuleMatch>, AndroidParserError>
     {
-        let mut results: Vec<(String, String)> = vec![];
-        for line in read_buffer.lines() {
-            let line_str = match line {
-                Ok(x) => x,
-                Err(_err) => String::from(""),
-            };
-            let splitted_str: Vec<String> = line_str.split(":").map(|x| x.to_string()).collect();
-            results.push(( splitted_str.first().unwrap().to_string(),
-                    splitted_str.last().unwrap().to_string() )
-            );
+        let mut yara_matched: Vec<YaraRuleMatch> = vec![];
+        for yara_scanner in self.yara_scanners().iter_mut() {
+            yara_scanner.scan_file(path)?.iter().for_each(|x| {
+                if !yara_matched.iter().any(|y| y.rule == x.identifier) {
+                    yara_matched.push(YaraRuleMatch::from_rule(x));
+                }
+            });
         }
-        results
+        Ok(yara_matched)
     }
-    
-    fn parse_permissions_list(&self,
-            read_buffer: BufReader<File>) -> Vec<[String; 5]>
+
+    /// Count and emit 'err', met on 'file' of 'device', written into 'reported_errors.csv'.
+    fn report_error(&self,
+            device: &str,
+            file: &str,
+            err: &AndroidParserError)
     {
-        let mut results: Vec<[String; 5]> = vec![];
-        let mut block_counter: usize = 0;
-        let mut row_values: [String; 5] = Default::default();
-        for line in read_buffer.lines() {
-            let line_str = match line {
-                Ok(x) if x.starts_with("+ ") | x.starts_with("  ") => String::from(&x[2..]),
-                Ok(_) | Err(_) => continue,
-            };
-            let splitted_line: Vec<String> = line_str.split(":").map(|x| x.to_string()).collect();
-            row_values[block_counter] = splitted_line.last().unwrap().to_string();
-            if block_counter >= 4 {
-                results.push(row_values);
-                block_counter = 0;
-                row_values = Default::default();
-            }
-            else {
-                block_counter += 1;
-            }
+        self.run_stats.add_error();
+        self.emit(Event::Error {
+            device: device.to_string(),
+            file: file.to_string(),
+            kind: err.kind(),
+            message: err.to_string(),
+        });
+    }
+
+    /// The value of 'result', or its default once the error is reported.
+    fn collect_error<T: Default>(&self,
+            device: &str,
+            file: &str,
+            result: Result<T, AndroidParserError>) -> T
+    {
+        match result {
+            Ok(x) => x,
+            Err(err) => {
+                self.report_error(device, file, &err);
+                T::default()
+            },
         }
-        results
     }
 
+    fn flush_reports(&self) -> Result<(), AndroidParserError> {
+        self.file_reporter.flush()?;
+        Ok(())
+    }
+
+    /// Parse the dump file 'file_path' and compare it against the reference of 'device'.
+    /// Returns false if no parser handles this file.
     fn android_file_selector(&self,
-            buf_reader: BufReader<File>,
-            file_path: String)
+            mut buf_reader: BufReader<File>,
+            file_path: String,
+            device: &DeviceContext) -> Result<bool, AndroidParserError>
     {
         let path_filename = Path::new(&file_path);
-        match path_filename.file_name() {
-            Some(x) => {
-                let mut splited_str = x.to_str().unwrap().split(".");
-                let parted = splited_str.next().unwrap();
-                if parted == "getprop"  {
-                    self.compare_key_value(String::from(path_filename.to_str().unwrap()),
-                            self.parse_getprop(buf_reader),
-                            parted.to_string()
-                    );
-                }
-                else if parted.starts_with("settings_") ||
-                        parted.starts_with("printenv.txt")
-                {
-                    self.compare_key_value(String::from(path_filename.to_str().unwrap()),
-                            self.parse_settings(buf_reader),
-                            parted.to_string()
-                    );
-                }
-                else if parted == "df_ah" {
-                    self.compare_key_value(String::from(path_filename.to_str().unwrap()),
-                            self.parse_df_ah(buf_reader),
-                            parted.to_string()
-                    );
-                }
-                else if parted == "services" {
-                    self.compare_key_value(String::from(path_filename.to_str().unwrap()),
-                            self.parse_services(buf_reader),
-                            parted.to_string()
-                    );
-                }
-                else if parted == "id" {
-                    self.compare_key_xvalues(String::from(path_filename.to_str().unwrap()),
-                            self.parse_id(buf_reader),
-                            parted.to_string()
-                    );
-                }
-                else if parted == "mount" {
-                    self.compare_key_3values(String::from(path_filename.to_str().unwrap()),
-                            parted.to_string(),
-                            self.parse_mount(buf_reader),
-                            "name".to_string()
-                    );
-                }
-                else if parted == "ps" {
-                    self.compare_key_3values(String::from(path_filename.to_str().unwrap()),
-                            parted.to_string(),
-                            self.parse_ps(buf_reader),
-                            "uid".to_string()
-                    );
-                }
-                else if parted == "pm_list_permissions-f" {
-                    self.compare_5values_block(String::from(path_filename.to_str().unwrap()),
-                            self.parse_permissions_list(buf_reader),
-                            parted.to_string(),
-                            "permission".to_string()
-                    );
-                }
-                else if parted.starts_with("pm_list_") &
-                        !parted.ends_with("users")
-                {
-                    self.compare_key_value(String::from(path_filename.to_str().unwrap()),
-                            self.parse_list(buf_reader),
-                            parted.to_string()
-                    );
-                }
-                else {
-                    match Regex::new(r"[\\/]system[\\/]bin[\\/](?P<bin_name>.*)$").unwrap().captures(&file_path) {
-                        Some(caps) => {
-                            self.compare_binary_hash(String::from(path_filename.to_str().unwrap()),
-                                    ( caps.name("bin_name").map_or("".to_string(), |m| m.as_str().to_string()),
-                                            sha256::try_digest(Path::new(&file_path)).unwrap() )
-                            );
-                        },
-                        _ => (),
-                    };
-                }
+        let parser = match self.find_parser(path_filename) {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+        let table = parser.table_name(path_filename);
+        match self.normalizers.normalize(&table, parser.parse(path_filename, &mut buf_reader)?) {
+            ParsedEntries::KeyValue(entries) => {
+                self.compare_key_value(device,
+                        file_path.clone(),
+                        entries,
+                        table
+                )?;
+            },
+            ParsedEntries::KeyXValues(entries) => {
+                self.compare_key_xvalues(device,
+                        file_path.clone(),
+                        entries,
+                        table
+                )?;
+            },
+            ParsedEntries::Key3Values { headers, entries } => {
+                self.compare_key_3values(device,
+                        file_path.clone(),
+                        table,
+                        entries,
+                        headers.0
+                )?;
+            },
+            ParsedEntries::Block5Values { headers, entries } => {
+                self.compare_5values_block(device,
+                        file_path.clone(),
+                        entries,
+                        table,
+                        headers.0
+                )?;
+            },
+            ParsedEntries::BinaryHash { name, sha256_sum } => {
+                self.compare_binary_hash(device,
+                        file_path.clone(),
+                        (name, sha256_sum)
+                )?;
             },
-            _ => (),
         };
+        Ok(true)
     }
 
+    /// Parse the dump file 'file_path' of the baseline dump 'baseline_device'
+    /// into the reference DB. Returns false if no parser handles this file.
     fn android_file_reference(&self,
             file_path: String,
-            buf_reader: BufReader<File>
-    ) {
+            mut buf_reader: BufReader<File>,
+            baseline_device: i64
+    ) -> Result<bool, AndroidParserError> {
         let path_filename = Path::new(&file_path);
-        match path_filename.file_name() {
-            Some(x) => { 
-                let mut splited_str = x.to_str().unwrap().split(".");
-                let parted = splited_str.next().unwrap();
-                if x == "getprop.txt" {
-                    self.create_key_value_table_ref(parted.to_string(),
-                            self.parse_getprop(buf_reader)
-                    );
-                }
-                else if parted.starts_with("settings_") || parted == "printenv" {
-                    self.create_key_value_table_ref(parted.to_string(),
-                            self.parse_settings(buf_reader)
-                    );
-                }
-                else if parted.starts_with("df_ah") {
-                    self.create_key_value_table_ref(parted.to_string(),
-                            self.parse_df_ah(buf_reader)
-                    );
-                }
-                else if parted == "id" {
-                    self.create_key_xvalues_table_ref(parted.to_string(),
-                            self.parse_id(buf_reader)
-                    );
-                }
-                else if parted == "mount" {
-                    let headers: (String, String, String, String) = ( "name".to_string(),
-                            "mountpoint".to_string(),
-                            "type".to_string(),
-                            "options".to_string()
-                    );
-                    self.create_key_3values_table_ref(parted.to_string(),
-                            self.parse_mount(buf_reader),
-                            headers
-                    );
-                }
-                else if parted == "ps" {
-                    let headers: (String, String, String, String) = ( "uid".to_string(),
-                            "pid".to_string(),
-                            "ppid".to_string(),
-                            "cmd".to_string()
-                    );
-                    self.create_key_3values_table_ref( parted.to_string(),
-                            self.parse_ps(buf_reader),
-                            headers
-                    );
-                }
-                else if parted == "services" {
-                    self.create_key_value_table_ref(parted.to_string(),
-                            self.parse_services(buf_reader)
-                    );
-                }
-                else if parted == "pm_list_permissions-f" {
-                    let headers: (String, String, String, String, String) = ( "permission".to_string(),
-                            "package".to_string(), "label".to_string(),
-                            "description".to_string(),
-                            "protectionlevel".to_string()
-                    );
-                    self.create_5values_block_table_ref(parted.to_string(),
-                            self.parse_permissions_list(buf_reader),
-                            headers
-                        );
-                }
-                else if parted.starts_with("pm_list_") &
-                        !parted.ends_with("users")
-                {
-                    self.create_key_value_table_ref(parted.to_string(),
-                             self.parse_list(buf_reader)
-                    );
-                }
-                else {
-                    match Regex::new(r"[\\/]system[\\/]bin[\\/](?P<bin_name>.*)$").unwrap().captures(&file_path) {
-                        Some(caps) => {
-                            self.create_key_value_table_ref("binaries_hashes".to_string(),
-                                    vec![( caps.name("bin_name").map_or("".to_string(), |m| m.as_str().to_string()),
-                                            sha256::try_digest(Path::new(&file_path)).unwrap() )
-                                    ]
-                            );
-                        },
-                        _ => (),
-                    };
-                }
+        let parser = match self.find_parser(path_filename) {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+        let table = parser.table_name(path_filename);
+        match self.normalizers.normalize(&table, parser.parse(path_filename, &mut buf_reader)?) {
+            ParsedEntries::KeyValue(entries) => {
+                self.create_key_value_table_ref(table,
+                        entries,
+                        baseline_device
+                )?;
+            },
+            ParsedEntries::KeyXValues(entries) => {
+                self.create_key_xvalues_table_ref(table,
+                        entries,
+                        baseline_device
+                )?;
+            },
+            ParsedEntries::Key3Values { headers, entries } => {
+                self.create_key_3values_table_ref(table,
+                        entries,
+                        headers,
+                        baseline_device
+                )?;
+            },
+            ParsedEntries::Block5Values { headers, entries } => {
+                self.create_5values_block_table_ref(table,
+                        entries,
+                        headers,
+                        baseline_device
+                )?;
+            },
+            ParsedEntries::BinaryHash { name, sha256_sum } => {
+                self.create_key_value_table_ref(table,
+                        vec![(name, sha256_sum)],
+                        baseline_device
+                )?;
             },
-            _ => (),
         };
-        
+        Ok(true)
     }
     
 }
 
-mod yara_customizer{
+/// Header of the module report of the reference rows selected by 'stmt'
+/// (see 'select_module_rows()'): its key and 'values_count' value columns, then
+/// the expected values.
+fn module_header(stmt: &Statement,
+        values_count: usize) -> Vec<String>
+{
+    let columns: Vec<String> = (1..values_count + 2).map(|index| stmt.column_name(index).unwrap_or("setting_config").to_string())
+            .collect();
+    [
+        vec![String::from("file_name")],
+        columns.clone(),
+        columns[1..].iter().map(|x| format!("expected_{x}")).collect(),
+        vec![String::from("status"), String::from("yara_match"), String::from("yara_rulename"), String::from("severity"), String::from("frequency")],
+    ].concat()
+}
+
+/// Query of the rows of the reference 'table' of 'connx', selected by name:
+/// rowid, 'key_column', then the value columns.
+fn select_module_rows(connx: &ConnectionThreadSafe,
+        table: &str,
+        key_column: &str) -> Result<String, AndroidParserError>
+{
+    let stmt = connx.prepare(format!("SELECT * FROM '{table}' LIMIT 0"))?;
+    let values_columns: Vec<String> = stmt.column_names().iter()
+            .filter(|x| x.as_str() != key_column)
+            .map(|x| format!(", \"{x}\""))
+            .collect();
+    Ok(format!("SELECT rowid, \"{key_column}\"{} FROM '{table}'",
+            values_columns.concat()
+    ))
+}
+
+/// YARA rules discovery and compilation helpers.
+pub mod yara_customizer{
 
     use std::{fs::OpenOptions, path::Path, io::{BufRead, BufReader}};
     use yara::{Rules, Compiler};
     use regex::Regex;
     use console::style;
 
+    use super::AndroidParserError;
 
-    pub fn yara_rules_ingester(paths: Vec<String>) -> Rules
+
+    /// Compile every given YARA rules file into one ruleset.
+    /// Duplicated rules (by identifier), global rules and files that cannot
+    /// be compiled are skipped and counted in the final report.
+    /// Fails only if the remaining rules cannot be compiled together.
+    pub fn yara_rules_ingester(paths: Vec<String>) -> Result<Rules, AndroidParserError>
     {
         // let mut compiled_ruleset: Vec<Rules> = vec![];
         let mut concat_rules = String::new();
@@ -1343,7 +1459,7 @@ mod yara_customizer{
             Err(err) => panic!("{}", err),
         };
         // let mut compiler = Compiler::new().unwrap();
-        paths.into_iter().for_each(|each_path| {
+        for each_path in paths {
             // if let Ok(yara_compiler) = Compiler::new().unwrap().add_rules_file(each_path) {
             if let Ok(file_handler) = OpenOptions::new()
                     .read(true)
@@ -1355,18 +1471,13 @@ mod yara_customizer{
                 let mut crash_string = String::new();
                 let mut crash_ingest_rules: Vec<String> = vec![];
                 let mut valid_rule_flag: bool = false;
-                buf_reader.lines().into_iter().for_each(|line| {
+                buf_reader.lines().for_each(|line| {
                     match line {
                         Ok(l) => {
-                            let captures = match re.captures(l.as_str()) {
-                                Some(caps) => Some((caps.name("global").map_or("".to_string(), |m| String::from(m.as_str())),
-                                        caps.name("identifier").map_or("".to_string(), |m| String::from(m.as_str())))
-                                ),
-                                None => {
-                                    None
-                                },
-                            };
-                            let _ = match captures {
+                            let captures = re.captures(l.as_str()).map(|caps| (caps.name("global").map_or("".to_string(), |m| String::from(m.as_str())),
+                                    caps.name("identifier").map_or("".to_string(), |m| String::from(m.as_str())))
+                            );
+                            match captures {
                                 Some(x) => {
                                     if x.0.is_empty() {
                                         // Si pas global, je traite
@@ -1417,15 +1528,15 @@ mod yara_customizer{
                         },
                         Err(err) => println!("{} {}",
                                 style("[ERROR]").bold().red(),
-                                err.to_string()
+                                err
                         ),
                     };
                 });
-                let yara_compiler = Compiler::new().unwrap()
+                let yara_compiler = Compiler::new()?
                         .add_rules_str(&crash_string);
                 match yara_compiler {
                     Ok(yara_compiler) => {
-                        let _ = match yara_compiler.compile_rules() {
+                        match yara_compiler.compile_rules() {
                             Ok(_) => {
                                 concat_rules.push_str(format!("{}\n",
                                         crash_string).as_str()
@@ -1436,7 +1547,7 @@ mod yara_customizer{
                             },
                             Err(err) => {
                                 println!("{} on file => {}",
-                                        style(format!("[ERROR {}]", err.kind.to_string())).bold().dim().red(),
+                                        style(format!("[ERROR {}]", err.kind)).bold().dim().red(),
                                         each_path.as_str()
                                 );
                                 inval_rules_counter += 1;
@@ -1452,62 +1563,335 @@ mod yara_customizer{
                     },
                 };
             };
-        });
+        }
         println!("{3} Skipped {}/{2} rule(s) file(s) containing duplicated.\n{3} Skipped {}/{2} rule(s) file(s) containing error(s)",
                 style(skipped_rules_counter).bold().dim().red(),
                 style(inval_rules_counter).bold().dim().red(),
                 style(overall_yara_files).dim().yellow(),
                 style("[REPORT]").bold().dim().magenta(),
         );
-        let compiler = Compiler::new().unwrap();
-        let _ = match compiler.add_rules_str(concat_rules.as_str()) {
-            Ok(x) => {
-                let _ = match x.compile_rules() {
-                    Ok(z) => return z,
-                    Err(err) => panic!("{} {}",
-                            style("[ERROR]").bold().dim().red(),
-                            err.kind.to_string()),
-                };
-            }
-            Err(err) => panic!("{} {}",
-                    style("[ERROR]").bold().dim().red(),
-                    err.to_string()),
-        };
+        let compiler = Compiler::new()?;
+        Ok(compiler.add_rules_str(concat_rules.as_str())?
+                .compile_rules()?)
     }
 
-    pub fn yara_rules_finder(path:&Path) -> Vec<String>
+    /// Recursively find every '.yar' and '.yara' file under 'path'.
+    pub fn yara_rules_finder(path:&Path) -> Result<Vec<String>, AndroidParserError>
     {
-        let validator_flag: bool = match path.try_exists() {
-            Ok(x) => x,
-            Err(err) => panic!("{} {}",
-                    style("[ERROR]]").bold().dim().red(),
-                    err.to_string()),
-        };
         let mut yara_rules_vec: Vec<String> = vec![];
-        if validator_flag &&
-                path.is_dir() == true
+        if path.try_exists()? &&
+                path.is_dir()
         {
-            if let Ok(read_dir) = path.read_dir() {
-                read_dir.into_iter().for_each(|each_dir| {
-                    if let Ok(each_entry) = each_dir {
-                        if each_entry.file_type().unwrap().is_dir() {
-                            self::yara_rules_finder(each_entry.path().as_path())
-                                    .into_iter().for_each(|each_yara_rules_path|
-                            {
-                                yara_rules_vec.push(each_yara_rules_path);
-                            });
-                        }
-                        else if each_entry.file_type().unwrap().is_file() &&
-                                ( each_entry.path().extension().is_some_and(|x| x.to_str().unwrap() == "yar") || 
-                                each_entry.path().extension().is_some_and(|x| x.to_str().unwrap() == "yara") )
-                        {
-                            yara_rules_vec.push(each_entry.path().to_str().unwrap().to_string());
-                        }
-                    }
-                });
+            for each_entry in path.read_dir()? {
+                let each_entry = each_entry?;
+                if each_entry.file_type()?.is_dir() {
+                    yara_rules_vec.extend(self::yara_rules_finder(each_entry.path().as_path())?);
+                }
+                else if each_entry.file_type()?.is_file() &&
+                        ( each_entry.path().extension().is_some_and(|x| x == "yar") || 
+                        each_entry.path().extension().is_some_and(|x| x == "yara") )
+                {
+                    yara_rules_vec.push(each_entry.path().to_string_lossy().to_string());
+                }
             }
         }
-        yara_rules_vec
+        Ok(yara_rules_vec)
     }    
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{channel, Receiver};
+
+    /// A parser without reference nor YARA rules, its events sent to the returned
+    /// receiver, and a device of 'test_dir' compared against the reference built
+    /// by the SQL 'statements'.
+    fn compared_device(test_dir: &Path,
+            statements: &[&str]) -> (AndroidParser, DeviceContext, Receiver<Event>)
+    {
+        let dump_dir = test_dir.join("dump");
+        std::fs::create_dir_all(&dump_dir).unwrap();
+        let (sender, receiver) = channel();
+        let android_parser = AndroidParserBuilder::new(dump_dir.to_string_lossy().to_string(), dump_dir.to_string_lossy().to_string())
+                .embedded_rules(false)
+                .load_reference(false)
+                .output_dir(test_dir.join("reports").to_string_lossy().to_string())
+                .observer(sender)
+                .build()
+                .unwrap();
+        let connx = Arc::new(sqlite::Connection::open_thread_safe(":memory:").unwrap());
+        statements.iter().for_each(|x| connx.execute(x).unwrap());
+        let device = DeviceContext {
+            root: dump_dir,
+            wildcards: reference_json::load_wildcards(&connx),
+            connx,
+            seen_binaries: Mutex::new(HashSet::new()),
+            baseline_devices: 1,
+        };
+        (android_parser, device, receiver)
+    }
+
+    fn reported_findings(receiver: &Receiver<Event>) -> Vec<Finding> {
+        receiver.try_iter()
+                .filter_map(|x| match x {
+                    Event::Finding { finding, .. } => Some(finding),
+                    _ => None,
+                })
+                .collect()
+    }
+
+    #[test]
+    fn missing_rows_skip_matched_rows_and_differing_keys() {
+        let test_dir = std::env::temp_dir().join(format!("asc_missing_rows_{}", std::process::id()));
+        let (android_parser, device, receiver) = compared_device(&test_dir, &[
+            "CREATE TABLE 'getprop' (key TEXT, value TEXT)",
+            "INSERT INTO 'getprop' VALUES ('ro.debuggable', '0'), ('ro.secure', '1'), ('ro.build.type', 'user')",
+        ]);
+        let mut compared_rows = ComparedRows::default();
+        compared_rows.matched_rowids.insert(1);
+        compared_rows.differing_keys.insert(String::from("ro.secure"));
+        android_parser.report_missing_rows(&device, "getprop.txt", "getprop", "key", &compared_rows);
+
+        let findings = reported_findings(&receiver);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, FindingKind::Removed);
+        assert_eq!(findings[0].key, "ro.build.type");
+        assert!(findings[0].observed.is_empty());
+        assert_eq!(findings[0].expected, vec![vec![String::from("user")]]);
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn missing_rows_read_the_key_column_by_name() {
+        let test_dir = std::env::temp_dir().join(format!("asc_missing_rows_named_{}", std::process::id()));
+        let (android_parser, device, receiver) = compared_device(&test_dir, &[
+            "CREATE TABLE 'mount' (mountpoint TEXT, name TEXT, type TEXT)",
+            "INSERT INTO 'mount' VALUES ('/system', '/dev/block/dm-0', 'ext4')",
+        ]);
+        android_parser.report_missing_rows(&device, "mount.txt", "mount", "name", &ComparedRows::default());
+
+        let findings = reported_findings(&receiver);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].key, "/dev/block/dm-0");
+        assert_eq!(findings[0].expected, vec![vec![String::from("/system"), String::from("ext4")]]);
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn missing_rows_of_a_module_missing_from_the_reference_are_not_reported() {
+        let test_dir = std::env::temp_dir().join(format!("asc_missing_rows_none_{}", std::process::id()));
+        let (android_parser, device, receiver) = compared_device(&test_dir, &[]);
+        android_parser.report_missing_rows(&device, "lsmod.txt", "lsmod", "key", &ComparedRows::default());
+
+        assert!(reported_findings(&receiver).is_empty());
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    fn compare_getprop_row(android_parser: &AndroidParser,
+            device: &DeviceContext,
+            row: (&str, &str),
+            compared_rows: &mut ComparedRows) -> Option<Finding>
+    {
+        let mut stmt = device.connx.prepare(format!("{} WHERE key=:key", select_module_rows(&device.connx, "getprop", "key").unwrap())).unwrap();
+        android_parser.compare_row(device, "getprop.txt", &mut stmt, "getprop", (row.0.to_string(), vec![row.1.to_string()]), compared_rows)
+    }
+
+    #[test]
+    fn compare_row_records_a_matching_row() {
+        let test_dir = std::env::temp_dir().join(format!("asc_compare_row_match_{}", std::process::id()));
+        let (android_parser, device, receiver) = compared_device(&test_dir, &[
+            "CREATE TABLE 'getprop' (key TEXT, value TEXT)",
+            "INSERT INTO 'getprop' VALUES ('ro.debuggable', '0'), ('ro.secure', '1')",
+        ]);
+        let mut compared_rows = ComparedRows::default();
+
+        assert!(compare_getprop_row(&android_parser, &device, ("ro.secure", "1"), &mut compared_rows).is_none());
+        assert_eq!(compared_rows.matched_rowids, HashSet::from([2]));
+        assert!(compared_rows.differing_keys.is_empty());
+        assert!(reported_findings(&receiver).is_empty());
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn compare_row_returns_changed_and_added_rows_with_the_expected_values() {
+        let test_dir = std::env::temp_dir().join(format!("asc_compare_row_diff_{}", std::process::id()));
+        let (android_parser, device, _receiver) = compared_device(&test_dir, &[
+            "CREATE TABLE 'getprop' (key TEXT, value TEXT)",
+            "INSERT INTO 'getprop' VALUES ('ro.debuggable', '0'), ('ro.debuggable', '2')",
+        ]);
+        let mut compared_rows = ComparedRows::default();
+
+        let changed = compare_getprop_row(&android_parser, &device, ("ro.debuggable", "1"), &mut compared_rows).unwrap();
+        assert_eq!(changed.kind, FindingKind::Changed);
+        assert_eq!(changed.module, "getprop");
+        assert_eq!(changed.observed, vec![String::from("1")]);
+        assert_eq!(changed.expected, vec![vec![String::from("0")], vec![String::from("2")]]);
+
+        let added = compare_getprop_row(&android_parser, &device, ("ro.adb.secure", "0"), &mut compared_rows).unwrap();
+        assert_eq!(added.kind, FindingKind::Added);
+        assert!(added.expected.is_empty());
+
+        assert!(compared_rows.matched_rowids.is_empty());
+        assert_eq!(compared_rows.differing_keys, HashSet::from([String::from("ro.debuggable"), String::from("ro.adb.secure")]));
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn compare_row_accepts_a_wildcard_row() {
+        let test_dir = std::env::temp_dir().join(format!("asc_compare_row_wildcard_{}", std::process::id()));
+        let (android_parser, device, _receiver) = compared_device(&test_dir, &[
+            "CREATE TABLE 'getprop' (key TEXT, value TEXT)",
+            "INSERT INTO 'getprop' VALUES ('ro.build.date', 'Mon Jan 1 2024')",
+            "CREATE TABLE 'asc_reference_wildcards' (module TEXT, key TEXT, value TEXT)",
+            "INSERT INTO 'asc_reference_wildcards' VALUES ('getprop', 'ro.build.date', '*2025')",
+        ]);
+        let mut compared_rows = ComparedRows::default();
+
+        assert!(compare_getprop_row(&android_parser, &device, ("ro.build.date", "Tue Feb 4 2025"), &mut compared_rows).is_none());
+        assert!(compare_getprop_row(&android_parser, &device, ("ro.build.date", "Tue Feb 4 2026"), &mut compared_rows).is_some());
+        // the reference row is then not reported as removed
+        assert!(compared_rows.differing_keys.contains("ro.build.date"));
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn compare_key_3values_reports_changed_added_and_removed_rows() {
+        let test_dir = std::env::temp_dir().join(format!("asc_compare_3values_{}", std::process::id()));
+        let (android_parser, device, receiver) = compared_device(&test_dir, &[
+            "CREATE TABLE 'mount' (name TEXT, mountpoint TEXT, type TEXT, options TEXT)",
+            "INSERT INTO 'mount' VALUES ('/dev/block/dm-0', '/', 'ext4', 'ro'), ('/dev/block/dm-1', '/vendor', 'ext4', 'ro'), ('tmpfs', '/dev', 'tmpfs', 'rw')",
+        ]);
+        android_parser.compare_key_3values(&device,
+                String::from("mount.txt"),
+                String::from("mount"),
+                vec![
+                    (String::from("/dev/block/dm-0"), String::from("/"), String::from("ext4"), String::from("ro")),
+                    (String::from("/dev/block/dm-1"), String::from("/vendor"), String::from("ext4"), String::from("rw")),
+                    (String::from("/dev/block/loop0"), String::from("/apex"), String::from("ext4"), String::from("ro")),
+                ],
+                String::from("name")
+        ).unwrap();
+
+        let mut findings: Vec<(FindingKind, String)> = reported_findings(&receiver).into_iter()
+                .map(|x| (x.kind, x.key))
+                .collect();
+        findings.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(findings, vec![
+            (FindingKind::Changed, String::from("/dev/block/dm-1")),
+            (FindingKind::Added, String::from("/dev/block/loop0")),
+            (FindingKind::Removed, String::from("tmpfs")),
+        ]);
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn library_reference_failing_to_load_is_an_error_not_a_finding() {
+        let test_dir = std::env::temp_dir().join(format!("asc_library_load_failed_{}", std::process::id()));
+        let library_dir = test_dir.join("library");
+        let dump_dir = test_dir.join("dump");
+        std::fs::create_dir_all(&library_dir).unwrap();
+        std::fs::create_dir_all(&dump_dir).unwrap();
+        std::fs::write(dump_dir.join("getprop.txt"), "[ro.build.fingerprint]: [google/redfin/redfin:14/UQ1A]\n").unwrap();
+        let reference_path = library_dir.join("redfin.sqlite");
+        reference::write_metadata(&sqlite::Connection::open_thread_safe(&reference_path).unwrap(), &ReferenceMetadata {
+            build_fingerprint: String::from("google/redfin/redfin:14/UQ1A"),
+            ..Default::default()
+        }).unwrap();
+        let (sender, receiver) = channel();
+        let android_parser = AndroidParserBuilder::new(library_dir.to_string_lossy().to_string(), dump_dir.to_string_lossy().to_string())
+                .embedded_rules(false)
+                .output_dir(test_dir.join("reports").to_string_lossy().to_string())
+                .observer(sender)
+                .build()
+                .unwrap();
+        std::fs::write(&reference_path, "not a sqlite database").unwrap();
+        let summary = android_parser.go_parse().unwrap();
+
+        let events: Vec<Event> = receiver.try_iter().collect();
+        assert!(events.iter().any(|x| matches!(x,
+                Event::DeviceStarted { reference_match: ReferenceMatch::LoadFailed, .. }
+        )));
+        assert!(events.iter().any(|x| matches!(x, Event::Error { .. })));
+        assert!(!events.iter().any(|x| matches!(x, Event::Finding { .. })));
+        assert_eq!(summary.findings, 0);
+        assert_eq!(summary.errors, 1);
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    /// A fleet of 'debuggable.len()' devices under 'test_dir/fleet', each
+    /// with the 'ro.debuggable' value given and the same 'ro.secure'.
+    fn fleet_dumps(test_dir: &Path,
+            debuggable: &[&str]) -> PathBuf
+    {
+        let fleet_dir = test_dir.join("fleet");
+        debuggable.iter().enumerate().for_each(|(index, value)| {
+            let info_dir = fleet_dir.join(format!("d{}", index + 1)).join("info");
+            std::fs::create_dir_all(&info_dir).unwrap();
+            std::fs::write(info_dir.join("getprop.txt"),
+                    format!("[ro.debuggable]: [{value}]\n[ro.secure]: [1]\n")
+            ).unwrap();
+        });
+        fleet_dir
+    }
+
+    #[test]
+    fn fleet_outliers_are_the_values_of_a_minority_of_devices() {
+        let test_dir = std::env::temp_dir().join(format!("asc_fleet_outliers_{}", std::process::id()));
+        let fleet_dir = fleet_dumps(&test_dir, &["0", "0", "1", "0", "0"]);
+        let (sender, receiver) = channel();
+        let android_parser = AndroidParserBuilder::new(fleet_dir.to_string_lossy().to_string(), fleet_dir.to_string_lossy().to_string())
+                .embedded_rules(false)
+                .output_dir(test_dir.join("reports").to_string_lossy().to_string())
+                .observer(sender)
+                .build()
+                .unwrap();
+        assert_eq!(android_parser.go_fleet().unwrap().devices, 5);
+        android_parser.go_parse().unwrap();
+
+        let findings = reported_findings(&receiver);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, FindingKind::Rare);
+        assert!(findings[0].device.ends_with("d3"));
+        let outliers = android_parser.outliers();
+        assert_eq!(outliers.len(), 1);
+        assert_eq!((outliers[0].module.as_str(), outliers[0].key.as_str()), ("getprop", "ro.debuggable"));
+        assert_eq!(outliers[0].values, vec![String::from("1")]);
+        assert_eq!((outliers[0].frequency.seen, outliers[0].frequency.baseline), (1, 5));
+        assert_eq!(outliers[0].devices, vec![findings[0].device.clone()]);
+        let outliers_report = std::fs::read_to_string(android_parser.report_dir().join("reported_outliers.csv")).unwrap();
+        assert_eq!(outliers_report.lines().count(), 2);
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn fleet_outliers_need_a_minority() {
+        let test_dir = std::env::temp_dir().join(format!("asc_fleet_majority_{}", std::process::id()));
+        let fleet_dir = fleet_dumps(&test_dir, &["0", "1", "1", "0"]);
+        let android_parser = AndroidParserBuilder::new(fleet_dir.to_string_lossy().to_string(), fleet_dir.to_string_lossy().to_string())
+                .embedded_rules(false)
+                .output_dir(test_dir.join("reports").to_string_lossy().to_string())
+                .build()
+                .unwrap();
+        android_parser.go_fleet().unwrap();
+        let summary = android_parser.go_parse().unwrap();
+        assert_eq!(summary.findings, 0);
+        assert!(android_parser.outliers().is_empty());
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn fleet_baseline_cannot_replace_a_saved_reference() {
+        let test_dir = std::env::temp_dir().join(format!("asc_fleet_saved_{}", std::process::id()));
+        let fleet_dir = fleet_dumps(&test_dir, &["0", "1"]);
+        let reference_path = test_dir.join("golden.sqlite");
+        reference::write_metadata(&sqlite::Connection::open_thread_safe(&reference_path).unwrap(), &ReferenceMetadata::default()).unwrap();
+        let android_parser = AndroidParserBuilder::new(reference_path.to_string_lossy().to_string(), fleet_dir.to_string_lossy().to_string())
+                .embedded_rules(false)
+                .output_dir(test_dir.join("reports").to_string_lossy().to_string())
+                .build()
+                .unwrap();
+        assert!(matches!(android_parser.go_fleet(), Err(AndroidParserError::Io(err)) if err.kind() == ErrorKind::InvalidInput));
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+}
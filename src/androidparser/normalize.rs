@@ -0,0 +1,400 @@
+//! Normalizers
+//!
+//! Cosmetic differences removed from the parsed values before they reach
+//! the reference DB or the comparison, the same way in 'go_ref()' and
+//! 'go_parse()': whitespace, order of option lists, volatile columns,
+//! size units and numeric tolerances. Each normalizer applies to the
+//! columns of the modules (reference tables) matching it.
+//!
+//! ```toml
+//! [[normalizer]]
+//! module = '^mount$'
+//! column = "options"
+//! type = "sort_list"
+//! separator = ","
+//! ```
+
+use std::path::Path;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{AndroidParserError, ParsedEntries};
+
+/// Column names of 'ParsedEntries::KeyValue'.
+const KEY_VALUE_COLUMNS: [&str; 2] = ["key", "value"];
+/// Column names of 'ParsedEntries::KeyXValues'.
+const KEY_XVALUES_COLUMNS: [&str; 2] = ["key", "values"];
+
+/// How a value is normalized.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Normalization {
+    /// Remove leading and trailing whitespace, and collapse inner whitespace to a single space.
+    Trim,
+    Lowercase,
+    /// Sort the items of a list, as the comma separated options of 'mount'.
+    SortList {
+        #[serde(default = "default_separator")]
+        separator: String,
+    },
+    /// Empty the value, for a volatile column as the PIDs of 'ps'.
+    Blank,
+    /// Convert a size as '1.5G' or '512K' into bytes, in powers of 1024.
+    Size,
+    /// Round a number to a multiple of 'step', so close values compare equal.
+    Round {
+        step: f64,
+    },
+    /// Replace every match of the regex 'pattern' by 'replacement', as timestamps.
+    Replace {
+        pattern: String,
+        #[serde(default)]
+        replacement: String,
+    },
+}
+
+fn default_separator() -> String {
+    String::from(",")
+}
+
+/// A normalization of the 'column' of the modules matching 'module'.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NormalizerDefinition {
+    /// Regex matched against the module (reference table) of the values.
+    pub module: String,
+    /// Column of the values: a header of the module, 'key' and 'value'
+    /// for key/value modules (declared parsers with 1 value included),
+    /// 'key' and 'values' for 'id'. Every column if not set.
+    #[serde(default)]
+    pub column: Option<String>,
+    #[serde(flatten)]
+    pub normalization: Normalization,
+}
+
+/// A 'NormalizerDefinition' with its regexes compiled.
+#[derive(Clone, Debug)]
+struct Normalizer {
+    module: Regex,
+    column: Option<String>,
+    normalization: Normalization,
+    /// Regex of 'Normalization::Replace'.
+    pattern: Option<Regex>,
+}
+
+impl Normalizer {
+    fn new(definition: &NormalizerDefinition) -> Result<Self, AndroidParserError> {
+        let module = match Regex::new(&definition.module) {
+            Ok(x) => x,
+            Err(err) => return Err(AndroidParserError::parse(&definition.module,
+                    &format!("Invalid normalizer module regex => {err}"))
+            ),
+        };
+        let pattern = match &definition.normalization {
+            Normalization::Replace { pattern, .. } => match Regex::new(pattern) {
+                Ok(x) => Some(x),
+                Err(err) => return Err(AndroidParserError::parse(pattern,
+                        &format!("Invalid normalizer pattern => {err}"))
+                ),
+            },
+            _ => None,
+        };
+        Ok(Self {
+            module,
+            column: definition.column.clone(),
+            normalization: definition.normalization.clone(),
+            pattern,
+        })
+    }
+
+    fn apply(&self,
+            value: String) -> String
+    {
+        match &self.normalization {
+            Normalization::Trim => value.split_whitespace().collect::<Vec<&str>>().join(" "),
+            Normalization::Lowercase => value.to_lowercase(),
+            Normalization::SortList { separator } => {
+                let mut items: Vec<&str> = value.split(separator.as_str()).collect();
+                items.sort_unstable();
+                items.join(separator)
+            },
+            Normalization::Blank => String::new(),
+            Normalization::Size => match size_in_bytes(&value) {
+                Some(x) => x.to_string(),
+                None => value,
+            },
+            Normalization::Round { step } => match value.trim().parse::<f64>() {
+                Ok(x) if *step > 0.0 => {
+                    let rounded = (x / step).round() * step;
+                    match rounded.fract() == 0.0 {
+                        true => format!("{rounded:.0}"),
+                        false => rounded.to_string(),
+                    }
+                },
+                _ => value,
+            },
+            Normalization::Replace { replacement, .. } => match &self.pattern {
+                Some(x) => x.replace_all(&value, replacement.as_str()).to_string(),
+                None => value,
+            },
+        }
+    }
+}
+
+/// Bytes of a size as '1.5G', '512K', '4.0KiB' or '100', None if it is not a size.
+fn size_in_bytes(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let number_end = value.find(|x: char| !(x.is_ascii_digit() || x == '.')).unwrap_or(value.len());
+    let number: f64 = value[..number_end].parse().ok()?;
+    let exponent = match value[number_end..].trim().to_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        _ => return None,
+    };
+    Some((number * 1024_f64.powi(exponent)).round() as u64)
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NormalizerFile {
+    builtin: Option<bool>,
+    #[serde(rename = "normalizer")]
+    normalizers: Vec<NormalizerDefinition>,
+}
+
+/// Normalizers of the parsed values, applied in their order.
+#[derive(Clone, Debug)]
+pub struct Normalizers {
+    normalizers: Vec<Normalizer>,
+}
+
+impl Default for Normalizers {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl Normalizers {
+    /// No normalization at all, the values being compared as parsed.
+    pub fn empty() -> Self {
+        Self { normalizers: vec![] }
+    }
+
+    /// Whitespace trimmed everywhere, 'mount' options sorted, 'ps' PIDs blanked,
+    /// 'df_ah' sizes converted into bytes and its used space blanked.
+    pub fn builtin() -> Self {
+        let mut normalizers = Self::empty();
+        [
+            NormalizerDefinition { module: String::from(".*"), column: None, normalization: Normalization::Trim },
+            NormalizerDefinition { module: String::from("^mount$"), column: Some(String::from("options")), normalization: Normalization::SortList { separator: default_separator() } },
+            NormalizerDefinition { module: String::from("^ps$"), column: Some(String::from("pid")), normalization: Normalization::Blank },
+            NormalizerDefinition { module: String::from("^ps$"), column: Some(String::from("ppid")), normalization: Normalization::Blank },
+            NormalizerDefinition { module: String::from("^df_ah$"), column: Some(String::from("size")), normalization: Normalization::Size },
+            NormalizerDefinition { module: String::from("^df_ah$"), column: Some(String::from("used")), normalization: Normalization::Blank },
+        ].iter().for_each(|x| {
+            if let Ok(normalizer) = Normalizer::new(x) {
+                normalizers.normalizers.push(normalizer);
+            }
+        });
+        normalizers
+    }
+
+    /// Read the normalizers of the TOML file 'path', added after the built-in
+    /// ones unless 'builtin = false'.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use android_sanity_checker::androidparser::{AndroidParserBuilder, Normalizers};
+    ///
+    /// let normalizers = Normalizers::from_file(Path::new("C:/asc/normalizers.toml")).unwrap();
+    /// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+    ///         .normalizers(normalizers)
+    ///         .build()
+    ///         .unwrap();
+    /// ```
+    pub fn from_file(path: &Path) -> Result<Self, AndroidParserError> {
+        let content = std::fs::read_to_string(path)?;
+        let normalizer_file: NormalizerFile = match toml::from_str(&content) {
+            Ok(x) => x,
+            Err(err) => return Err(AndroidParserError::parse(path.to_str().unwrap_or_default(),
+                    &err.to_string())
+            ),
+        };
+        let mut normalizers = match normalizer_file.builtin {
+            Some(false) => Self::empty(),
+            _ => Self::builtin(),
+        };
+        for definition in normalizer_file.normalizers.iter() {
+            normalizers.add(definition)?;
+        }
+        Ok(normalizers)
+    }
+
+    /// Add 'definition' after the normalizers already added.
+    pub fn add(&mut self,
+            definition: &NormalizerDefinition) -> Result<(), AndroidParserError>
+    {
+        self.normalizers.push(Normalizer::new(definition)?);
+        Ok(())
+    }
+
+    /// Fingerprint of the normalizers (SHA-256 of their definitions, in their
+    /// order), recorded into the metadata of a reference built with them.
+    pub fn fingerprint(&self) -> String {
+        let definitions: Vec<NormalizerDefinition> = self.normalizers.iter()
+                .map(|x| NormalizerDefinition {
+                    module: x.module.as_str().to_string(),
+                    column: x.column.clone(),
+                    normalization: x.normalization.clone(),
+                })
+                .collect();
+        sha256::digest(serde_json::to_string(&definitions).unwrap_or_default())
+    }
+
+    /// Normalizers of each of the 'columns' of 'module'.
+    fn column_normalizers(&self,
+            module: &str,
+            columns: &[&str]) -> Vec<Vec<&Normalizer>>
+    {
+        let module_normalizers: Vec<&Normalizer> = self.normalizers.iter()
+                .filter(|x| x.module.is_match(module))
+                .collect();
+        columns.iter()
+                .map(|column| module_normalizers.iter()
+                        .filter(|x| x.column.as_ref().is_none_or(|y| y == column))
+                        .copied()
+                        .collect()
+                )
+                .collect()
+    }
+
+    /// Normalize the values of 'entries' of the module (reference table) 'module'.
+    pub fn normalize(&self,
+            module: &str,
+            entries: ParsedEntries) -> ParsedEntries
+    {
+        let apply = |normalizers: &[&Normalizer], value: String| -> String {
+            normalizers.iter().fold(value, |x, y| y.apply(x))
+        };
+        match entries {
+            ParsedEntries::KeyValue(entries) => {
+                let columns = self.column_normalizers(module, &KEY_VALUE_COLUMNS);
+                ParsedEntries::KeyValue(entries.into_iter()
+                        .map(|(x, y)| (apply(&columns[0], x), apply(&columns[1], y)))
+                        .collect()
+                )
+            },
+            ParsedEntries::KeyXValues(entries) => {
+                let columns = self.column_normalizers(module, &KEY_XVALUES_COLUMNS);
+                ParsedEntries::KeyXValues(entries.into_iter()
+                        .map(|x| x.into_iter()
+                                .map(|(y, z)| (apply(&columns[0], y), z.into_iter().map(|v| apply(&columns[1], v)).collect()))
+                                .collect()
+                        )
+                        .collect()
+                )
+            },
+            ParsedEntries::Key3Values { headers, entries } => {
+                let columns = self.column_normalizers(module, &[headers.0.as_str(), headers.1.as_str(), headers.2.as_str(), headers.3.as_str()]);
+                let entries = entries.into_iter()
+                        .map(|(w, x, y, z)| (apply(&columns[0], w), apply(&columns[1], x), apply(&columns[2], y), apply(&columns[3], z)))
+                        .collect();
+                ParsedEntries::Key3Values { headers, entries }
+            },
+            ParsedEntries::Block5Values { headers, entries } => {
+                let columns = self.column_normalizers(module, &[headers.0.as_str(), headers.1.as_str(), headers.2.as_str(), headers.3.as_str(), headers.4.as_str()]);
+                let entries = entries.into_iter()
+                        .map(|[v, w, x, y, z]| [apply(&columns[0], v), apply(&columns[1], w), apply(&columns[2], x), apply(&columns[3], y), apply(&columns[4], z)])
+                        .collect();
+                ParsedEntries::Block5Values { headers, entries }
+            },
+            ParsedEntries::BinaryHash { name, sha256_sum } => ParsedEntries::BinaryHash { name, sha256_sum },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalizers(definitions: &[NormalizerDefinition]) -> Normalizers {
+        let mut normalizers = Normalizers::empty();
+        definitions.iter().for_each(|x| normalizers.add(x).unwrap());
+        normalizers
+    }
+
+    #[test]
+    fn size_in_bytes_units() {
+        assert_eq!(size_in_bytes("100"), Some(100));
+        assert_eq!(size_in_bytes("512K"), Some(512 * 1024));
+        assert_eq!(size_in_bytes("1.5G"), Some(1610612736));
+        assert_eq!(size_in_bytes("3584M"), size_in_bytes("3.5G"));
+        assert_eq!(size_in_bytes("4.0KiB"), Some(4096));
+        assert_eq!(size_in_bytes("4kb"), Some(4096));
+        assert_eq!(size_in_bytes(" 2 T "), Some(2 * 1024_u64.pow(4)));
+        assert_eq!(size_in_bytes("0"), Some(0));
+    }
+
+    #[test]
+    fn size_in_bytes_not_a_size() {
+        assert_eq!(size_in_bytes(""), None);
+        assert_eq!(size_in_bytes("G"), None);
+        assert_eq!(size_in_bytes("12X"), None);
+        assert_eq!(size_in_bytes("1.2.3M"), None);
+        assert_eq!(size_in_bytes("98%"), None);
+    }
+
+    #[test]
+    fn mount_options_sorted() {
+        let entries = ParsedEntries::Key3Values {
+            headers: (String::from("name"), String::from("mountpoint"), String::from("type"), String::from("options")),
+            entries: vec![(String::from("/dev/block/dm-0"), String::from("/"), String::from("ext4"), String::from("ro,seclabel,relatime"))],
+        };
+        let normalized = Normalizers::builtin().normalize("mount", entries.clone());
+        assert_eq!(normalized.into_rows(), vec![(String::from("/dev/block/dm-0"), vec![String::from("/"), String::from("ext4"), String::from("relatime,ro,seclabel")])]);
+        let normalized = Normalizers::builtin().normalize("mounts", entries.clone());
+        assert_eq!(normalized, entries);
+        let normalized = Normalizers::empty().normalize("mount", entries.clone());
+        assert_eq!(normalized, entries);
+    }
+
+    #[test]
+    fn sort_list_separator() {
+        let normalizers = normalizers(&[NormalizerDefinition {
+            module: String::from("^getprop$"),
+            column: Some(String::from("value")),
+            normalization: Normalization::SortList { separator: String::from(" ") },
+        }]);
+        let entries = ParsedEntries::KeyValue(vec![(String::from("b a"), String::from("c b a"))]);
+        assert_eq!(normalizers.normalize("getprop", entries), ParsedEntries::KeyValue(vec![(String::from("b a"), String::from("a b c"))]));
+    }
+
+    #[test]
+    fn df_ah_sizes_builtin() {
+        let entries = ParsedEntries::Key3Values {
+            headers: (String::from("filesystem"), String::from("mountpoint"), String::from("size"), String::from("used")),
+            entries: vec![(String::from("tmpfs"), String::from("/dev"), String::from("1.5G"), String::from("1.0M"))],
+        };
+        assert_eq!(Normalizers::builtin().normalize("df_ah", entries).into_rows(),
+                vec![(String::from("tmpfs"), vec![String::from("/dev"), String::from("1610612736"), String::new()])]
+        );
+    }
+
+    #[test]
+    fn fingerprint_of_definitions() {
+        assert_eq!(Normalizers::builtin().fingerprint(), Normalizers::default().fingerprint());
+        assert_ne!(Normalizers::builtin().fingerprint(), Normalizers::empty().fingerprint());
+        let round = |step: f64| NormalizerDefinition {
+            module: String::from("^lsmod$"),
+            column: None,
+            normalization: Normalization::Round { step },
+        };
+        assert_eq!(normalizers(&[round(10.0)]).fingerprint(), normalizers(&[round(10.0)]).fingerprint());
+        assert_ne!(normalizers(&[round(10.0)]).fingerprint(), normalizers(&[round(5.0)]).fingerprint());
+    }
+}
@@ -0,0 +1,64 @@
+//! Outliers
+//!
+//! Values of a key held by only one or a small minority of the dumps of a
+//! baseline, gathered from the 'rare' findings of the devices. With a fleet
+//! baseline (see 'go_fleet()'), built from the analyzed devices themselves,
+//! they surface the deviating devices without any white reference.
+
+use std::{io::Error, path::Path};
+use serde::{Deserialize, Serialize};
+
+use super::{CsvWriter, Finding, ValueFrequency, findings::EXPECTED_SEPARATOR};
+
+/// A rare value of a key, and the analyzed devices holding it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Outlier {
+    pub module: String,
+    pub key: String,
+    pub values: Vec<String>,
+    /// How often the values were seen in the baseline.
+    pub frequency: ValueFrequency,
+    /// Root directories of the devices holding the values.
+    pub devices: Vec<String>,
+}
+
+impl Outlier {
+    /// Outlier of the 'rare' 'finding', None if it has no frequency.
+    pub(crate) fn from_finding(finding: &Finding) -> Option<Self> {
+        Some(Self {
+            module: finding.module.clone(),
+            key: finding.key.clone(),
+            values: finding.observed.clone(),
+            frequency: finding.frequency?,
+            devices: vec![finding.device.clone()],
+        })
+    }
+}
+
+/// Sort 'outliers' from the rarest, then by module and key, and their devices.
+pub(crate) fn rank_outliers(outliers: &mut [Outlier]) {
+    outliers.iter_mut().for_each(|x| x.devices.sort());
+    outliers.sort_by(|a, b| (a.frequency.seen, &a.module, &a.key, &a.values).cmp(&(b.frequency.seen, &b.module, &b.key, &b.values)));
+}
+
+/// Write the already ranked 'outliers' into the CSV report 'path'.
+pub(crate) fn write_outliers(path: &Path,
+        outliers: &[Outlier],
+        delimiter: char) -> Result<(), Error>
+{
+    let mut csv_writer = CsvWriter::create(path,
+            &["module", "key", "values", "seen", "baseline", "devices"],
+            delimiter
+    )?;
+    for outlier in outliers.iter() {
+        csv_writer.write_record(&[
+            outlier.module.clone(),
+            outlier.key.clone(),
+            outlier.values.join(" "),
+            outlier.frequency.seen.to_string(),
+            outlier.frequency.baseline.to_string(),
+            outlier.devices.join(EXPECTED_SEPARATOR),
+        ])?;
+    }
+    csv_writer.flush()
+}
@@ -0,0 +1,278 @@
+//! Parsers
+//!
+//! A parser recognizes a dump file by its path and extracts its entries
+//! in one of the shapes the reference DB knows. The same registry of
+//! parsers is used to build the reference and to analyze devices, and
+//! other crates can register their own parsers for in-house dump files.
+
+use std::{io::BufRead, path::Path, sync::Arc};
+use serde::{Deserialize, Serialize};
+
+use super::{AndroidParserError, DumpRecords, builtin_parsers};
+
+/// Entries extracted from a dump file, each shape being referenced
+/// and compared its own way.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParsedEntries {
+    /// One value per key ('getprop', 'settings_*'...).
+    KeyValue(Vec<(String, String)>),
+    /// Many values per key ('id').
+    KeyXValues(Vec<Vec<(String, Vec<String>)>>),
+    /// Three values per key ('mount', 'ps'). The first header is the key column.
+    Key3Values {
+        headers: (String, String, String, String),
+        entries: Vec<(String, String, String, String)>,
+    },
+    /// Blocks of five values ('pm_list_permissions-f'). The first header is the key column.
+    Block5Values {
+        headers: (String, String, String, String, String),
+        entries: Vec<[String; 5]>,
+    },
+    /// SHA256 sum of a binary of '/system/bin'.
+    BinaryHash {
+        name: String,
+        sha256_sum: String,
+    },
+}
+
+impl ParsedEntries {
+    /// Rows of the entries, as referenced: the key and the values beside it,
+    /// one row per value for 'KeyXValues'.
+    pub fn into_rows(self) -> Vec<(String, Vec<String>)> {
+        match self {
+            ParsedEntries::KeyValue(entries) => entries.into_iter()
+                    .map(|(x, y)| (x, vec![y]))
+                    .collect(),
+            ParsedEntries::KeyXValues(entries) => entries.into_iter()
+                    .flatten()
+                    .flat_map(|(x, y)| y.into_iter().map(move |z| (x.clone(), vec![z])))
+                    .collect(),
+            ParsedEntries::Key3Values { entries, .. } => entries.into_iter()
+                    .map(|(w, x, y, z)| (w, vec![x, y, z]))
+                    .collect(),
+            ParsedEntries::Block5Values { entries, .. } => entries.into_iter()
+                    .map(|[v, w, x, y, z]| (v, vec![w, x, y, z]))
+                    .collect(),
+            ParsedEntries::BinaryHash { name, sha256_sum } => vec![(name, vec![sha256_sum])],
+        }
+    }
+}
+
+/// A dump file parser.
+///
+/// # Exemple
+///
+/// ```no_run
+/// use std::{io::BufRead, path::Path};
+/// use android_sanity_checker::androidparser::{dump_name, AndroidParserBuilder, AndroidParserError, ParsedEntries, Parser};
+///
+/// struct LsmodParser;
+///
+/// impl Parser for LsmodParser {
+///     fn name(&self) -> &str { "lsmod" }
+///     fn matches(&self, path: &Path) -> bool { dump_name(path).is_some_and(|x| x == "lsmod") }
+///     fn parse(&self, _path: &Path, read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError> {
+///         Ok(ParsedEntries::KeyValue(read_buffer.lines()
+///                 .map_while(Result::ok)
+///                 .filter_map(|x| x.split_once(' ').map(|(y, z)| (y.to_string(), z.to_string())))
+///                 .collect()))
+///     }
+/// }
+///
+/// let mut android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+///         .build()
+///         .unwrap();
+/// android_parser.register_parser(LsmodParser);
+/// ```
+pub trait Parser: Send + Sync {
+    /// Name of the parser.
+    fn name(&self) -> &str;
+
+    /// Whether the parser handles the dump file 'path'.
+    fn matches(&self,
+            path: &Path) -> bool;
+
+    /// Reference table of the dump file 'path': its name up to the first '.'.
+    fn table_name(&self,
+            path: &Path) -> String
+    {
+        path.file_name()
+                .map_or(String::new(), |x| x.to_string_lossy().split('.').next().unwrap_or_default().to_string())
+    }
+
+    /// Columns of the reference table 'table' if the parser creates it, the
+    /// first one being the key, checked when a JSON reference is imported.
+    /// None if the table is not its own, or its columns are only known once parsed.
+    fn columns(&self,
+            _table: &str) -> Option<Vec<String>>
+    {
+        None
+    }
+
+    /// Extract the entries of the dump file 'path', read from 'read_buffer'.
+    fn parse(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>;
+
+    /// Typed records of the dump file 'path', read from 'read_buffer'.
+    /// The built-in parsers return their own records, the others their
+    /// entries as 'DumpRecords::Entries'.
+    fn records(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        Ok(DumpRecords::Entries(self.parse(path, read_buffer)?))
+    }
+}
+
+/// Parsers tried on every file of the reference and of the analyzed devices.
+/// The last registered parser matching a file handles it, so a registered
+/// parser takes precedence over the built-in ones.
+#[derive(Clone)]
+pub struct ParserRegistry {
+    parsers: Vec<Arc<dyn Parser>>,
+}
+
+impl ParserRegistry {
+    /// A registry without any parser.
+    pub fn empty() -> Self {
+        Self { parsers: vec![] }
+    }
+
+    /// A registry of the built-in parsers: getprop, settings, df_ah, services,
+    /// id, mount, ps, pm_list and '/system/bin' binaries.
+    pub fn builtin() -> Self {
+        let mut registry = Self::empty();
+        registry.register(builtin_parsers::BinaryParser);
+        registry.register(builtin_parsers::GetpropParser);
+        registry.register(builtin_parsers::SettingsParser);
+        registry.register(builtin_parsers::DfAhParser);
+        registry.register(builtin_parsers::ServicesParser);
+        registry.register(builtin_parsers::IdParser);
+        registry.register(builtin_parsers::MountParser);
+        registry.register(builtin_parsers::PsParser);
+        registry.register(builtin_parsers::PmListParser);
+        registry.register(builtin_parsers::PermissionsListParser);
+        registry
+    }
+
+    pub fn register<P: Parser + 'static>(&mut self,
+            parser: P)
+    {
+        self.parsers.push(Arc::new(parser));
+    }
+
+    /// The parser handling the dump file 'path', if any.
+    pub fn find(&self,
+            path: &Path) -> Option<&dyn Parser>
+    {
+        self.find_enabled(path, |_| true)
+    }
+
+    /// The parser handling the dump file 'path' among the parsers whose name
+    /// is 'enabled', so a disabled parser falls back to the one it overrides.
+    pub fn find_enabled(&self,
+            path: &Path,
+            enabled: impl Fn(&str) -> bool) -> Option<&dyn Parser>
+    {
+        self.parsers.iter()
+                .rev()
+                .filter(|x| enabled(x.name()))
+                .find(|x| x.matches(path))
+                .map(|x| x.as_ref())
+    }
+
+    /// Columns of the reference table 'table', given by the last registered
+    /// parser knowing them, None if no parser does.
+    pub fn columns(&self,
+            table: &str) -> Option<Vec<String>>
+    {
+        self.parsers.iter()
+                .rev()
+                .find_map(|x| x.columns(table))
+    }
+
+    /// Typed records of the dump file 'path', read from 'read_buffer',
+    /// by the parser handling it.
+    pub fn records(&self,
+            path: &Path,
+            read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+    {
+        match self.find(path) {
+            Some(x) => x.records(path, read_buffer),
+            None => Err(AndroidParserError::parse(path.to_str().unwrap_or_default(),
+                    "No parser handles this file")
+            ),
+        }
+    }
+
+    /// Names of the registered parsers.
+    pub fn names(&self) -> Vec<&str> {
+        self.parsers.iter().map(|x| x.name()).collect()
+    }
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Name of the '.txt' dump file 'path' up to the first '.', as 'getprop'
+/// for 'getprop.txt'. None if 'path' is not a '.txt' file.
+pub fn dump_name(path: &Path) -> Option<String> {
+    match path.extension().is_some_and(|x| x == "txt") {
+        true => path.file_name()
+                .map(|x| x.to_string_lossy().split('.').next().unwrap_or_default().to_string()),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 'getprop.txt' parser overriding the built-in one, every line being a key.
+    struct RawGetpropParser;
+
+    impl Parser for RawGetpropParser {
+        fn name(&self) -> &str {
+            "raw_getprop"
+        }
+
+        fn matches(&self,
+                path: &Path) -> bool
+        {
+            dump_name(path).is_some_and(|x| x == "getprop")
+        }
+
+        fn parse(&self,
+                _path: &Path,
+                read_buffer: &mut dyn BufRead) -> Result<ParsedEntries, AndroidParserError>
+        {
+            Ok(ParsedEntries::KeyValue(read_buffer.lines()
+                    .map_while(Result::ok)
+                    .map(|x| (x, String::new()))
+                    .collect()))
+        }
+    }
+
+    #[test]
+    fn registered_parser_overrides_the_builtin_one() {
+        let mut registry = ParserRegistry::builtin();
+        registry.register(RawGetpropParser);
+        assert_eq!(registry.find(Path::new("/dump/getprop.txt")).map(|x| x.name()), Some("raw_getprop"));
+        assert_eq!(registry.find(Path::new("/dump/mount.txt")).map(|x| x.name()), Some("mount"));
+        assert!(registry.find(Path::new("/dump/unknown.txt")).is_none());
+    }
+
+    #[test]
+    fn disabled_parser_falls_back_to_the_overridden_one() {
+        let mut registry = ParserRegistry::builtin();
+        registry.register(RawGetpropParser);
+        let path = Path::new("/dump/getprop.txt");
+        assert_eq!(registry.find_enabled(path, |x| x != "raw_getprop").map(|x| x.name()), Some("getprop"));
+        assert!(registry.find_enabled(path, |x| x == "mount").is_none());
+    }
+}
@@ -0,0 +1,253 @@
+//! Records
+//!
+//! Typed records of the dump files handled by the built-in parsers,
+//! so other tools can reuse the parsing without running a comparison.
+
+use std::{io::{BufRead, BufReader}, fs::File, path::Path};
+use serde::{Deserialize, Serialize};
+
+use super::{AndroidParserError, ParsedEntries, ParserRegistry};
+
+/// A system property of 'getprop.txt'.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Property {
+    pub key: String,
+    pub value: String,
+}
+
+/// A setting of 'settings_*.txt' or a variable of 'printenv.txt'.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+}
+
+/// A filesystem of 'df_ah.txt', where it is mounted and its sizes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskUsageEntry {
+    pub filesystem: String,
+    pub mounted_on: String,
+    /// Size of the filesystem, as printed: '3.5G', '512K'...
+    pub size: String,
+    /// Used space of the filesystem, as printed.
+    pub used: String,
+}
+
+/// A service of 'services.txt' and its interface.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceEntry {
+    pub name: String,
+    pub interface: String,
+}
+
+/// A field of 'id.txt' ('uid', 'gid' or 'groups') and its values.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdEntry {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+/// A mount of 'mount.txt'.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MountEntry {
+    pub device: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub options: String,
+}
+
+/// A process of 'ps.txt'.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessEntry {
+    pub user: String,
+    pub pid: String,
+    pub ppid: String,
+    pub command: String,
+}
+
+/// A line of 'pm_list_*.txt', as 'package:com.android.phone'.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageListEntry {
+    pub kind: String,
+    pub name: String,
+}
+
+/// A permission of 'pm_list_permissions-f.txt'.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionDefinition {
+    pub permission: String,
+    pub package: String,
+    pub label: String,
+    pub description: String,
+    pub protection_level: String,
+}
+
+/// A binary of '/system/bin' and its SHA256 sum.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinaryEntry {
+    pub name: String,
+    pub sha256_sum: String,
+}
+
+/// Records of a dump file, by kind of dump.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "records", rename_all = "snake_case")]
+pub enum DumpRecords {
+    Properties(Vec<Property>),
+    Settings(Vec<Setting>),
+    DiskUsage(Vec<DiskUsageEntry>),
+    Services(Vec<ServiceEntry>),
+    Ids(Vec<IdEntry>),
+    Mounts(Vec<MountEntry>),
+    Processes(Vec<ProcessEntry>),
+    Packages(Vec<PackageListEntry>),
+    Permissions(Vec<PermissionDefinition>),
+    Binary(BinaryEntry),
+    /// Entries of a parser without typed records (declared or registered parsers).
+    Entries(ParsedEntries),
+}
+
+impl From<DumpRecords> for ParsedEntries {
+    fn from(records: DumpRecords) -> Self {
+        match records {
+            DumpRecords::Properties(x) => ParsedEntries::KeyValue(x.into_iter().map(|y| (y.key, y.value)).collect()),
+            DumpRecords::Settings(x) => ParsedEntries::KeyValue(x.into_iter().map(|y| (y.key, y.value)).collect()),
+            DumpRecords::DiskUsage(x) => ParsedEntries::Key3Values {
+                headers: ( "filesystem".to_string(),
+                        "mountpoint".to_string(),
+                        "size".to_string(),
+                        "used".to_string()
+                ),
+                entries: x.into_iter().map(|y| (y.filesystem, y.mounted_on, y.size, y.used)).collect(),
+            },
+            DumpRecords::Services(x) => ParsedEntries::KeyValue(x.into_iter().map(|y| (y.name, y.interface)).collect()),
+            DumpRecords::Ids(x) => ParsedEntries::KeyXValues(x.into_iter().map(|y| vec![(y.name, y.values)]).collect()),
+            DumpRecords::Mounts(x) => ParsedEntries::Key3Values {
+                headers: ( "name".to_string(),
+                        "mountpoint".to_string(),
+                        "type".to_string(),
+                        "options".to_string()
+                ),
+                entries: x.into_iter().map(|y| (y.device, y.mount_point, y.fs_type, y.options)).collect(),
+            },
+            DumpRecords::Processes(x) => ParsedEntries::Key3Values {
+                headers: ( "uid".to_string(),
+                        "pid".to_string(),
+                        "ppid".to_string(),
+                        "cmd".to_string()
+                ),
+                entries: x.into_iter().map(|y| (y.user, y.pid, y.ppid, y.command)).collect(),
+            },
+            DumpRecords::Packages(x) => ParsedEntries::KeyValue(x.into_iter().map(|y| (y.kind, y.name)).collect()),
+            DumpRecords::Permissions(x) => ParsedEntries::Block5Values {
+                headers: ( "permission".to_string(),
+                        "package".to_string(),
+                        "label".to_string(),
+                        "description".to_string(),
+                        "protectionlevel".to_string()
+                ),
+                entries: x.into_iter().map(|y| [y.permission, y.package, y.label, y.description, y.protection_level]).collect(),
+            },
+            DumpRecords::Binary(x) => ParsedEntries::BinaryHash { name: x.name, sha256_sum: x.sha256_sum },
+            DumpRecords::Entries(x) => x,
+        }
+    }
+}
+
+/// Parse the dump file 'path' into its typed records with the built-in
+/// parsers, without any reference nor report.
+///
+/// # Exemple
+///
+/// ```
+/// use std::path::Path;
+/// use android_sanity_checker::androidparser::{parse_dump_file, DumpRecords};
+///
+/// match parse_dump_file(Path::new("C:/Smartphones/S21/infos/mount.txt")) {
+///     Ok(DumpRecords::Mounts(mounts)) => mounts.iter().for_each(|x| println!("{} => {}", x.mount_point, x.options)),
+///     Ok(_) => (),
+///     Err(err) => println!("{err}"),
+/// };
+/// ```
+pub fn parse_dump_file(path: &Path) -> Result<DumpRecords, AndroidParserError> {
+    let mut buf_reader = BufReader::new(File::open(path)?);
+    parse_dump(path, &mut buf_reader)
+}
+
+/// Parse the dump 'read_buffer' into its typed records with the built-in
+/// parsers, the kind of dump being chosen from its file name 'path'.
+/// 'ParserRegistry::records()' does the same with other registered parsers.
+pub fn parse_dump(path: &Path,
+        read_buffer: &mut dyn BufRead) -> Result<DumpRecords, AndroidParserError>
+{
+    ParserRegistry::builtin().records(path, read_buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn parse_dump_chooses_the_records_from_the_file_name() {
+        let records = parse_dump(Path::new("/dump/S21/mount.txt"),
+                &mut Cursor::new("/dev/block/dm-0 on / type ext4 (ro,seclabel)\n")
+        ).unwrap();
+        assert_eq!(records, DumpRecords::Mounts(vec![MountEntry {
+            device: String::from("/dev/block/dm-0"),
+            mount_point: String::from("/"),
+            fs_type: String::from("ext4"),
+            options: String::from("ro,seclabel"),
+        }]));
+        let records = parse_dump(Path::new("/dump/S21/settings_secure.txt"), &mut Cursor::new("adb_enabled=1\n")).unwrap();
+        assert_eq!(records, DumpRecords::Settings(vec![Setting { key: String::from("adb_enabled"), value: String::from("1") }]));
+        assert!(parse_dump(Path::new("/dump/S21/lsmod.txt"), &mut Cursor::new("")).is_err());
+    }
+
+    #[test]
+    fn parse_dump_file_hashes_the_binaries() {
+        let test_dir = std::env::temp_dir().join(format!("asc_records_{}", std::process::id()));
+        std::fs::create_dir_all(test_dir.join("system").join("bin")).unwrap();
+        std::fs::write(test_dir.join("system").join("bin").join("sh"), "#!/bin/sh\n").unwrap();
+        let records = parse_dump_file(&test_dir.join("system").join("bin").join("sh")).unwrap();
+        assert_eq!(records, DumpRecords::Binary(BinaryEntry {
+            name: String::from("sh"),
+            sha256_sum: sha256::digest("#!/bin/sh\n"),
+        }));
+        assert!(parse_dump_file(&test_dir.join("getprop.txt")).is_err());
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn records_convert_into_the_referenced_entries() {
+        let entries: ParsedEntries = DumpRecords::Processes(vec![ProcessEntry {
+            user: String::from("root"),
+            pid: String::from("1"),
+            ppid: String::from("0"),
+            command: String::from("init"),
+        }]).into();
+        assert_eq!(entries.clone().into_rows(), vec![
+            (String::from("root"), vec![String::from("1"), String::from("0"), String::from("init")]),
+        ]);
+        match entries {
+            ParsedEntries::Key3Values { headers, .. } => assert_eq!(headers.0, "uid"),
+            _ => panic!("processes are not Key3Values"),
+        };
+        let entries: ParsedEntries = DumpRecords::Ids(vec![IdEntry {
+            name: String::from("groups"),
+            values: vec![String::from("0(root)"), String::from("1007(log)")],
+        }]).into();
+        assert_eq!(entries.into_rows(), vec![
+            (String::from("groups"), vec![String::from("0(root)")]),
+            (String::from("groups"), vec![String::from("1007(log)")]),
+        ]);
+    }
+
+    #[test]
+    fn records_serialize_with_their_kind() {
+        let records = DumpRecords::Properties(vec![Property { key: String::from("ro.secure"), value: String::from("1") }]);
+        let json = serde_json::to_string(&records).unwrap();
+        assert_eq!(json, r#"{"kind":"properties","records":[{"key":"ro.secure","value":"1"}]}"#);
+        assert_eq!(serde_json::from_str::<DumpRecords>(&json).unwrap(), records);
+    }
+}
@@ -0,0 +1,562 @@
+//! Reference persistence
+//!
+//! A reference built by 'go_ref()' can be saved into a '.sqlite' file
+//! with its metadata, and loaded back later in place of the reference
+//! directory, so a golden baseline is built once and shared.
+//! A directory of saved references is a library, in which each device
+//! finds its reference by build fingerprint or product model.
+//! A reference can also be exported to JSON and imported back (see
+//! 'reference_json').
+
+use std::{io::{Error, ErrorKind}, path::{Path, PathBuf}};
+use sqlite::{ConnectionThreadSafe, State};
+use serde::{Deserialize, Serialize};
+
+use super::{AndroidParser, AndroidParserError, Normalizers, ParserRegistry, baseline, device::DeviceIdentity, reference_json};
+
+/// Name of the table holding the reference metadata inside a saved reference.
+pub const METADATA_TABLE: &str = "asc_reference_metadata";
+/// Prefix of the tables of a reference which are not modules (metadata, baseline statistics).
+pub(crate) const INTERNAL_TABLE_PREFIX: &str = "asc_";
+/// Separator between the values of a row stored into one column of an internal
+/// table: values frequencies of a baseline, values patterns of the wildcard rows.
+pub(crate) const VALUES_SEPARATOR: &str = "\u{1f}";
+
+/// Metadata stored alongside a saved reference.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReferenceMetadata {
+    /// Path of the white ADB dump the reference was built from.
+    pub source_path: String,
+    /// 'ro.build.fingerprint' of the reference device.
+    pub build_fingerprint: String,
+    /// 'ro.product.model' of the reference device.
+    pub product_model: String,
+    /// Version of android_sanity_checker which built the reference.
+    pub tool_version: String,
+    /// Creation time of the reference (RFC 3339).
+    pub created_at: String,
+    /// Dumps the reference was built from, more than 1 for a baseline
+    /// (0 if saved before the baselines).
+    pub baseline_devices: usize,
+    /// 'Normalizers::fingerprint()' of the normalizers the reference was
+    /// built with (empty if saved before the normalizers fingerprint).
+    pub normalizers: String,
+}
+
+impl ReferenceMetadata {
+    /// Metadata of the saved ('.sqlite' file) or exported (JSON directory)
+    /// reference 'path', None if it is neither.
+    pub fn from_file(path: &Path) -> Option<Self> {
+        match (path.is_file(), reference_json::is_reference_export(path)) {
+            (true, _) => read_file_metadata(path),
+            (false, true) => reference_json::read_export_metadata(path),
+            _ => None,
+        }
+    }
+}
+
+impl AndroidParser {
+    /// Save the reference built by 'go_ref()' into the 'path' SQLite file,
+    /// with its metadata: the ones of a loaded reference are kept, else they
+    /// are created now. An existing file at 'path' is replaced.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use android_sanity_checker::androidparser::AndroidParserBuilder;
+    ///
+    /// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+    ///         .build()
+    ///         .unwrap();
+    /// android_parser.go_ref().unwrap();
+    /// let metadata = android_parser.save_reference("S21_white.sqlite").unwrap();
+    /// println!("{} => {}", metadata.product_model, metadata.build_fingerprint);
+    /// ```
+    pub fn save_reference(&self,
+            path: &str) -> Result<ReferenceMetadata, AndroidParserError>
+    {
+        let metadata = self.reference_metadata().unwrap_or_else(|| self.current_metadata());
+        if Path::new(path).try_exists()? {
+            std::fs::remove_file(path)?;
+        }
+        match self.connx.execute(format!("VACUUM INTO '{}'",
+                escape_quotes(path))
+        ) {
+            Ok(_) => (),
+            Err(err) => return Err(Error::other(format!("Saving reference to {path} => {err}")).into()),
+        };
+        // the metadata goes into the saved file only, the reference of the analysis is left as is
+        write_metadata(&sqlite::Connection::open_thread_safe(path)?, &metadata)?;
+        Ok(metadata)
+    }
+
+    /// Metadata of the reference, if it was saved or loaded from a file.
+    pub fn reference_metadata(&self) -> Option<ReferenceMetadata> {
+        read_metadata(&self.connx)
+    }
+
+    /// Metadata of the reference as it is now, created now by this version.
+    pub(crate) fn current_metadata(&self) -> ReferenceMetadata {
+        ReferenceMetadata {
+            source_path: self.path_reference.clone(),
+            build_fingerprint: read_getprop_value(&self.connx, "ro.build.fingerprint").unwrap_or_default(),
+            product_model: read_getprop_value(&self.connx, "ro.product.model").unwrap_or_default(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: chrono::Local::now().to_rfc3339(),
+            baseline_devices: baseline::baseline_devices(&self.connx),
+            normalizers: self.normalizers.fingerprint(),
+        }
+    }
+
+    /// Save the reference built by 'go_ref()' into the 'library' directory.
+    /// The file is named after the product model and build fingerprint.
+    pub fn save_reference_to_library(&self,
+            library: &str) -> Result<(PathBuf, ReferenceMetadata), AndroidParserError>
+    {
+        let file_name = format!("{}_{}.sqlite",
+                read_getprop_value(&self.connx, "ro.product.model").unwrap_or(String::from("unknown")),
+                read_getprop_value(&self.connx, "ro.build.fingerprint").unwrap_or(String::from("unknown"))
+        )
+        .chars()
+        .map(|x| if x.is_ascii_alphanumeric() || x == '.' || x == '-' || x == '_' { x } else { '_' })
+        .collect::<String>();
+        std::fs::create_dir_all(library)?;
+        let path = Path::new(library).join(file_name);
+        let metadata = match path.to_str() {
+            Some(x) => self.save_reference(x)?,
+            None => return Err(Error::new(ErrorKind::InvalidInput,
+                    format!("Reference path {} is not valid UTF-8", path.display())).into()
+            ),
+        };
+        Ok((path, metadata))
+    }
+
+    /// Whether the reference was loaded from a saved '.sqlite' file or
+    /// imported from JSON, in which case 'go_ref()' has nothing to do.
+    pub fn is_reference_loaded(&self) -> bool {
+        Path::new(&self.path_reference).is_file() ||
+                reference_json::is_reference_export(Path::new(&self.path_reference))
+    }
+
+    /// Whether the reference is a library of saved references,
+    /// in which case each device is compared against its own reference.
+    pub fn is_reference_library(&self) -> bool {
+        self.reference_library.is_some()
+    }
+}
+
+/// How a reference was selected for a device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceMatch {
+    /// The only reference of the run was used.
+    Single,
+    /// A library reference with the same build fingerprint was found.
+    Fingerprint,
+    /// A library reference with the same product model was found.
+    Model,
+    /// No library reference matches the device.
+    NoMatch,
+    /// The library reference matching the device could not be loaded,
+    /// the error is reported and the device is not analyzed.
+    LoadFailed,
+}
+
+impl std::fmt::Display for ReferenceMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReferenceMatch::Single => write!(f, "single reference"),
+            ReferenceMatch::Fingerprint => write!(f, "matched build fingerprint"),
+            ReferenceMatch::Model => write!(f, "matched product model"),
+            ReferenceMatch::NoMatch => write!(f, "no matching reference"),
+            ReferenceMatch::LoadFailed => write!(f, "reference failed to load"),
+        }
+    }
+}
+
+/// A directory of references saved with 'save_reference()',
+/// indexed by their build fingerprint and product model.
+#[derive(Clone, Debug, Default)]
+pub struct ReferenceLibrary {
+    entries: Vec<(PathBuf, ReferenceMetadata)>,
+}
+
+impl ReferenceLibrary {
+    /// Index every saved reference ('.sqlite' file) of the 'path' directory.
+    /// Files which are not saved references are ignored.
+    pub fn open(path: &Path) -> Result<Self, AndroidParserError> {
+        let mut entries: Vec<(PathBuf, ReferenceMetadata)> = vec![];
+        for each_entry in path.read_dir()?.filter_map(|each_dir| each_dir.ok()) {
+            let each_path = each_entry.path();
+            if each_path.is_file() &&
+                    each_path.extension().is_some_and(|x| x == "sqlite")
+            {
+                if let Some(metadata) = read_file_metadata(&each_path) {
+                    entries.push((each_path, metadata));
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(Self { entries })
+    }
+
+    /// Whether 'path' is a directory holding at least one saved reference.
+    pub fn is_library(path: &Path) -> bool {
+        path.is_dir() &&
+                Self::open(path).is_ok_and(|x| !x.is_empty())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Saved references of the library, with their metadata.
+    pub fn entries(&self) -> &[(PathBuf, ReferenceMetadata)] {
+        &self.entries
+    }
+
+    /// Select the reference of a device: same build fingerprint first,
+    /// then same product model.
+    pub fn select(&self,
+            identity: &DeviceIdentity) -> (Option<&(PathBuf, ReferenceMetadata)>, ReferenceMatch)
+    {
+        if !identity.build_fingerprint.is_empty() {
+            if let Some(x) = self.entries.iter().find(|x| x.1.build_fingerprint == identity.build_fingerprint) {
+                return (Some(x), ReferenceMatch::Fingerprint);
+            }
+        }
+        if !identity.product_model.is_empty() {
+            if let Some(x) = self.entries.iter().find(|x| x.1.product_model == identity.product_model) {
+                return (Some(x), ReferenceMatch::Model);
+            }
+        }
+        (None, ReferenceMatch::NoMatch)
+    }
+}
+
+/// Copy every table of the saved reference 'path' into 'connx'.
+pub(crate) fn load_reference(connx: &ConnectionThreadSafe,
+        path: &Path) -> Result<(), Error>
+{
+    let to_io_error = |err: sqlite::Error| Error::new(ErrorKind::InvalidData,
+            format!("Loading reference {} => {err}", path.display())
+    );
+    connx.execute(format!("ATTACH DATABASE '{}' AS golden",
+            escape_quotes(path.to_str().unwrap_or_default()))
+    ).map_err(to_io_error)?;
+    let mut tables: Vec<String> = vec![];
+    let mut stmt = connx.prepare("SELECT name FROM golden.sqlite_master WHERE type='table'")
+            .map_err(to_io_error)?;
+    while let Ok(State::Row) = stmt.next() {
+        if let Ok(name) = stmt.read::<String, _>(0) {
+            tables.push(name);
+        }
+    }
+    drop(stmt);
+    for table in tables.iter() {
+        connx.execute(format!("CREATE TABLE main.'{0}' AS SELECT * FROM golden.'{0}'",
+                escape_quotes(table))
+        ).map_err(to_io_error)?;
+    }
+    connx.execute("DETACH DATABASE golden").map_err(to_io_error)?;
+    if tables.iter().any(|x| x == baseline::VALUE_FREQUENCY_TABLE) {
+        baseline::create_frequency_index(connx)?;
+    }
+    Ok(())
+}
+
+/// Load the saved ('.sqlite' file) or exported (JSON directory) reference
+/// 'path' into 'connx', whichever it is, the modules of an exported one being
+/// checked against 'parser_registry'.
+pub(crate) fn load_any_reference(connx: &ConnectionThreadSafe,
+        path: &Path,
+        parser_registry: &ParserRegistry) -> Result<(), AndroidParserError>
+{
+    match (path.is_file(), reference_json::is_reference_export(path)) {
+        (true, _) => Ok(load_reference(connx, path)?),
+        (false, true) => reference_json::import_reference(connx, path, parser_registry),
+        _ => Err(Error::new(ErrorKind::InvalidInput,
+                format!("{} is neither a saved nor an exported reference", path.display())).into()
+        ),
+    }
+}
+
+/// Refuse the reference 'path' built with other normalizers than 'normalizers':
+/// its values would not compare with the normalized values of the devices.
+/// A reference saved before the normalizers fingerprint is accepted.
+pub(crate) fn check_normalizers(metadata: &ReferenceMetadata,
+        normalizers: &Normalizers,
+        path: &Path) -> Result<(), Error>
+{
+    match metadata.normalizers.is_empty() || metadata.normalizers == normalizers.fingerprint() {
+        true => Ok(()),
+        false => Err(Error::new(ErrorKind::InvalidInput,
+                format!("Reference {} was built with other normalizers, expected the same --normalize file (or none)", path.display()))
+        ),
+    }
+}
+
+/// Drop every table of 'connx', before a reference is built again into it.
+pub(crate) fn clear_reference(connx: &ConnectionThreadSafe) -> Result<(), Error> {
+    let to_io_error = |err: sqlite::Error| Error::other(format!("Clearing reference => {err}"));
+    let mut tables: Vec<String> = vec![];
+    let mut stmt = connx.prepare("SELECT name FROM sqlite_master WHERE type='table'")
+            .map_err(to_io_error)?;
+    while let Ok(State::Row) = stmt.next() {
+        if let Ok(name) = stmt.read::<String, _>(0) {
+            tables.push(name);
+        }
+    }
+    drop(stmt);
+    for table in tables.iter() {
+        connx.execute(format!("DROP TABLE IF EXISTS '{}'",
+                escape_quotes(table))
+        ).map_err(to_io_error)?;
+    }
+    Ok(())
+}
+
+/// Module tables of the reference 'connx', sorted.
+pub(crate) fn module_tables(connx: &ConnectionThreadSafe) -> Result<Vec<String>, Error> {
+    let to_io_error = |err: sqlite::Error| Error::new(ErrorKind::InvalidData,
+            format!("Listing reference tables => {err}")
+    );
+    let mut tables: Vec<String> = vec![];
+    let mut stmt = connx.prepare("SELECT name FROM sqlite_master WHERE type='table' ORDER BY name")
+            .map_err(to_io_error)?;
+    while let Ok(State::Row) = stmt.next() {
+        if let Ok(name) = stmt.read::<String, _>(0) {
+            if !name.starts_with(INTERNAL_TABLE_PREFIX) {
+                tables.push(name);
+            }
+        }
+    }
+    Ok(tables)
+}
+
+/// Rows of the module 'table' of the reference 'connx': the key (first
+/// column) and the values beside it.
+pub(crate) fn read_table_rows(connx: &ConnectionThreadSafe,
+        table: &str) -> Result<Vec<(String, Vec<String>)>, Error>
+{
+    let to_io_error = |err: sqlite::Error| Error::new(ErrorKind::InvalidData,
+            format!("Reading reference table {table} => {err}")
+    );
+    let mut stmt = connx.prepare(format!("SELECT * FROM '{}'",
+            escape_quotes(table))
+    ).map_err(to_io_error)?;
+    let columns_count = stmt.column_count();
+    let mut rows: Vec<(String, Vec<String>)> = vec![];
+    while let Ok(State::Row) = stmt.next() {
+        let mut values: Vec<String> = (0..columns_count).map(|index| stmt.read::<String, _>(index).unwrap_or_default())
+                .collect();
+        let key = values.remove(0);
+        rows.push((key, values));
+    }
+    Ok(rows)
+}
+
+pub(crate) fn read_getprop_value(connx: &ConnectionThreadSafe,
+        key: &str) -> Option<String>
+{
+    let mut stmt = connx.prepare("SELECT value FROM 'getprop' WHERE key=:key").ok()?;
+    let _ = stmt.bind((":key", key));
+    match stmt.next() {
+        Ok(State::Row) => stmt.read::<String, _>(0).ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn read_metadata(connx: &ConnectionThreadSafe) -> Option<ReferenceMetadata> {
+    let mut stmt = connx.prepare(format!("SELECT key, value FROM '{METADATA_TABLE}'")).ok()?;
+    let mut metadata = ReferenceMetadata::default();
+    while let Ok(State::Row) = stmt.next() {
+        let key: String = stmt.read(0).unwrap_or_default();
+        let value: String = stmt.read(1).unwrap_or_default();
+        match key.as_str() {
+            "source_path" => metadata.source_path = value,
+            "build_fingerprint" => metadata.build_fingerprint = value,
+            "product_model" => metadata.product_model = value,
+            "tool_version" => metadata.tool_version = value,
+            "created_at" => metadata.created_at = value,
+            "normalizers" => metadata.normalizers = value,
+            _ => (),
+        };
+    }
+    metadata.baseline_devices = baseline::baseline_devices(connx);
+    Some(metadata)
+}
+
+pub(crate) fn read_file_metadata(path: &Path) -> Option<ReferenceMetadata> {
+    let connx = sqlite::Connection::open_thread_safe(path).ok()?;
+    read_metadata(&connx)
+}
+
+pub(crate) fn write_metadata(connx: &ConnectionThreadSafe,
+        metadata: &ReferenceMetadata) -> Result<(), Error>
+{
+    let to_io_error = |err: sqlite::Error| Error::other(format!("Writing reference metadata => {err}"));
+    connx.execute(format!("DROP TABLE IF EXISTS '{METADATA_TABLE}'")).map_err(to_io_error)?;
+    connx.execute(format!("CREATE TABLE '{METADATA_TABLE}' (key TEXT, value TEXT)")).map_err(to_io_error)?;
+    let mut statement = connx.prepare(format!("INSERT INTO '{METADATA_TABLE}' (key, value) VALUES (:key, :value)"))
+            .map_err(to_io_error)?;
+    [
+        ("source_path", metadata.source_path.as_str()),
+        ("build_fingerprint", metadata.build_fingerprint.as_str()),
+        ("product_model", metadata.product_model.as_str()),
+        ("tool_version", metadata.tool_version.as_str()),
+        ("created_at", metadata.created_at.as_str()),
+        ("normalizers", metadata.normalizers.as_str()),
+    ].into_iter().for_each(|couple| {
+        let _ = statement.bind(&[
+            (":key", couple.0),
+            (":value", couple.1),
+        ][..]);
+        while let Ok(State::Row) = statement.next() {}
+        let _ = statement.reset();
+    });
+    Ok(())
+}
+
+pub(crate) fn escape_quotes(value: &str) -> String {
+    value.replace('\'', "''")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(references: &[(&str, &str, &str)]) -> ReferenceLibrary {
+        ReferenceLibrary {
+            entries: references.iter()
+                    .map(|(path, build_fingerprint, product_model)| (PathBuf::from(path), ReferenceMetadata {
+                        build_fingerprint: build_fingerprint.to_string(),
+                        product_model: product_model.to_string(),
+                        ..Default::default()
+                    }))
+                    .collect(),
+        }
+    }
+
+    fn identity(build_fingerprint: &str,
+            product_model: &str) -> DeviceIdentity
+    {
+        DeviceIdentity {
+            build_fingerprint: build_fingerprint.to_string(),
+            product_model: product_model.to_string(),
+        }
+    }
+
+    fn selected(library: &ReferenceLibrary,
+            identity: &DeviceIdentity) -> (Option<String>, ReferenceMatch)
+    {
+        let (entry, reference_match) = library.select(identity);
+        (entry.map(|x| x.0.to_string_lossy().to_string()), reference_match)
+    }
+
+    #[test]
+    fn select_by_fingerprint_first() {
+        let library = library(&[
+            ("S21_january.sqlite", "samsung/s21:13/JAN", "SM-G991B"),
+            ("S21_june.sqlite", "samsung/s21:14/JUN", "SM-G991B"),
+        ]);
+        assert_eq!(selected(&library, &identity("samsung/s21:14/JUN", "SM-G991B")),
+                (Some(String::from("S21_june.sqlite")), ReferenceMatch::Fingerprint)
+        );
+        assert_eq!(selected(&library, &identity("samsung/s21:14/JUN", "")),
+                (Some(String::from("S21_june.sqlite")), ReferenceMatch::Fingerprint)
+        );
+    }
+
+    #[test]
+    fn select_by_model_then_no_match() {
+        let library = library(&[
+            ("A52.sqlite", "samsung/a52:13/JAN", "SM-A525F"),
+            ("S21.sqlite", "samsung/s21:13/JAN", "SM-G991B"),
+        ]);
+        assert_eq!(selected(&library, &identity("samsung/s21:14/OTA", "SM-G991B")),
+                (Some(String::from("S21.sqlite")), ReferenceMatch::Model)
+        );
+        assert_eq!(selected(&library, &identity("google/pixel:14/JUN", "Pixel 8")),
+                (None, ReferenceMatch::NoMatch)
+        );
+    }
+
+    #[test]
+    fn select_without_identity() {
+        let library = library(&[("unknown.sqlite", "", "")]);
+        assert_eq!(selected(&library, &identity("", "")), (None, ReferenceMatch::NoMatch));
+        assert_eq!(selected(&ReferenceLibrary::default(), &identity("samsung/s21:14/JUN", "SM-G991B")),
+                (None, ReferenceMatch::NoMatch)
+        );
+    }
+
+    /// A parser of the dump directory of 'test_dir', whose reference is a
+    /// 'getprop' table written here instead of being built from the dump.
+    fn getprop_reference(test_dir: &Path) -> AndroidParser {
+        let dump_dir = test_dir.join("dump");
+        std::fs::create_dir_all(&dump_dir).unwrap();
+        let android_parser = super::super::AndroidParserBuilder::new(dump_dir.to_string_lossy().to_string(), dump_dir.to_string_lossy().to_string())
+                .embedded_rules(false)
+                .output_dir(test_dir.join("reports").to_string_lossy().to_string())
+                .build()
+                .unwrap();
+        android_parser.connx.execute("CREATE TABLE 'getprop' (key TEXT, value TEXT)").unwrap();
+        android_parser.connx.execute("INSERT INTO 'getprop' VALUES ('ro.build.fingerprint', 'samsung/s21:14/JUN'), ('ro.product.model', 'SM-G991B'), ('ro.debuggable', '0')").unwrap();
+        android_parser
+    }
+
+    #[test]
+    fn save_reference_writes_the_metadata_into_the_saved_file_only() {
+        let test_dir = std::env::temp_dir().join(format!("asc_save_reference_{}", std::process::id()));
+        let android_parser = getprop_reference(&test_dir);
+        let saved_path = test_dir.join("S21.sqlite");
+        let metadata = android_parser.save_reference(saved_path.to_str().unwrap()).unwrap();
+
+        assert_eq!(metadata.build_fingerprint, "samsung/s21:14/JUN");
+        assert_eq!(metadata.product_model, "SM-G991B");
+        assert_eq!(metadata.tool_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(metadata.normalizers, android_parser.normalizers.fingerprint());
+        assert!(read_metadata(&android_parser.connx).is_none());
+        assert_eq!(read_file_metadata(&saved_path), Some(metadata.clone()));
+
+        let connx = sqlite::Connection::open_thread_safe(":memory:").unwrap();
+        load_reference(&connx, &saved_path).unwrap();
+        assert_eq!(module_tables(&connx).unwrap(), vec![String::from("getprop")]);
+        assert_eq!(read_table_rows(&connx, "getprop").unwrap(), read_table_rows(&android_parser.connx, "getprop").unwrap());
+        assert_eq!(read_metadata(&connx), Some(metadata));
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn save_reference_keeps_the_metadata_of_a_loaded_reference() {
+        let test_dir = std::env::temp_dir().join(format!("asc_resave_reference_{}", std::process::id()));
+        let first_path = test_dir.join("first.sqlite");
+        let first_metadata = getprop_reference(&test_dir).save_reference(first_path.to_str().unwrap()).unwrap();
+
+        let android_parser = super::super::AndroidParserBuilder::new(first_path.to_string_lossy().to_string(), test_dir.join("dump").to_string_lossy().to_string())
+                .embedded_rules(false)
+                .output_dir(test_dir.join("reports").to_string_lossy().to_string())
+                .build()
+                .unwrap();
+        assert!(android_parser.is_reference_loaded());
+        let second_path = test_dir.join("second.sqlite");
+        assert_eq!(android_parser.save_reference(second_path.to_str().unwrap()).unwrap(), first_metadata);
+        assert_eq!(read_file_metadata(&second_path), Some(first_metadata));
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+
+    #[test]
+    fn save_reference_to_library_names_the_file_after_the_device() {
+        let test_dir = std::env::temp_dir().join(format!("asc_library_reference_{}", std::process::id()));
+        let android_parser = getprop_reference(&test_dir);
+        let library_dir = test_dir.join("library");
+        let (path, metadata) = android_parser.save_reference_to_library(library_dir.to_str().unwrap()).unwrap();
+
+        assert_eq!(path, library_dir.join("SM-G991B_samsung_s21_14_JUN.sqlite"));
+        let library = ReferenceLibrary::open(&library_dir).unwrap();
+        assert_eq!(library.entries(), &[(path, metadata)]);
+        let _ = std::fs::remove_dir_all(&test_dir);
+    }
+}
@@ -0,0 +1,435 @@
+//! Reference JSON
+//!
+//! A reference exported as one JSON document per module, to be reviewed
+//! and versioned in git: accepted rows are added or removed by hand, and
+//! wildcard rows accept every row matching their patterns, '*' standing
+//! for any run of characters. The exported directory is itself a reference
+//! ('ref_path'), imported when the parser is built, and saved back into
+//! a '.sqlite' file by 'save_reference()'.
+//! The baseline statistics (values frequencies) are not exported.
+//!
+//! ```json
+//! {
+//!   "module": "getprop",
+//!   "columns": ["key", "value"],
+//!   "rows": [
+//!     { "key": "ro.debuggable", "value": "0" }
+//!   ],
+//!   "wildcards": [
+//!     { "key": "ro.build.date*", "value": "*" },
+//!     { "key": "persist.sys.timezone" }
+//!   ]
+//! }
+//! ```
+
+use std::{collections::{BTreeMap, HashMap}, fs::File, io::{BufReader, BufWriter, Error, ErrorKind, Write}, path::Path};
+use sqlite::{ConnectionThreadSafe, State};
+use serde::{Deserialize, Serialize};
+
+use super::{AndroidParser, AndroidParserError, ParserRegistry, ReferenceMetadata, reference::{self, escape_quotes, VALUES_SEPARATOR}};
+
+/// File of an exported reference listing its metadata and modules.
+pub const REFERENCE_INDEX_FILE: &str = "asc_reference.json";
+/// Table of the wildcard rows of a reference imported from JSON.
+pub const WILDCARDS_TABLE: &str = "asc_reference_wildcards";
+/// Character of a wildcard pattern matching any run of characters.
+pub const WILDCARD: char = '*';
+
+/// Wildcard rows of a reference by module: key and values patterns.
+pub(crate) type ModuleWildcards = HashMap<String, Vec<(String, Vec<String>)>>;
+
+/// Index of an exported reference, written into 'REFERENCE_INDEX_FILE'.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceDocument {
+    #[serde(default)]
+    pub metadata: ReferenceMetadata,
+    /// Modules of the reference, each exported into '<module>.json'.
+    pub modules: Vec<String>,
+}
+
+/// A module of an exported reference, written into '<module>.json'.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModuleDocument {
+    /// Module (reference table): 'getprop', 'mount', 'binaries_hashes'...
+    pub module: String,
+    /// Columns of the module, the first one being the key.
+    pub columns: Vec<String>,
+    /// Accepted rows, every column being set.
+    #[serde(default)]
+    pub rows: Vec<BTreeMap<String, String>>,
+    /// Wildcard rows, a column not set accepting any value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub wildcards: Vec<BTreeMap<String, String>>,
+}
+
+impl AndroidParser {
+    /// Export the reference built by 'go_ref()' or loaded, into the 'path'
+    /// directory: 'REFERENCE_INDEX_FILE' and one '<module>.json' per module.
+    /// The directory can then be given as 'ref_path'.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use android_sanity_checker::androidparser::AndroidParserBuilder;
+    ///
+    /// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+    ///         .build()
+    ///         .unwrap();
+    /// android_parser.go_ref().unwrap();
+    /// let document = android_parser.export_reference(Path::new("C:/asc/S21_white")).unwrap();
+    /// println!("{} module(s) exported", document.modules.len());
+    /// ```
+    pub fn export_reference(&self,
+            path: &Path) -> Result<ReferenceDocument, AndroidParserError>
+    {
+        std::fs::create_dir_all(path)?;
+        let wildcards = read_wildcards(&self.connx);
+        let mut document = ReferenceDocument {
+            metadata: self.reference_metadata().unwrap_or_else(|| self.current_metadata()),
+            modules: vec![],
+        };
+        for table in reference::module_tables(&self.connx)? {
+            let columns = read_columns(&self.connx, &table)?;
+            let rows = reference::read_table_rows(&self.connx, &table)?.into_iter()
+                    .map(|(key, values)| to_columns(&columns, key, values))
+                    .collect();
+            let module_document = ModuleDocument {
+                module: table.clone(),
+                wildcards: wildcards.iter()
+                        .filter(|x| x.0 == table)
+                        .map(|x| to_columns(&columns, x.1.clone(), x.2.clone()))
+                        .collect(),
+                columns,
+                rows,
+            };
+            write_json(&path.join(format!("{table}.json")), &module_document)?;
+            document.modules.push(table);
+        }
+        write_json(&path.join(REFERENCE_INDEX_FILE), &document)?;
+        Ok(document)
+    }
+}
+
+/// Whether 'path' is a reference exported by 'export_reference()'.
+pub fn is_reference_export(path: &Path) -> bool {
+    path.join(REFERENCE_INDEX_FILE).is_file()
+}
+
+/// Metadata of the reference exported into 'path', None if it cannot be read.
+pub(crate) fn read_export_metadata(path: &Path) -> Option<ReferenceMetadata> {
+    read_json::<ReferenceDocument>(&path.join(REFERENCE_INDEX_FILE)).ok().map(|x| x.metadata)
+}
+
+/// Import every module of the reference exported into 'path' into 'connx',
+/// with its wildcard rows and metadata. The columns of a module must be the
+/// ones of its table given by 'parser_registry', if any.
+pub(crate) fn import_reference(connx: &ConnectionThreadSafe,
+        path: &Path,
+        parser_registry: &ParserRegistry) -> Result<(), AndroidParserError>
+{
+    let index_path = path.join(REFERENCE_INDEX_FILE);
+    let document: ReferenceDocument = read_json(&index_path)?;
+    connx.execute(format!("CREATE TABLE '{WILDCARDS_TABLE}' (module TEXT, key TEXT, value TEXT)"))?;
+    for module in document.modules.iter() {
+        let module_path = path.join(format!("{module}.json"));
+        let module_str = module_path.to_str().unwrap_or_default();
+        if module.is_empty() ||
+                module.contains(['/', '\\']) ||
+                module.starts_with(reference::INTERNAL_TABLE_PREFIX)
+        {
+            return Err(AndroidParserError::parse(index_path.to_str().unwrap_or_default(),
+                    &format!("Invalid module name {module}"))
+            );
+        }
+        let module_document: ModuleDocument = read_json(&module_path)?;
+        if module_document.module != *module {
+            return Err(AndroidParserError::parse(module_str,
+                    &format!("Module {} declared, {module} expected", module_document.module))
+            );
+        }
+        let columns = &module_document.columns;
+        if columns.len() < 2 ||
+                columns.iter().enumerate().any(|(index, x)| x.is_empty() || columns[..index].contains(x))
+        {
+            return Err(AndroidParserError::parse(module_str,
+                    "Expected a key column and at least one value column, named once each")
+            );
+        }
+        if let Some(expected) = parser_registry.columns(module) {
+            if *columns != expected {
+                return Err(AndroidParserError::parse(module_str,
+                        &format!("Columns {} expected for the module {module}, found {}", expected.join(", "), columns.join(", ")))
+                );
+            }
+        }
+        connx.execute(format!("CREATE TABLE '{}' ({})",
+                escape_quotes(module),
+                columns.iter().map(|x| format!("'{}' TEXT", escape_quotes(x))).collect::<Vec<String>>().join(", ")
+        ))?;
+        let mut statement = connx.prepare(format!("INSERT INTO '{}' VALUES ({})",
+                escape_quotes(module),
+                vec!["?"; columns.len()].join(", ")
+        ))?;
+        for (index, row) in module_document.rows.iter().enumerate() {
+            if let Some(unknown) = row.keys().find(|x| !columns.contains(x)) {
+                return Err(AndroidParserError::parse(module_str,
+                        &format!("Row {} => unknown column {unknown}", index + 1))
+                );
+            }
+            for (position, column) in columns.iter().enumerate() {
+                match row.get(column) {
+                    Some(x) => statement.bind((position + 1, x.as_str()))?,
+                    None => return Err(AndroidParserError::parse(module_str,
+                            &format!("Row {} => missing column {column}", index + 1))
+                    ),
+                };
+            }
+            while let Ok(State::Row) = statement.next() {}
+            statement.reset()?;
+        }
+        drop(statement);
+        let mut statement = connx.prepare(format!("INSERT INTO '{WILDCARDS_TABLE}' (module, key, value) VALUES (:module, :key, :value)"))?;
+        for (index, wildcard) in module_document.wildcards.iter().enumerate() {
+            if let Some(unknown) = wildcard.keys().find(|x| !columns.contains(x)) {
+                return Err(AndroidParserError::parse(module_str,
+                        &format!("Wildcard {} => unknown column {unknown}", index + 1))
+                );
+            }
+            let mut patterns: Vec<&str> = columns.iter()
+                    .map(|x| wildcard.get(x).map(|y| y.as_str()).unwrap_or("*"))
+                    .collect();
+            let key = patterns.remove(0);
+            statement.bind(&[
+                (":module", module.as_str()),
+                (":key", key),
+                (":value", patterns.join(VALUES_SEPARATOR).as_str()),
+            ][..])?;
+            while let Ok(State::Row) = statement.next() {}
+            statement.reset()?;
+        }
+    }
+    reference::write_metadata(connx, &document.metadata)?;
+    Ok(())
+}
+
+/// Wildcard rows of the reference 'connx' by module, read once for the
+/// rows of a device to be checked against them.
+pub(crate) fn load_wildcards(connx: &ConnectionThreadSafe) -> ModuleWildcards {
+    let mut wildcards = ModuleWildcards::new();
+    for (module, key, values) in read_wildcards(connx) {
+        wildcards.entry(module).or_default().push((key, values));
+    }
+    wildcards
+}
+
+/// Whether a wildcard row of the module 'table' of 'wildcards' accepts the
+/// row 'key', 'values'.
+pub(crate) fn wildcard_accepts(wildcards: &ModuleWildcards,
+        table: &str,
+        key: &str,
+        values: &[String]) -> bool
+{
+    match wildcards.get(table) {
+        Some(x) => x.iter().any(|(key_pattern, value_patterns)| {
+            wildcard_match(key_pattern, key) &&
+                    value_patterns.len() == values.len() &&
+                    value_patterns.iter().zip(values.iter()).all(|(x, y)| wildcard_match(x, y))
+        }),
+        None => false,
+    }
+}
+
+/// Whether 'value' matches 'pattern', 'WILDCARD' matching any run of characters.
+fn wildcard_match(pattern: &str,
+        value: &str) -> bool
+{
+    let mut parts = pattern.split(WILDCARD);
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match value.strip_prefix(first) {
+        Some(x) => x,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    let last = match parts.split_last() {
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(x) => rest = &rest[x + part.len()..],
+                    None => return false,
+                };
+            }
+            last
+        },
+        None => return rest.is_empty(),
+    };
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Wildcard rows of the reference 'connx': module, key and values patterns.
+fn read_wildcards(connx: &ConnectionThreadSafe) -> Vec<(String, String, Vec<String>)> {
+    let mut stmt = match connx.prepare(format!("SELECT module, key, value FROM '{WILDCARDS_TABLE}'")) {
+        Ok(x) => x,
+        Err(_) => return vec![],
+    };
+    let mut wildcards: Vec<(String, String, Vec<String>)> = vec![];
+    while let Ok(State::Row) = stmt.next() {
+        let value: String = stmt.read(2).unwrap_or_default();
+        wildcards.push((stmt.read(0).unwrap_or_default(),
+                stmt.read(1).unwrap_or_default(),
+                value.split(VALUES_SEPARATOR).map(String::from).collect()
+        ));
+    }
+    wildcards
+}
+
+/// Columns of the module 'table' of the reference 'connx'.
+fn read_columns(connx: &ConnectionThreadSafe,
+        table: &str) -> Result<Vec<String>, Error>
+{
+    match connx.prepare(format!("SELECT * FROM '{}' LIMIT 0", escape_quotes(table))) {
+        Ok(x) => Ok(x.column_names().to_vec()),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData,
+                format!("Reading reference table {table} => {err}"))
+        ),
+    }
+}
+
+/// Row 'key', 'values' by the names of 'columns'.
+fn to_columns(columns: &[String],
+        key: String,
+        values: Vec<String>) -> BTreeMap<String, String>
+{
+    columns.iter().cloned()
+            .zip(std::iter::once(key).chain(values))
+            .collect()
+}
+
+fn read_json<T: for<'a> Deserialize<'a>>(path: &Path) -> Result<T, AndroidParserError> {
+    let file = File::open(path).map_err(|err| Error::new(err.kind(),
+            format!("{} => {err}", path.display()))
+    )?;
+    let buf_reader = BufReader::new(file);
+    match serde_json::from_reader(buf_reader) {
+        Ok(x) => Ok(x),
+        Err(err) => Err(AndroidParserError::parse(path.to_str().unwrap_or_default(),
+                &err.to_string())
+        ),
+    }
+}
+
+fn write_json<T: Serialize>(path: &Path,
+        value: &T) -> Result<(), Error>
+{
+    let mut buf_writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut buf_writer, value).map_err(Error::from)?;
+    buf_writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_match_without_wildcard() {
+        assert!(wildcard_match("", ""));
+        assert!(!wildcard_match("", "0"));
+        assert!(wildcard_match("ro.debuggable", "ro.debuggable"));
+        assert!(!wildcard_match("ro.debuggable", "ro.debuggable2"));
+        assert!(!wildcard_match("ro.debuggable", "ro.debug"));
+    }
+
+    #[test]
+    fn wildcard_match_with_wildcards() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("**", "anything"));
+        assert!(wildcard_match("ro.build.*", "ro.build.date"));
+        assert!(wildcard_match("ro.build.*", "ro.build."));
+        assert!(!wildcard_match("ro.build.*", "ro.product.model"));
+        assert!(wildcard_match("*.date", "ro.build.date"));
+        assert!(wildcard_match("ro.*.date*", "ro.build.date.utc"));
+        assert!(!wildcard_match("ro.*.date", "ro.build.date.utc"));
+        assert!(wildcard_match("a*b*c", "aXbYc"));
+        assert!(!wildcard_match("a*b*c", "aXcYb"));
+    }
+
+    #[test]
+    fn wildcard_match_overlapping_prefix_and_suffix() {
+        assert!(!wildcard_match("ab*b", "ab"));
+        assert!(wildcard_match("ab*b", "abb"));
+        assert!(!wildcard_match("a*a", "a"));
+        assert!(wildcard_match("a*a", "aa"));
+        assert!(!wildcard_match("ab*bc*c", "abc"));
+        assert!(wildcard_match("ab*bc*c", "abbcc"));
+    }
+
+    #[test]
+    fn import_and_accept_wildcards() {
+        let path = std::env::temp_dir().join(format!("asc_reference_json_test_{}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        write_json(&path.join(REFERENCE_INDEX_FILE), &ReferenceDocument {
+            metadata: ReferenceMetadata::default(),
+            modules: vec![String::from("getprop"), String::from("mount")],
+        }).unwrap();
+        write_json(&path.join("getprop.json"), &ModuleDocument {
+            module: String::from("getprop"),
+            columns: vec![String::from("key"), String::from("value")],
+            rows: vec![to_columns(&[String::from("key"), String::from("value")], String::from("ro.debuggable"), vec![String::from("0")])],
+            wildcards: vec![BTreeMap::from([(String::from("key"), String::from("ro.build.date*"))])],
+        }).unwrap();
+        write_json(&path.join("mount.json"), &ModuleDocument {
+            module: String::from("mount"),
+            columns: vec![String::from("name"), String::from("mountpoint"), String::from("type"), String::from("options")],
+            rows: vec![],
+            wildcards: vec![BTreeMap::from([(String::from("mountpoint"), String::from("/mnt/*")), (String::from("options"), String::from("ro,*"))])],
+        }).unwrap();
+        let connx = sqlite::Connection::open_thread_safe(":memory:").unwrap();
+        import_reference(&connx, &path, &ParserRegistry::builtin()).unwrap();
+        let _ = std::fs::remove_dir_all(&path);
+        assert_eq!(reference::read_table_rows(&connx, "getprop").unwrap(), vec![(String::from("ro.debuggable"), vec![String::from("0")])]);
+        let wildcards = load_wildcards(&connx);
+        assert_eq!(wildcards.len(), 2);
+        assert!(wildcard_accepts(&wildcards, "getprop", "ro.build.date.utc", &[String::from("1700000000")]));
+        assert!(!wildcard_accepts(&wildcards, "getprop", "ro.debuggable", &[String::from("1")]));
+        assert!(!wildcard_accepts(&wildcards, "settings_global", "ro.build.date.utc", &[String::from("1700000000")]));
+        assert!(wildcard_accepts(&wildcards, "mount", "/dev/block/dm-3", &[String::from("/mnt/vendor"), String::from("ext4"), String::from("ro,noatime")]));
+        assert!(!wildcard_accepts(&wildcards, "mount", "/dev/block/dm-3", &[String::from("/mnt/vendor"), String::from("ext4"), String::from("rw,noatime")]));
+        assert!(!wildcard_accepts(&wildcards, "mount", "/dev/block/dm-3", &[String::from("/mnt/vendor"), String::from("ext4")]));
+    }
+
+    /// An exported reference 'path' of the single module 'module' having 'columns' and no row.
+    fn single_module_export(path: &Path,
+            module: &str,
+            columns: &[&str])
+    {
+        std::fs::create_dir_all(path).unwrap();
+        write_json(&path.join(REFERENCE_INDEX_FILE), &ReferenceDocument {
+            metadata: ReferenceMetadata::default(),
+            modules: vec![module.to_string()],
+        }).unwrap();
+        write_json(&path.join(format!("{module}.json")), &ModuleDocument {
+            module: module.to_string(),
+            columns: columns.iter().map(|x| x.to_string()).collect(),
+            rows: vec![],
+            wildcards: vec![],
+        }).unwrap();
+    }
+
+    #[test]
+    fn import_refuses_the_columns_of_another_shape() {
+        let path = std::env::temp_dir().join(format!("asc_reference_json_shape_{}", std::process::id()));
+        let parser_registry = ParserRegistry::builtin();
+        let import = |module: &str, columns: &[&str]| -> Result<(), AndroidParserError> {
+            single_module_export(&path, module, columns);
+            import_reference(&sqlite::Connection::open_thread_safe(":memory:").unwrap(), &path, &parser_registry)
+        };
+        assert!(matches!(import("mount", &["mountpoint", "filesystem", "options"]), Err(AndroidParserError::Parse { .. })));
+        assert!(matches!(import("getprop", &["name", "value"]), Err(AndroidParserError::Parse { .. })));
+        assert!(matches!(import("binaries_hashes", &["name", "sha256"]), Err(AndroidParserError::Parse { .. })));
+        assert!(import("settings_secure", &["key", "value"]).is_ok());
+        assert!(import("ps", &["uid", "pid", "ppid", "cmd"]).is_ok());
+        assert!(import("lsmod", &["module", "size"]).is_ok());
+        let _ = std::fs::remove_dir_all(&path);
+    }
+}
@@ -0,0 +1,686 @@
+//! Reports
+//!
+//! CSV reports written following RFC 4180: fields holding the delimiter,
+//! a double quote or a line break are enclosed in double quotes, inner
+//! double quotes being doubled, and records end with CRLF.
+//! Each run writes its reports into its own timestamped folder,
+//! outside of the evidence directories, from the events of the run.
+
+use std::{io::{BufWriter, Error, ErrorKind, Write}, fs::{File, OpenOptions}, path::{Path, PathBuf}, sync::{Arc, Mutex}, collections::{BTreeMap, HashMap, HashSet}};
+
+use super::{DeviceSummary, Event, FindingKind, Observer, Outlier, RunPhase, SeverityModel, findings::{EXPECTED_SEPARATOR, Finding}, outliers, summary};
+
+/// Module, key and values of an outlier.
+type OutlierKey = (String, String, Vec<String>);
+
+/// Delimiter of the CSV reports, unless set with 'set_csv_delimiter()'.
+pub const DEFAULT_CSV_DELIMITER: char = ';';
+
+/// Format of the findings reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReportFormat {
+    /// Module CSV reports, 'reported_yara_matches.csv' and 'reported_binaries.csv'.
+    Csv,
+    /// 'reported_findings.jsonl', one JSON finding per line.
+    JsonLines,
+}
+
+/// A CSV report file.
+pub struct CsvWriter {
+    /// None for a report which is not written (CSV reports disabled).
+    buf_writer: Option<BufWriter<File>>,
+    delimiter: char,
+}
+
+impl CsvWriter {
+    /// Create the report 'path', replacing any previous one, and write its 'header'.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// use android_sanity_checker::androidparser::CsvWriter;
+    ///
+    /// let path = std::env::temp_dir().join("reported_devices.csv");
+    /// let mut csv_writer = CsvWriter::create(&path, &["device", "reference"], ';').unwrap();
+    /// csv_writer.write_record(&["C:/Smartphones/S21", "S21_white.sqlite"]).unwrap();
+    /// csv_writer.flush().unwrap();
+    /// ```
+    pub fn create<S: AsRef<str>>(path: &Path,
+            header: &[S],
+            delimiter: char) -> Result<Self, Error>
+    {
+        let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)?;
+        let mut csv_writer = Self { buf_writer: Some(BufWriter::new(file)), delimiter };
+        csv_writer.write_record(header)?;
+        Ok(csv_writer)
+    }
+
+    /// Open the report 'path' to add records after the existing ones, without header.
+    pub fn append(path: &Path,
+            delimiter: char) -> Result<Self, Error>
+    {
+        let file = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)?;
+        Ok(Self { buf_writer: Some(BufWriter::new(file)), delimiter })
+    }
+
+    /// A report discarding every record, without any file.
+    pub fn sink(delimiter: char) -> Self {
+        Self { buf_writer: None, delimiter }
+    }
+
+    pub fn write_record<S: AsRef<str>>(&mut self,
+            record: &[S]) -> Result<(), Error>
+    {
+        let buf_writer = match self.buf_writer.as_mut() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        let line = record.iter()
+                .map(|x| escape_field(x.as_ref(), self.delimiter))
+                .collect::<Vec<String>>()
+                .join(self.delimiter.to_string().as_str());
+        buf_writer.write_all(format!("{line}\r\n").as_bytes())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        match self.buf_writer.as_mut() {
+            Some(x) => x.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Refuse a CSV 'delimiter' which cannot separate fields: a double quote or a line break.
+pub(crate) fn check_delimiter(delimiter: char) -> Result<(), Error> {
+    match delimiter {
+        '"' | '\r' | '\n' => Err(Error::new(ErrorKind::InvalidInput,
+                format!("Invalid CSV delimiter {delimiter:?}"))
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Quote 'field' if it holds 'delimiter', a double quote or a line break.
+pub fn escape_field(field: &str,
+        delimiter: char) -> String
+{
+    match field.contains(delimiter) || field.contains(['"', '\r', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// Create the folder of a run, 'asc_<YYYYmmdd_HHMMSS>', inside 'output_dir'.
+/// 'output_dir' is refused if it is inside one of the 'evidence_dirs'.
+pub fn create_run_dir(output_dir: &Path,
+        evidence_dirs: &[&Path]) -> Result<PathBuf, Error>
+{
+    let output_dir = std::path::absolute(output_dir)?;
+    for evidence_dir in evidence_dirs.iter().filter(|x| x.is_dir()) {
+        let canonical_output_dir = output_dir.canonicalize().unwrap_or(output_dir.clone());
+        if output_dir.starts_with(std::path::absolute(evidence_dir)?) ||
+                canonical_output_dir.starts_with(evidence_dir.canonicalize()?)
+        {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                    format!("Output directory {} is inside the evidence directory {}", output_dir.display(), evidence_dir.display()))
+            );
+        }
+    }
+    std::fs::create_dir_all(&output_dir)?;
+    let run_name = format!("asc_{}", chrono::Local::now().format("%Y%m%d_%H%M%S"));
+    let mut run_dir = output_dir.join(&run_name);
+    let mut counter: usize = 1;
+    loop {
+        match std::fs::create_dir(&run_dir) {
+            Ok(_) => return Ok(run_dir),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                run_dir = output_dir.join(format!("{run_name}_{counter}"));
+                counter += 1;
+            },
+            Err(err) => return Err(err),
+        };
+    }
+}
+
+/// Path of the module report of the dump file 'path' of the device 'device_root',
+/// mirroring the device tree into 'device_report_dir': '<dump file stem>.csv'
+/// beside where the dump is, or 'binaries.csv' for '/system/bin'.
+/// Both POSIX and Windows paths are handled, from the 'Path' components.
+pub(crate) fn module_report_path(device_root: &Path,
+        device_report_dir: &Path,
+        path: &str) -> Result<PathBuf, Error>
+{
+    let relative_path = match Path::new(path).strip_prefix(device_root) {
+        Ok(x) => x,
+        Err(_) => return Err(Error::new(ErrorKind::InvalidInput,
+                format!("{path} => Error occured while creating the report : not under the device {}", device_root.display()))
+        ),
+    };
+    if relative_path.starts_with(Path::new("system").join("bin")) {
+        return Ok(device_report_dir.join("binaries.csv"));
+    }
+    match (relative_path.parent(), relative_path.file_stem()) {
+        (Some(parent), Some(stem)) => Ok(device_report_dir.join(parent).join(format!("{}.csv", stem.to_string_lossy()))),
+        _ => Err(Error::new(ErrorKind::InvalidInput,
+                format!("{path} => Error occured while creating the report : no file name"))
+        ),
+    }
+}
+
+/// The file reports of a run, written from its events: the module CSV
+/// reports and 'device_summary.json' of each device, 'reported_yara_matches.csv',
+/// 'reported_binaries.csv', 'reported_devices.csv', 'reported_errors.csv',
+/// 'reported_suppressed.csv', 'reported_fleet.csv', 'reported_outliers.csv'
+/// and 'reported_findings.jsonl'.
+pub(crate) struct FileReporter {
+    report_dir: PathBuf,
+    delimiter: char,
+    report_formats: Vec<ReportFormat>,
+    /// Risk score and verdict of the device summaries.
+    severity_model: Arc<SeverityModel>,
+    yara_report: Mutex<CsvWriter>,
+    binaries_report: Mutex<CsvWriter>,
+    devices_report: Mutex<CsvWriter>,
+    errors_report: Mutex<CsvWriter>,
+    suppressed_report: Mutex<CsvWriter>,
+    /// None when the JSON Lines report is disabled.
+    findings_report: Option<Mutex<BufWriter<File>>>,
+    /// Root and report folder of each device being analyzed.
+    devices: Mutex<HashMap<String, (PathBuf, PathBuf)>>,
+    /// Module reports open for the devices being analyzed.
+    module_reports: Mutex<HashMap<PathBuf, CsvWriter>>,
+    /// Module reports created during this run, to write their header only once.
+    created_reports: Mutex<HashSet<PathBuf>>,
+    /// Summaries of the devices being analyzed.
+    device_summaries: Mutex<HashMap<String, DeviceSummary>>,
+    /// Summaries of the devices analyzed during the last run, ranked once it is finished.
+    fleet_summaries: Mutex<Vec<DeviceSummary>>,
+    /// Rare values of the last run, by module, key and values.
+    outliers: Mutex<BTreeMap<OutlierKey, Outlier>>,
+    /// Failures of the reports, not yet emitted back as 'Event::Error'.
+    failures: Mutex<Vec<Event>>,
+}
+
+impl FileReporter {
+    /// Create the global reports into 'report_dir'. Only 'reported_devices.csv',
+    /// 'reported_errors.csv' and 'reported_suppressed.csv' are written whatever the 'report_formats'.
+    pub fn new(report_dir: PathBuf,
+            delimiter: char,
+            report_formats: &[ReportFormat],
+            severity_model: Arc<SeverityModel>) -> Result<Self, Error>
+    {
+        check_delimiter(delimiter)?;
+        let (yara_report, binaries_report) = match report_formats.contains(&ReportFormat::Csv) {
+            true => (CsvWriter::create(&report_dir.join("reported_yara_matches.csv"),
+                            &["filename", "yara_rulename", "yara_rule_description", "yara_rule_reference", "severity"],
+                            delimiter
+                    )?,
+                    CsvWriter::create(&report_dir.join("reported_binaries.csv"),
+                            &["filename", "sha256_sum", "expected_sha256_sum", "status", "yara_match", "yara_rulename", "severity", "frequency"],
+                            delimiter
+                    )?
+            ),
+            false => (CsvWriter::sink(delimiter), CsvWriter::sink(delimiter)),
+        };
+        let devices_report = CsvWriter::create(&report_dir.join("reported_devices.csv"),
+                &["device", "build_fingerprint", "product_model", "reference", "reference_match"],
+                delimiter
+        )?;
+        let errors_report = CsvWriter::create(&report_dir.join("reported_errors.csv"),
+                &["device", "file", "error_kind", "error"],
+                delimiter
+        )?;
+        let suppressed_report = CsvWriter::create(&report_dir.join("reported_suppressed.csv"),
+                &["device", "module", "file", "key", "observed", "status", "severity", "reason"],
+                delimiter
+        )?;
+        let findings_report = match report_formats.contains(&ReportFormat::JsonLines) {
+            true => Some(Mutex::new(BufWriter::new(OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(report_dir.join("reported_findings.jsonl"))?
+            ))),
+            false => None,
+        };
+        Ok(Self {
+            report_dir,
+            delimiter,
+            report_formats: report_formats.to_vec(),
+            severity_model,
+            yara_report: Mutex::new(yara_report),
+            binaries_report: Mutex::new(binaries_report),
+            devices_report: Mutex::new(devices_report),
+            errors_report: Mutex::new(errors_report),
+            suppressed_report: Mutex::new(suppressed_report),
+            findings_report,
+            devices: Mutex::new(HashMap::new()),
+            module_reports: Mutex::new(HashMap::new()),
+            created_reports: Mutex::new(HashSet::new()),
+            device_summaries: Mutex::new(HashMap::new()),
+            fleet_summaries: Mutex::new(vec![]),
+            outliers: Mutex::new(BTreeMap::new()),
+            failures: Mutex::new(vec![]),
+        })
+    }
+
+    pub fn report_dir(&self) -> &Path {
+        &self.report_dir
+    }
+
+    pub fn delimiter(&self) -> char {
+        self.delimiter
+    }
+
+    pub fn report_formats(&self) -> &[ReportFormat] {
+        &self.report_formats
+    }
+
+    pub fn severity_model(&self) -> Arc<SeverityModel> {
+        self.severity_model.clone()
+    }
+
+    /// Summaries of the devices analyzed during the last run.
+    pub fn fleet_summaries(&self) -> Vec<DeviceSummary> {
+        self.fleet_summaries.lock().unwrap().clone()
+    }
+
+    /// Rare values of the last run, from the rarest.
+    pub fn outliers(&self) -> Vec<Outlier> {
+        let mut outliers: Vec<Outlier> = self.outliers.lock().unwrap().values().cloned().collect();
+        outliers::rank_outliers(&mut outliers);
+        outliers
+    }
+
+    /// Count the 'rare' 'finding' into the outliers of the run.
+    fn add_outlier(&self,
+            finding: &Finding)
+    {
+        let mut outliers = self.outliers.lock().unwrap();
+        match outliers.get_mut(&(finding.module.clone(), finding.key.clone(), finding.observed.clone())) {
+            Some(x) => x.devices.push(finding.device.clone()),
+            None => {
+                if let Some(x) = Outlier::from_finding(finding) {
+                    outliers.insert((x.module.clone(), x.key.clone(), x.values.clone()), x);
+                }
+            },
+        };
+    }
+
+    pub fn flush(&self) -> Result<(), Error> {
+        self.devices_report.lock().unwrap().flush()?;
+        self.binaries_report.lock().unwrap().flush()?;
+        self.yara_report.lock().unwrap().flush()?;
+        self.errors_report.lock().unwrap().flush()?;
+        self.suppressed_report.lock().unwrap().flush()?;
+        for module_report in self.module_reports.lock().unwrap().values_mut() {
+            module_report.flush()?;
+        }
+        if let Some(findings_report) = &self.findings_report {
+            findings_report.lock().unwrap().flush()?;
+        }
+        Ok(())
+    }
+
+    /// Keep a report failure, emitted back as an 'Event::Error' by the caller of 'take_failures'.
+    fn report_failure(&self,
+            device: &str,
+            file: &str,
+            err: &Error)
+    {
+        self.failures.lock().unwrap().push(Event::Error {
+            device: device.to_string(),
+            file: file.to_string(),
+            kind: "io",
+            message: err.to_string(),
+        });
+    }
+
+    /// The report failures kept since the last call.
+    pub(crate) fn take_failures(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.failures.lock().unwrap())
+    }
+
+    /// Path of the module report of 'file' of 'device'.
+    fn device_report_path(&self,
+            device: &str,
+            file: &str) -> Result<PathBuf, Error>
+    {
+        match self.devices.lock().unwrap().get(device) {
+            Some((device_root, device_report_dir)) => module_report_path(device_root, device_report_dir, file),
+            None => Err(Error::new(ErrorKind::NotFound,
+                    format!("{file} => Error occured while creating the report : unknown device {device}"))
+            ),
+        }
+    }
+
+    /// Open the module report 'report_path'. The first time a report is opened
+    /// during the run, it is created and its 'header' written, later openings
+    /// add records after the existing ones.
+    fn open_module_report<S: AsRef<str>>(&self,
+            report_path: PathBuf,
+            header: &[S]) -> Result<(), Error>
+    {
+        if !self.report_formats.contains(&ReportFormat::Csv) {
+            return Ok(());
+        }
+        let mut module_reports = self.module_reports.lock().unwrap();
+        if module_reports.contains_key(&report_path) {
+            return Ok(());
+        }
+        if let Some(parent) = report_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let csv_writer = match self.created_reports.lock().unwrap().insert(report_path.clone()) {
+            true => CsvWriter::create(&report_path, header, self.delimiter)?,
+            false => CsvWriter::append(&report_path, self.delimiter)?,
+        };
+        module_reports.insert(report_path, csv_writer);
+        Ok(())
+    }
+
+    fn write_finding(&self,
+            finding: &Finding,
+            values_count: usize) -> Result<(), Error>
+    {
+        match finding.kind {
+            FindingKind::NoReference => (),
+            FindingKind::YaraMatch => {
+                self.yara_report.lock().unwrap().write_record(&[
+                    finding.file.clone(),
+                    finding.yara_rules_names(),
+                    finding.yara_matches.iter().map(|x| match x.metadata_str(&["desc"]) {
+                        y if y.is_empty() => String::from("[---]"),
+                        y => y,
+                    }).collect::<Vec<String>>().join(EXPECTED_SEPARATOR),
+                    finding.yara_matches.iter().map(|x| match x.metadata_str(&["url", "reference"]) {
+                        y if y.is_empty() => String::from("[---]"),
+                        y => y,
+                    }).collect::<Vec<String>>().join(EXPECTED_SEPARATOR),
+                    finding.severity.to_string(),
+                ])?;
+            },
+            _ if finding.module == "binaries" => {
+                self.binaries_report.lock().unwrap().write_record(&[
+                    finding.file.clone(),
+                    finding.observed.join(EXPECTED_SEPARATOR),
+                    finding.expected.iter().map(|x| x.join(EXPECTED_SEPARATOR)).collect::<Vec<String>>().join(EXPECTED_SEPARATOR),
+                    finding.kind.to_string(),
+                    (!finding.yara_matches.is_empty()).to_string(),
+                    finding.yara_rules_names(),
+                    finding.severity.to_string(),
+                    finding.frequency.map_or(String::new(), |x| x.to_string()),
+                ])?;
+            },
+            _ if self.report_formats.contains(&ReportFormat::Csv) => {
+                let report_path = self.device_report_path(&finding.device, &finding.file)?;
+                if let Some(csv_writer) = self.module_reports.lock().unwrap().get_mut(&report_path) {
+                    csv_writer.write_record(&finding.to_csv_record(values_count))?;
+                }
+            },
+            _ => (),
+        };
+        if let Some(findings_report) = &self.findings_report {
+            let json_line = serde_json::to_string(finding)?;
+            findings_report.lock().unwrap().write_all(format!("{json_line}\n").as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl Observer for FileReporter {
+    fn on_event(&self,
+            event: &Event)
+    {
+        match event {
+            Event::RunStarted { phase: RunPhase::Analysis, .. } => {
+                self.fleet_summaries.lock().unwrap().clear();
+                self.outliers.lock().unwrap().clear();
+            },
+            Event::DeviceStarted { device, report_dir, identity, reference, reference_match, .. } => {
+                self.devices.lock().unwrap().insert(device.clone(), (PathBuf::from(device), report_dir.clone()));
+                self.device_summaries.lock().unwrap().insert(device.clone(), DeviceSummary::new(device.clone(),
+                        identity.clone(),
+                        reference.clone(),
+                        *reference_match
+                ));
+                if let Err(err) = self.devices_report.lock().unwrap().write_record(&[
+                    device.as_str(),
+                    identity.build_fingerprint.as_str(),
+                    identity.product_model.as_str(),
+                    reference.as_str(),
+                    reference_match.to_string().as_str(),
+                ]) {
+                    self.report_failure(device, "reported_devices.csv", &err);
+                }
+            },
+            Event::ModuleStarted { device, file, header } => {
+                if let Err(err) = self.device_report_path(device, file)
+                        .and_then(|x| self.open_module_report(x, header))
+                {
+                    self.report_failure(device, file, &err);
+                }
+            },
+            Event::Finding { finding, values_count } => {
+                if let Some(device_summary) = self.device_summaries.lock().unwrap().get_mut(&finding.device) {
+                    device_summary.add_finding(finding, &self.severity_model);
+                }
+                if finding.kind == FindingKind::Rare {
+                    self.add_outlier(finding);
+                }
+                if let Err(err) = self.write_finding(finding, *values_count) {
+                    self.report_failure(&finding.device, &finding.file, &err);
+                }
+            },
+            Event::FindingSuppressed { finding, reason } => {
+                if let Some(device_summary) = self.device_summaries.lock().unwrap().get_mut(&finding.device) {
+                    device_summary.suppressed += 1;
+                }
+                if let Err(err) = self.suppressed_report.lock().unwrap().write_record(&[
+                    finding.device.clone(),
+                    finding.module.clone(),
+                    finding.file.clone(),
+                    finding.key.clone(),
+                    finding.observed.join(EXPECTED_SEPARATOR),
+                    finding.kind.to_string(),
+                    finding.severity.to_string(),
+                    reason.clone(),
+                ]) {
+                    self.report_failure(&finding.device, "reported_suppressed.csv", &err);
+                }
+            },
+            Event::Error { device, file, kind, message } => {
+                if let Some(device_summary) = self.device_summaries.lock().unwrap().get_mut(device) {
+                    device_summary.errors += 1;
+                }
+                let _ = self.errors_report.lock().unwrap().write_record(&[
+                    device.as_str(),
+                    file.as_str(),
+                    kind,
+                    message.as_str(),
+                ]);
+            },
+            Event::DeviceFinished { device } => {
+                let device_paths = self.devices.lock().unwrap().remove(device);
+                let device_summary = self.device_summaries.lock().unwrap().remove(device);
+                if let (Some((_, device_report_dir)), Some(device_summary)) = (&device_paths, device_summary) {
+                    if let Err(err) = summary::write_device_summary(&device_report_dir.join("device_summary.json"), &device_summary) {
+                        self.report_failure(device, "device_summary.json", &err);
+                    }
+                    self.fleet_summaries.lock().unwrap().push(device_summary);
+                }
+                if let Some((_, device_report_dir)) = device_paths {
+                    self.module_reports.lock().unwrap().retain(|x, csv_writer| {
+                        match x.starts_with(&device_report_dir) {
+                            true => {
+                                let _ = csv_writer.flush();
+                                false
+                            },
+                            false => true,
+                        }
+                    });
+                }
+            },
+            Event::RunFinished { phase: RunPhase::Analysis, .. } => {
+                let mut fleet_summaries = self.fleet_summaries.lock().unwrap();
+                summary::rank_devices(&mut fleet_summaries);
+                if let Err(err) = summary::write_fleet_overview(&self.report_dir.join("reported_fleet.csv"), &fleet_summaries, self.delimiter) {
+                    self.report_failure("", "reported_fleet.csv", &err);
+                }
+                if let Err(err) = outliers::write_outliers(&self.report_dir.join("reported_outliers.csv"), &self.outliers(), self.delimiter) {
+                    self.report_failure("", "reported_outliers.csv", &err);
+                }
+            },
+            Event::RunStarted { .. } | Event::FileParsed { .. } | Event::FileScanned { .. } | Event::FileSkipped { .. } | Event::RunFinished { .. } => (),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("asc_report_test_{}_{name}", std::process::id()))
+    }
+
+    #[test]
+    fn escape_field_quotes_when_needed() {
+        assert_eq!(escape_field("ro.debuggable", ';'), "ro.debuggable");
+        assert_eq!(escape_field("", ';'), "");
+        assert_eq!(escape_field("a;b", ';'), "\"a;b\"");
+        assert_eq!(escape_field("a;b", ','), "a;b");
+        assert_eq!(escape_field("a,b", ','), "\"a,b\"");
+        assert_eq!(escape_field("say \"hi\"", ';'), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_field("line\nbreak", ';'), "\"line\nbreak\"");
+        assert_eq!(escape_field("line\rbreak", ';'), "\"line\rbreak\"");
+    }
+
+    #[test]
+    fn check_delimiter_refuses_quote_and_line_breaks() {
+        assert!(check_delimiter(';').is_ok());
+        assert!(check_delimiter('\t').is_ok());
+        assert!(check_delimiter('"').is_err());
+        assert!(check_delimiter('\n').is_err());
+        assert!(check_delimiter('\r').is_err());
+    }
+
+    #[test]
+    fn csv_writer_create_and_append() {
+        let path = test_path("create.csv");
+        let mut csv_writer = CsvWriter::create(&path, &["key", "value"], ';').unwrap();
+        csv_writer.write_record(&["ro.debuggable", "0"]).unwrap();
+        csv_writer.write_record(&["ro.build.tags", "release;keys"]).unwrap();
+        csv_writer.flush().unwrap();
+        drop(csv_writer);
+        let mut csv_writer = CsvWriter::append(&path, ';').unwrap();
+        csv_writer.write_record(&["ro.secure", "\"1\""]).unwrap();
+        csv_writer.flush().unwrap();
+        drop(csv_writer);
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "key;value\r\nro.debuggable;0\r\nro.build.tags;\"release;keys\"\r\nro.secure;\"\"\"1\"\"\"\r\n");
+        let mut csv_writer = CsvWriter::create(&path, &["module"], ',').unwrap();
+        csv_writer.flush().unwrap();
+        drop(csv_writer);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "module\r\n");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn csv_writer_sink_writes_nothing() {
+        let mut csv_writer = CsvWriter::sink(';');
+        assert!(csv_writer.write_record(&["key", "value"]).is_ok());
+        assert!(csv_writer.flush().is_ok());
+    }
+
+    #[test]
+    fn create_run_dir_refuses_an_output_dir_inside_the_evidence() {
+        let evidence_dir = test_path("run_dir_evidence");
+        std::fs::create_dir_all(evidence_dir.join("S21")).unwrap();
+        for output_dir in [evidence_dir.clone(), evidence_dir.join("S21"), evidence_dir.join("reports")] {
+            let err = create_run_dir(&output_dir, &[Path::new("/nonexistent_reference"), &evidence_dir]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+        assert!(!evidence_dir.join("reports").exists());
+        let _ = std::fs::remove_dir_all(&evidence_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn create_run_dir_refuses_a_link_into_the_evidence() {
+        let evidence_dir = test_path("run_dir_linked_evidence");
+        let link = test_path("run_dir_link");
+        std::fs::create_dir_all(&evidence_dir).unwrap();
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&evidence_dir, &link).unwrap();
+        let err = create_run_dir(&link, &[&evidence_dir]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let _ = std::fs::remove_file(&link);
+        let _ = std::fs::remove_dir_all(&evidence_dir);
+    }
+
+    #[test]
+    fn create_run_dir_creates_a_new_folder_for_each_run() {
+        let output_dir = test_path("run_dir_output");
+        let evidence_dir = test_path("run_dir_output_evidence");
+        std::fs::create_dir_all(&evidence_dir).unwrap();
+        let first_run = create_run_dir(&output_dir, &[&evidence_dir]).unwrap();
+        let second_run = create_run_dir(&output_dir, &[&evidence_dir]).unwrap();
+        assert!(first_run.is_dir() && second_run.is_dir());
+        assert_ne!(first_run, second_run);
+        assert!(first_run.starts_with(std::path::absolute(&output_dir).unwrap()));
+        assert!(first_run.file_name().unwrap().to_string_lossy().starts_with("asc_"));
+        let _ = std::fs::remove_dir_all(&output_dir);
+        let _ = std::fs::remove_dir_all(&evidence_dir);
+    }
+
+    #[test]
+    fn module_report_path_mirrors_the_device_tree() {
+        let device_root = Path::new("/evidence/S21");
+        let report_dir = Path::new("/reports/asc_20240101_000000/S21");
+        assert_eq!(module_report_path(device_root, report_dir, "/evidence/S21/getprop.txt").unwrap(),
+                report_dir.join("getprop.csv")
+        );
+        assert_eq!(module_report_path(device_root, report_dir, "/evidence/S21/dumpsys/package.txt").unwrap(),
+                report_dir.join("dumpsys").join("package.csv")
+        );
+        assert_eq!(module_report_path(device_root, report_dir, "/evidence/S21/system/bin/sh").unwrap(),
+                report_dir.join("binaries.csv")
+        );
+        assert_eq!(module_report_path(device_root, report_dir, "/evidence/S21/system/bin/toolbox/ls").unwrap(),
+                report_dir.join("binaries.csv")
+        );
+    }
+
+    #[test]
+    fn module_report_path_refuses_files_outside_the_device() {
+        let report_dir = Path::new("/reports/S21");
+        let err = module_report_path(Path::new("/evidence/S21"), report_dir, "/evidence/A52/getprop.txt").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(module_report_path(Path::new("/evidence/S21"), report_dir, "/evidence/S21").is_err());
+    }
+
+    #[test]
+    fn report_failures_are_taken_once() {
+        let report_dir = test_path("failures");
+        std::fs::create_dir_all(&report_dir).unwrap();
+        let file_reporter = FileReporter::new(report_dir.clone(), ';', &[ReportFormat::Csv], Arc::new(SeverityModel::default())).unwrap();
+        file_reporter.on_event(&Event::ModuleStarted {
+            device: String::from("unknown"),
+            file: String::from("getprop"),
+            header: vec![],
+        });
+        let failures = file_reporter.take_failures();
+        assert_eq!(failures.len(), 1);
+        assert!(matches!(&failures[0], Event::Error { device, file, kind: "io", .. } if device == "unknown" && file == "getprop"));
+        assert!(file_reporter.take_failures().is_empty());
+        let _ = std::fs::remove_dir_all(&report_dir);
+    }
+}
@@ -0,0 +1,69 @@
+//! Run summary
+//!
+//! Counters of a 'go_ref()' or 'go_parse()' run, shared by the worker threads.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// What a 'go_ref()' or 'go_parse()' run did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RunSummary {
+    /// Devices analyzed (1 for the reference).
+    pub devices: usize,
+    /// Dump files parsed and binaries hashed.
+    pub files: usize,
+    /// Findings reported.
+    pub findings: usize,
+    /// Findings suppressed as known noise, written into 'reported_suppressed.csv'.
+    pub suppressed: usize,
+    /// Failures written into 'reported_errors.csv'.
+    pub errors: usize,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RunStats {
+    devices: AtomicUsize,
+    files: AtomicUsize,
+    findings: AtomicUsize,
+    suppressed: AtomicUsize,
+    errors: AtomicUsize,
+}
+
+impl RunStats {
+    pub fn reset(&self) {
+        self.devices.store(0, Ordering::Relaxed);
+        self.files.store(0, Ordering::Relaxed);
+        self.findings.store(0, Ordering::Relaxed);
+        self.suppressed.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+    }
+
+    pub fn add_device(&self) {
+        self.devices.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_file(&self) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_finding(&self) {
+        self.findings.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_suppressed(&self) {
+        self.suppressed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn summary(&self) -> RunSummary {
+        RunSummary {
+            devices: self.devices.load(Ordering::Relaxed),
+            files: self.files.load(Ordering::Relaxed),
+            findings: self.findings.load(Ordering::Relaxed),
+            suppressed: self.suppressed.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
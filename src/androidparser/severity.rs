@@ -0,0 +1,457 @@
+//! Severity
+//!
+//! How much each finding matters, from a configurable model: rules on the
+//! module, key and kind of the findings, the 'severity' or 'score' metadata
+//! of the matching YARA rules, then the built-in rules. The severities of
+//! the findings of a device add up to its risk score, giving its verdict.
+//!
+//! ```toml
+//! default_severity = "low"
+//!
+//! [weights]
+//! critical = 50
+//!
+//! [verdict]
+//! suspicious = 10
+//! compromised = 50
+//!
+//! [[rule]]
+//! module = '^getprop$'
+//! key = '^persist\.sys\.usb\.config$'
+//! severity = "high"
+//! ```
+
+use std::{fmt, path::Path};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{AndroidParserError, Finding, FindingKind, YaraRuleMatch};
+
+/// Severity of a finding, from the least to the most severe.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    #[default]
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Severity named 'name' ('info', 'low', 'medium', 'high' or 'critical', in any case).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "info" | "informational" => Some(Severity::Info),
+            "low" => Some(Severity::Low),
+            "medium" | "moderate" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+
+    /// Severity of a YARA 'score' metadata, from 0 to 100.
+    pub fn from_score(score: i64) -> Self {
+        match score {
+            x if x >= 80 => Severity::Critical,
+            x if x >= 60 => Severity::High,
+            x if x >= 40 => Severity::Medium,
+            x if x >= 20 => Severity::Low,
+            _ => Severity::Info,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// Verdict of a device, from its risk score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    #[default]
+    Clean,
+    Suspicious,
+    Compromised,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Clean => write!(f, "clean"),
+            Verdict::Suspicious => write!(f, "suspicious"),
+            Verdict::Compromised => write!(f, "compromised"),
+        }
+    }
+}
+
+/// Risk score added by a finding of each severity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeverityWeights {
+    pub info: u64,
+    pub low: u64,
+    pub medium: u64,
+    pub high: u64,
+    pub critical: u64,
+}
+
+impl Default for SeverityWeights {
+    fn default() -> Self {
+        Self {
+            info: 0,
+            low: 1,
+            medium: 3,
+            high: 10,
+            critical: 25,
+        }
+    }
+}
+
+/// Risk scores from which a device is 'suspicious' or 'compromised'.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerdictThresholds {
+    pub suspicious: u64,
+    pub compromised: u64,
+}
+
+impl Default for VerdictThresholds {
+    fn default() -> Self {
+        Self {
+            suspicious: 10,
+            compromised: 25,
+        }
+    }
+}
+
+/// A rule of the model, as declared in the TOML file: every set field must match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeverityRule {
+    /// Regex matched against the module of the finding.
+    #[serde(default)]
+    pub module: Option<String>,
+    /// Regex matched against the key of the finding.
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub kind: Option<FindingKind>,
+    pub severity: Severity,
+}
+
+/// A 'SeverityRule' with its regexes compiled.
+#[derive(Clone, Debug)]
+struct CompiledRule {
+    module: Option<Regex>,
+    key: Option<Regex>,
+    kind: Option<FindingKind>,
+    severity: Severity,
+}
+
+impl CompiledRule {
+    fn new(rule: &SeverityRule) -> Result<Self, AndroidParserError> {
+        let compile = |pattern: &Option<String>| -> Result<Option<Regex>, AndroidParserError> {
+            match pattern {
+                Some(x) => match Regex::new(x) {
+                    Ok(y) => Ok(Some(y)),
+                    Err(err) => Err(AndroidParserError::parse(x,
+                            &format!("Invalid severity rule regex => {err}"))
+                    ),
+                },
+                None => Ok(None),
+            }
+        };
+        Ok(Self {
+            module: compile(&rule.module)?,
+            key: compile(&rule.key)?,
+            kind: rule.kind,
+            severity: rule.severity,
+        })
+    }
+
+    fn matches(&self,
+            finding: &Finding) -> bool
+    {
+        self.module.as_ref().is_none_or(|x| x.is_match(&finding.module)) &&
+                self.key.as_ref().is_none_or(|x| x.is_match(&finding.key)) &&
+                self.kind.is_none_or(|x| x == finding.kind)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SeverityModelFile {
+    default_severity: Option<Severity>,
+    builtin_rules: Option<bool>,
+    weights: Option<SeverityWeights>,
+    verdict: Option<VerdictThresholds>,
+    #[serde(rename = "rule")]
+    rules: Vec<SeverityRule>,
+}
+
+/// Severity of the findings, and risk score and verdict of the devices.
+#[derive(Clone, Debug)]
+pub struct SeverityModel {
+    /// Severity of a finding no rule matches.
+    pub default_severity: Severity,
+    pub weights: SeverityWeights,
+    pub verdict: VerdictThresholds,
+    /// Rules checked before the YARA metadata.
+    rules: Vec<CompiledRule>,
+    /// Rules checked after the YARA metadata, empty if disabled.
+    builtin_rules: Vec<CompiledRule>,
+}
+
+impl Default for SeverityModel {
+    fn default() -> Self {
+        Self {
+            default_severity: Severity::Low,
+            weights: SeverityWeights::default(),
+            verdict: VerdictThresholds::default(),
+            rules: vec![],
+            builtin_rules: builtin_rules().iter()
+                    .filter_map(|x| CompiledRule::new(x).ok())
+                    .collect(),
+        }
+    }
+}
+
+impl SeverityModel {
+    /// Read the model of the TOML file 'path'. Its rules are checked first,
+    /// then the YARA metadata, then the built-in rules unless 'builtin_rules = false'.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use android_sanity_checker::androidparser::{AndroidParserBuilder, SeverityModel};
+    ///
+    /// let severity_model = SeverityModel::from_file(Path::new("C:/asc/severity.toml")).unwrap();
+    /// let android_parser = AndroidParserBuilder::new(String::from("C:/Smartphones/S21_white"), String::from("C:/Smartphones/Analyze"))
+    ///         .severity_model(severity_model)
+    ///         .build()
+    ///         .unwrap();
+    /// ```
+    pub fn from_file(path: &Path) -> Result<Self, AndroidParserError> {
+        let content = std::fs::read_to_string(path)?;
+        let model_file: SeverityModelFile = match toml::from_str(&content) {
+            Ok(x) => x,
+            Err(err) => return Err(AndroidParserError::parse(path.to_str().unwrap_or_default(),
+                    &err.to_string())
+            ),
+        };
+        let mut severity_model = Self::default();
+        if let Some(x) = model_file.default_severity {
+            severity_model.default_severity = x;
+        }
+        if let Some(x) = model_file.weights {
+            severity_model.weights = x;
+        }
+        if let Some(x) = model_file.verdict {
+            severity_model.verdict = x;
+        }
+        if model_file.builtin_rules == Some(false) {
+            severity_model.builtin_rules.clear();
+        }
+        for rule in model_file.rules.iter() {
+            severity_model.add_rule(rule)?;
+        }
+        Ok(severity_model)
+    }
+
+    /// Add 'rule' after the rules already added, before the YARA metadata.
+    pub fn add_rule(&mut self,
+            rule: &SeverityRule) -> Result<(), AndroidParserError>
+    {
+        self.rules.push(CompiledRule::new(rule)?);
+        Ok(())
+    }
+
+    /// Severity of 'finding': the first matching rule, else the most severe
+    /// 'severity' or 'score' metadata of its YARA rules, else the first
+    /// matching built-in rule, else the default severity.
+    pub fn severity(&self,
+            finding: &Finding) -> Severity
+    {
+        if let Some(rule) = self.rules.iter().find(|x| x.matches(finding)) {
+            return rule.severity;
+        }
+        if let Some(severity) = finding.yara_matches.iter().filter_map(yara_severity).max() {
+            return severity;
+        }
+        match self.builtin_rules.iter().find(|x| x.matches(finding)) {
+            Some(rule) => rule.severity,
+            None => self.default_severity,
+        }
+    }
+
+    /// Risk score added by a finding of 'severity'.
+    pub fn weight(&self,
+            severity: Severity) -> u64
+    {
+        match severity {
+            Severity::Info => self.weights.info,
+            Severity::Low => self.weights.low,
+            Severity::Medium => self.weights.medium,
+            Severity::High => self.weights.high,
+            Severity::Critical => self.weights.critical,
+        }
+    }
+
+    /// Verdict of a device of 'risk_score'.
+    pub fn verdict(&self,
+            risk_score: u64) -> Verdict
+    {
+        match risk_score {
+            x if x >= self.verdict.compromised => Verdict::Compromised,
+            x if x >= self.verdict.suspicious => Verdict::Suspicious,
+            _ => Verdict::Clean,
+        }
+    }
+}
+
+/// Severity given by the 'severity' or 'score' metadata of a YARA rule, if any.
+fn yara_severity(yara_match: &YaraRuleMatch) -> Option<Severity> {
+    let severity = match yara_match.metadata.get("severity") {
+        Some(serde_json::Value::String(x)) => Severity::from_name(x),
+        Some(serde_json::Value::Number(x)) => x.as_i64().map(Severity::from_score),
+        _ => None,
+    };
+    severity.or(match yara_match.metadata.get("score") {
+        Some(serde_json::Value::Number(x)) => x.as_i64().map(Severity::from_score),
+        Some(serde_json::Value::String(x)) => x.parse::<i64>().ok().map(Severity::from_score),
+        _ => None,
+    })
+}
+
+/// Rules of the default model.
+fn builtin_rules() -> Vec<SeverityRule> {
+    let rule = |module: Option<&str>, key: Option<&str>, kind: Option<FindingKind>, severity: Severity| SeverityRule {
+        module: module.map(String::from),
+        key: key.map(String::from),
+        kind,
+        severity,
+    };
+    vec![
+        rule(None, None, Some(FindingKind::Rare), Severity::Low),
+        rule(Some(r"^getprop$"),
+                Some(r"^(ro\.debuggable|ro\.secure|ro\.adb\.secure|ro\.build\.type|ro\.build\.tags|ro\.boot\.verifiedbootstate|ro\.boot\.flash\.locked|ro\.boot\.veritymode|ro\.boot\.vbmeta\.device_state|ro\.boot\.selinux)$"),
+                None,
+                Severity::Critical
+        ),
+        rule(Some(r"^getprop$"),
+                Some(r"^(ro\.build\.fingerprint|ro\.build\.version\.security_patch|persist\.sys\.usb\.config|sys\.usb\.config|service\.adb\.tcp\.port|persist\.adb\.tcp\.port)$"),
+                None,
+                Severity::High
+        ),
+        rule(Some(r"^binaries$"), None, Some(FindingKind::Changed), Severity::High),
+        rule(Some(r"^binaries$"), None, None, Severity::Medium),
+        rule(None, None, Some(FindingKind::YaraMatch), Severity::High),
+        rule(None, None, Some(FindingKind::NoReference), Severity::Medium),
+        rule(Some(r"^(mount|services|pm_list_.*)$"), None, None, Severity::Medium),
+        rule(Some(r"^(df_ah|ps)$"), None, None, Severity::Info),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(module: &str,
+            key: &str,
+            kind: FindingKind,
+            yara_metadata: &[(&str, serde_json::Value)]) -> Finding
+    {
+        Finding {
+            device: String::from("/dumps/S21"),
+            module: module.to_string(),
+            file: String::new(),
+            kind,
+            key: key.to_string(),
+            observed: vec![],
+            expected: vec![],
+            yara_matches: match yara_metadata.is_empty() {
+                true => vec![],
+                false => vec![YaraRuleMatch {
+                    rule: String::from("suspicious_rule"),
+                    metadata: yara_metadata.iter().map(|(x, y)| (x.to_string(), y.clone())).collect(),
+                    ..Default::default()
+                }],
+            },
+            severity: Severity::default(),
+            frequency: None,
+        }
+    }
+
+    fn rule(module: &str,
+            severity: Severity) -> SeverityRule
+    {
+        SeverityRule { module: Some(module.to_string()), key: None, kind: None, severity }
+    }
+
+    #[test]
+    fn builtin_then_default() {
+        let severity_model = SeverityModel::default();
+        assert_eq!(severity_model.severity(&finding("getprop", "ro.debuggable", FindingKind::Changed, &[])), Severity::Critical);
+        assert_eq!(severity_model.severity(&finding("getprop", "persist.sys.usb.config", FindingKind::Added, &[])), Severity::High);
+        assert_eq!(severity_model.severity(&finding("binaries", "su", FindingKind::Changed, &[])), Severity::High);
+        assert_eq!(severity_model.severity(&finding("binaries", "su", FindingKind::Added, &[])), Severity::Medium);
+        assert_eq!(severity_model.severity(&finding("ps", "zygote", FindingKind::Changed, &[])), Severity::Info);
+        assert_eq!(severity_model.severity(&finding("getprop", "ro.debuggable", FindingKind::Rare, &[])), Severity::Low);
+        assert_eq!(severity_model.severity(&finding("getprop", "ro.product.name", FindingKind::Changed, &[])), Severity::Low);
+    }
+
+    #[test]
+    fn yara_metadata_before_builtin() {
+        let severity_model = SeverityModel::default();
+        let ps = |metadata: &[(&str, serde_json::Value)]| severity_model.severity(&finding("ps", "zygote", FindingKind::Changed, metadata));
+        assert_eq!(ps(&[("severity", serde_json::Value::from("critical"))]), Severity::Critical);
+        assert_eq!(ps(&[("severity", serde_json::Value::from(45))]), Severity::Medium);
+        assert_eq!(ps(&[("score", serde_json::Value::from(65))]), Severity::High);
+        assert_eq!(ps(&[("score", serde_json::Value::from("90"))]), Severity::Critical);
+        assert_eq!(ps(&[("severity", serde_json::Value::from("low")), ("score", serde_json::Value::from(90))]), Severity::Low);
+        assert_eq!(ps(&[("severity", serde_json::Value::from("unknown"))]), Severity::Info);
+        assert_eq!(ps(&[("author", serde_json::Value::from("asc"))]), Severity::Info);
+    }
+
+    #[test]
+    fn rules_before_yara_metadata() {
+        let mut severity_model = SeverityModel::default();
+        severity_model.add_rule(&rule("^ps$", Severity::Medium)).unwrap();
+        severity_model.add_rule(&rule(".*", Severity::Info)).unwrap();
+        assert_eq!(severity_model.severity(&finding("ps", "zygote", FindingKind::Changed, &[("severity", serde_json::Value::from("critical"))])), Severity::Medium);
+        assert_eq!(severity_model.severity(&finding("getprop", "ro.debuggable", FindingKind::Changed, &[])), Severity::Info);
+        assert!(severity_model.add_rule(&rule("(", Severity::Low)).is_err());
+    }
+
+    #[test]
+    fn without_builtin_rules() {
+        let mut severity_model = SeverityModel::default();
+        severity_model.builtin_rules.clear();
+        severity_model.default_severity = Severity::Medium;
+        assert_eq!(severity_model.severity(&finding("getprop", "ro.debuggable", FindingKind::Changed, &[])), Severity::Medium);
+        assert_eq!(severity_model.severity(&finding("getprop", "ro.debuggable", FindingKind::Changed, &[("score", serde_json::Value::from(10))])), Severity::Info);
+    }
+
+    #[test]
+    fn weights_and_verdict() {
+        let severity_model = SeverityModel::default();
+        assert_eq!(severity_model.weight(Severity::Critical), 25);
+        assert_eq!(severity_model.verdict(0), Verdict::Clean);
+        assert_eq!(severity_model.verdict(10), Verdict::Suspicious);
+        assert_eq!(severity_model.verdict(25), Verdict::Compromised);
+        assert_eq!(Severity::from_name("Moderate"), Some(Severity::Medium));
+        assert_eq!(Severity::from_score(79), Severity::High);
+    }
+}
@@ -0,0 +1,267 @@
+//! Summaries
+//!
+//! Counts of the findings of each analyzed device, written into its
+//! 'device_summary.json', and the fleet overview 'reported_fleet.csv'
+//! ranking the devices by risk score, severity then number of deviations.
+
+use std::{cmp::Reverse, collections::BTreeMap, fs::File, io::{BufWriter, Error, Write}, path::Path};
+use serde::{Deserialize, Serialize};
+
+use super::{CsvWriter, DeviceIdentity, Finding, FindingKind, ReferenceMatch, Severity, SeverityModel, Verdict};
+
+/// Findings of a module of a device, by kind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleCounts {
+    pub added: usize,
+    pub changed: usize,
+    /// Entries of the reference missing on the device.
+    pub removed: usize,
+    /// Entries matching values rarely seen in a baseline reference,
+    /// not counted as deviations.
+    #[serde(default)]
+    pub rare: usize,
+}
+
+impl ModuleCounts {
+    /// Added, changed and removed entries.
+    pub fn total(&self) -> usize {
+        self.added + self.changed + self.removed
+    }
+}
+
+/// What the analysis found on a device.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSummary {
+    /// Root directory of the device.
+    pub device: String,
+    /// Identity read from its 'getprop.txt'.
+    pub identity: DeviceIdentity,
+    /// Reference the device was compared against, empty if none.
+    pub reference: String,
+    pub reference_match: ReferenceMatch,
+    /// Added, changed and removed entries of each module.
+    pub modules: BTreeMap<String, ModuleCounts>,
+    /// Files matching YARA rules.
+    pub yara_matches: usize,
+    /// Binaries of '/system/bin' whose SHA256 sum differs from the reference.
+    pub modified_binaries: usize,
+    /// Files which could not be parsed, scanned or compared.
+    pub errors: usize,
+    /// Findings suppressed as known noise, not counted in the modules.
+    pub suppressed: usize,
+    /// Findings of each severity.
+    pub severities: BTreeMap<Severity, usize>,
+    /// Sum of the weights of the severities of the findings.
+    pub risk_score: u64,
+    pub verdict: Verdict,
+}
+
+impl DeviceSummary {
+    pub fn new(device: String,
+            identity: DeviceIdentity,
+            reference: String,
+            reference_match: ReferenceMatch) -> Self
+    {
+        Self {
+            device,
+            identity,
+            reference,
+            reference_match,
+            modules: BTreeMap::new(),
+            yara_matches: 0,
+            modified_binaries: 0,
+            errors: 0,
+            suppressed: 0,
+            severities: BTreeMap::new(),
+            risk_score: 0,
+            verdict: Verdict::default(),
+        }
+    }
+
+    /// Count 'finding' into its module, kind and severity, updating the
+    /// risk score and verdict with 'severity_model'.
+    pub fn add_finding(&mut self,
+            finding: &Finding,
+            severity_model: &SeverityModel)
+    {
+        *self.severities.entry(finding.severity).or_default() += 1;
+        self.risk_score += severity_model.weight(finding.severity);
+        self.verdict = severity_model.verdict(self.risk_score);
+        if !finding.yara_matches.is_empty() {
+            self.yara_matches += 1;
+        }
+        let module_counts = self.modules.entry(finding.module.clone()).or_default();
+        match finding.kind {
+            FindingKind::Added => module_counts.added += 1,
+            FindingKind::Changed => {
+                module_counts.changed += 1;
+                if finding.module == "binaries" {
+                    self.modified_binaries += 1;
+                }
+            },
+            FindingKind::Removed => module_counts.removed += 1,
+            FindingKind::Rare => module_counts.rare += 1,
+            FindingKind::YaraMatch | FindingKind::NoReference => (),
+        };
+    }
+
+    /// Added, changed and removed entries of every module.
+    pub fn deviations(&self) -> usize {
+        self.modules.values().map(|x| x.total()).sum()
+    }
+
+    /// Entries matching values rarely seen in a baseline, in every module.
+    pub fn rare_values(&self) -> usize {
+        self.modules.values().map(|x| x.rare).sum()
+    }
+
+    /// Counts the fleet overview is sorted by, the most severe first: risk score,
+    /// YARA matches, modified binaries, changed and removed entries, then every deviation.
+    fn severity_key(&self) -> (u64, usize, usize, usize, usize) {
+        ( self.risk_score,
+                self.yara_matches,
+                self.modified_binaries,
+                self.modules.values().map(|x| x.changed + x.removed).sum(),
+                self.deviations()
+        )
+    }
+}
+
+/// Sort 'summaries' from the most to the least deviating device.
+pub fn rank_devices(summaries: &mut [DeviceSummary]) {
+    summaries.sort_by_key(|x| (Reverse(x.severity_key()), x.device.clone()));
+}
+
+/// Write 'summary' into the JSON file 'path'.
+pub(crate) fn write_device_summary(path: &Path,
+        summary: &DeviceSummary) -> Result<(), Error>
+{
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut buf_writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut buf_writer, summary)?;
+    buf_writer.flush()
+}
+
+/// Write the already ranked 'summaries' into the CSV report 'path'.
+pub(crate) fn write_fleet_overview(path: &Path,
+        summaries: &[DeviceSummary],
+        delimiter: char) -> Result<(), Error>
+{
+    let mut csv_writer = CsvWriter::create(path,
+            &["rank", "device", "product_model", "build_fingerprint", "reference", "verdict", "risk_score", "yara_matches", "modified_binaries", "added", "changed", "removed", "deviations", "rare", "suppressed", "errors"],
+            delimiter
+    )?;
+    for (index, summary) in summaries.iter().enumerate() {
+        let module_counts = summary.modules.values().fold(ModuleCounts::default(), |x, y| ModuleCounts {
+            added: x.added + y.added,
+            changed: x.changed + y.changed,
+            removed: x.removed + y.removed,
+            rare: x.rare + y.rare,
+        });
+        csv_writer.write_record(&[
+            (index + 1).to_string(),
+            summary.device.clone(),
+            summary.identity.product_model.clone(),
+            summary.identity.build_fingerprint.clone(),
+            summary.reference.clone(),
+            summary.verdict.to_string(),
+            summary.risk_score.to_string(),
+            summary.yara_matches.to_string(),
+            summary.modified_binaries.to_string(),
+            module_counts.added.to_string(),
+            module_counts.changed.to_string(),
+            module_counts.removed.to_string(),
+            summary.deviations().to_string(),
+            module_counts.rare.to_string(),
+            summary.suppressed.to_string(),
+            summary.errors.to_string(),
+        ])?;
+    }
+    csv_writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device_summary(device: &str,
+            findings: &[(&str, FindingKind, Severity)]) -> DeviceSummary
+    {
+        let severity_model = SeverityModel::default();
+        let mut summary = DeviceSummary::new(device.to_string(), DeviceIdentity::default(), String::from("golden.sqlite"), ReferenceMatch::Single);
+        findings.iter().for_each(|(module, kind, severity)| summary.add_finding(&Finding {
+            device: device.to_string(),
+            module: module.to_string(),
+            file: String::new(),
+            kind: *kind,
+            key: String::from("key"),
+            observed: vec![],
+            expected: vec![],
+            yara_matches: vec![],
+            severity: *severity,
+            frequency: None,
+        }, &severity_model));
+        summary
+    }
+
+    #[test]
+    fn add_finding_counts_the_modules_severities_and_verdict() {
+        let summary = device_summary("S21", &[
+            ("getprop", FindingKind::Added, Severity::Low),
+            ("getprop", FindingKind::Changed, Severity::High),
+            ("binaries", FindingKind::Changed, Severity::Critical),
+            ("mount", FindingKind::Removed, Severity::Medium),
+            ("getprop", FindingKind::Rare, Severity::Info),
+        ]);
+        assert_eq!(summary.modules["getprop"], ModuleCounts { added: 1, changed: 1, removed: 0, rare: 1 });
+        assert_eq!(summary.modified_binaries, 1);
+        assert_eq!(summary.deviations(), 4);
+        assert_eq!(summary.rare_values(), 1);
+        assert_eq!(summary.severities[&Severity::Critical], 1);
+        assert_eq!(summary.risk_score, 1 + 10 + 25 + 3);
+        assert_eq!(summary.verdict, Verdict::Compromised);
+    }
+
+    #[test]
+    fn rank_devices_puts_the_most_severe_first() {
+        let mut summaries = vec![
+            device_summary("many_low", &[
+                ("getprop", FindingKind::Added, Severity::Low),
+                ("getprop", FindingKind::Added, Severity::Low),
+                ("getprop", FindingKind::Added, Severity::Low),
+            ]),
+            device_summary("clean", &[]),
+            device_summary("one_high", &[("getprop", FindingKind::Changed, Severity::High)]),
+            device_summary("binary", &[("binaries", FindingKind::Changed, Severity::Low)]),
+            device_summary("added", &[("getprop", FindingKind::Added, Severity::Low)]),
+            device_summary("another_clean", &[]),
+        ];
+        rank_devices(&mut summaries);
+        assert_eq!(summaries.iter().map(|x| x.device.as_str()).collect::<Vec<&str>>(),
+                vec!["one_high", "many_low", "binary", "added", "another_clean", "clean"]
+        );
+    }
+
+    #[test]
+    fn fleet_overview_writes_one_ranked_row_per_device() {
+        let path = std::env::temp_dir().join(format!("asc_fleet_overview_{}.csv", std::process::id()));
+        let mut summaries = vec![
+            device_summary("A52", &[]),
+            device_summary("S21", &[
+                ("getprop", FindingKind::Changed, Severity::High),
+                ("mount", FindingKind::Removed, Severity::Medium),
+            ]),
+        ];
+        rank_devices(&mut summaries);
+        write_fleet_overview(&path, &summaries, ';').unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("rank;device;"));
+        assert_eq!(lines[1], "1;S21;;;golden.sqlite;suspicious;13;0;0;0;1;1;2;0;0;0");
+        assert_eq!(lines[2], "2;A52;;;golden.sqlite;clean;0;0;0;0;0;0;0;0;0;0");
+        let _ = std::fs::remove_file(&path);
+    }
+}
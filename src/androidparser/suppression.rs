@@ -0,0 +1,286 @@
+//! Suppressions
+//!
+//! Known noise of the comparisons (serial numbers, timestamps, PIDs...)
+//! suppressed from the findings: exact keys and key regexes of any module,
+//! and rules on the module, key, observed values and kind. Suppressed
+//! findings are counted and written into 'reported_suppressed.csv'.
+//!
+//! ```toml
+//! keys = ["ro.boot.serialno", "ro.serialno"]
+//! patterns = ['^ro\.boot\.bootreason$']
+//!
+//! [[rule]]
+//! module = '^ps$'
+//! kind = "changed"
+//! reason = "PIDs differ on every phone"
+//! ```
+
+use std::{collections::HashSet, path::Path};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{AndroidParserError, Finding, FindingKind};
+
+/// A rule of the suppression file: every set field must match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SuppressionRule {
+    /// Regex matched against the module of the finding.
+    #[serde(default)]
+    pub module: Option<String>,
+    /// Regex matched against the key of the finding.
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Regex matched against any observed value of the finding.
+    #[serde(default)]
+    pub value: Option<String>,
+    /// Kind of the finding: 'added', 'changed', 'removed', 'yara_match' or 'rare'.
+    #[serde(default)]
+    pub kind: Option<FindingKind>,
+    /// Why these findings are noise, written into 'reported_suppressed.csv'.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// A 'SuppressionRule' with its regexes compiled.
+#[derive(Clone, Debug)]
+struct CompiledRule {
+    module: Option<Regex>,
+    key: Option<Regex>,
+    value: Option<Regex>,
+    kind: Option<FindingKind>,
+    reason: String,
+}
+
+impl CompiledRule {
+    fn new(rule: &SuppressionRule) -> Result<Self, AndroidParserError> {
+        let compile = |pattern: &Option<String>| -> Result<Option<Regex>, AndroidParserError> {
+            match pattern {
+                Some(x) => match Regex::new(x) {
+                    Ok(y) => Ok(Some(y)),
+                    Err(err) => Err(AndroidParserError::parse(x,
+                            &format!("Invalid suppression rule regex => {err}"))
+                    ),
+                },
+                None => Ok(None),
+            }
+        };
+        Ok(Self {
+            module: compile(&rule.module)?,
+            key: compile(&rule.key)?,
+            value: compile(&rule.value)?,
+            kind: rule.kind,
+            reason: rule.reason.clone().unwrap_or(String::from("suppression rule")),
+        })
+    }
+
+    fn matches(&self,
+            finding: &Finding) -> bool
+    {
+        self.module.as_ref().is_none_or(|x| x.is_match(&finding.module)) &&
+                self.key.as_ref().is_none_or(|x| x.is_match(&finding.key)) &&
+                self.value.as_ref().is_none_or(|x| finding.observed.iter().any(|y| x.is_match(y))) &&
+                self.kind.is_none_or(|x| x == finding.kind)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SuppressionFile {
+    keys: Vec<String>,
+    patterns: Vec<String>,
+    #[serde(rename = "rule")]
+    rules: Vec<SuppressionRule>,
+}
+
+/// Findings not reported as deviations. Empty by default.
+#[derive(Clone, Debug, Default)]
+pub struct Suppressions {
+    /// Keys suppressed in every module.
+    keys: HashSet<String>,
+    /// Regexes of the keys suppressed in every module.
+    patterns: Vec<Regex>,
+    rules: Vec<CompiledRule>,
+}
+
+impl Suppressions {
+    /// Read the suppressions of the TOML file 'path'.
+    ///
+    /// # Exemple
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use android_sanity_checker::androidparser::Suppressions;
+    ///
+    /// let suppressions = Suppressions::from_file(Path::new("C:/asc/suppressions.toml")).unwrap();
+    /// ```
+    pub fn from_file(path: &Path) -> Result<Self, AndroidParserError> {
+        let content = std::fs::read_to_string(path)?;
+        let suppression_file: SuppressionFile = match toml::from_str(&content) {
+            Ok(x) => x,
+            Err(err) => return Err(AndroidParserError::parse(path.to_str().unwrap_or_default(),
+                    &err.to_string())
+            ),
+        };
+        let mut suppressions = Self::default();
+        suppression_file.keys.into_iter().for_each(|x| suppressions.add_key(x));
+        for pattern in suppression_file.patterns.iter() {
+            suppressions.add_pattern(pattern)?;
+        }
+        for rule in suppression_file.rules.iter() {
+            suppressions.add_rule(rule)?;
+        }
+        Ok(suppressions)
+    }
+
+    /// Suppress the findings of 'key', in every module.
+    pub fn add_key(&mut self,
+            key: String)
+    {
+        self.keys.insert(key);
+    }
+
+    /// Suppress the findings whose key matches the regex 'pattern', in every module.
+    pub fn add_pattern(&mut self,
+            pattern: &str) -> Result<(), AndroidParserError>
+    {
+        match Regex::new(pattern) {
+            Ok(x) => {
+                self.patterns.push(x);
+                Ok(())
+            },
+            Err(err) => Err(AndroidParserError::parse(pattern,
+                    &format!("Invalid suppression pattern => {err}"))
+            ),
+        }
+    }
+
+    /// Suppress the findings matching every set field of 'rule'.
+    pub fn add_rule(&mut self,
+            rule: &SuppressionRule) -> Result<(), AndroidParserError>
+    {
+        self.rules.push(CompiledRule::new(rule)?);
+        Ok(())
+    }
+
+    /// Whether no finding can be suppressed: no key, pattern nor rule.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.patterns.is_empty() && self.rules.is_empty()
+    }
+
+    /// Why 'finding' is suppressed, None if it is reported.
+    /// Findings without reference are never suppressed.
+    pub fn suppression(&self,
+            finding: &Finding) -> Option<String>
+    {
+        if finding.kind == FindingKind::NoReference {
+            return None;
+        }
+        if self.keys.contains(&finding.key) {
+            return Some(format!("key {}", finding.key));
+        }
+        if let Some(pattern) = self.patterns.iter().find(|x| x.is_match(&finding.key)) {
+            return Some(format!("pattern {}", pattern.as_str()));
+        }
+        self.rules.iter()
+                .find(|x| x.matches(finding))
+                .map(|x| x.reason.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn getprop_finding(kind: FindingKind,
+            key: &str,
+            observed: &str) -> Finding
+    {
+        Finding {
+            device: String::from("/evidence/S21"),
+            module: String::from("getprop"),
+            file: String::from("/evidence/S21/getprop.txt"),
+            kind,
+            key: key.to_string(),
+            observed: vec![observed.to_string()],
+            expected: vec![],
+            yara_matches: vec![],
+            severity: Default::default(),
+            frequency: None,
+        }
+    }
+
+    #[test]
+    fn keys_and_patterns_suppress_in_every_module() {
+        let mut suppressions = Suppressions::default();
+        assert!(suppressions.is_empty());
+        suppressions.add_key(String::from("ro.serialno"));
+        suppressions.add_pattern(r"^ro\.boot\.").unwrap();
+        assert!(!suppressions.is_empty());
+        assert_eq!(suppressions.suppression(&getprop_finding(FindingKind::Changed, "ro.serialno", "R58M")),
+                Some(String::from("key ro.serialno"))
+        );
+        let mut finding = getprop_finding(FindingKind::Added, "ro.boot.bootreason", "reboot");
+        finding.module = String::from("settings_global");
+        assert_eq!(suppressions.suppression(&finding), Some(String::from(r"pattern ^ro\.boot\.")));
+        assert_eq!(suppressions.suppression(&getprop_finding(FindingKind::Changed, "ro.debuggable", "1")), None);
+        assert!(suppressions.add_pattern("(unclosed").is_err());
+    }
+
+    #[test]
+    fn rules_match_every_set_field() {
+        let mut suppressions = Suppressions::default();
+        suppressions.add_rule(&SuppressionRule {
+            module: Some(String::from("^getprop$")),
+            key: None,
+            value: Some(String::from(r"^\d{10}$")),
+            kind: Some(FindingKind::Changed),
+            reason: Some(String::from("timestamps")),
+        }).unwrap();
+        assert_eq!(suppressions.suppression(&getprop_finding(FindingKind::Changed, "ro.build.date.utc", "1700000000")),
+                Some(String::from("timestamps"))
+        );
+        assert_eq!(suppressions.suppression(&getprop_finding(FindingKind::Added, "ro.build.date.utc", "1700000000")), None);
+        assert_eq!(suppressions.suppression(&getprop_finding(FindingKind::Changed, "ro.build.date", "Mon")), None);
+        let mut finding = getprop_finding(FindingKind::Changed, "ro.build.date.utc", "1700000000");
+        finding.module = String::from("mount");
+        assert_eq!(suppressions.suppression(&finding), None);
+        assert!(suppressions.add_rule(&SuppressionRule {
+            module: None,
+            key: Some(String::from("[")),
+            value: None,
+            kind: None,
+            reason: None,
+        }).is_err());
+    }
+
+    #[test]
+    fn findings_without_reference_are_never_suppressed() {
+        let mut suppressions = Suppressions::default();
+        suppressions.add_key(String::from("ro.build.fingerprint"));
+        suppressions.add_pattern(".*").unwrap();
+        suppressions.add_rule(&SuppressionRule { module: None, key: None, value: None, kind: None, reason: None }).unwrap();
+        assert_eq!(suppressions.suppression(&getprop_finding(FindingKind::NoReference, "ro.build.fingerprint", "")), None);
+    }
+
+    #[test]
+    fn from_file_reads_keys_patterns_and_rules() {
+        let path = std::env::temp_dir().join(format!("asc_suppressions_{}.toml", std::process::id()));
+        std::fs::write(&path, concat!(
+            "keys = [\"ro.serialno\"]\n",
+            "patterns = ['^persist\\.']\n",
+            "\n",
+            "[[rule]]\n",
+            "module = '^ps$'\n",
+            "kind = \"changed\"\n",
+        )).unwrap();
+        let suppressions = Suppressions::from_file(&path).unwrap();
+        assert!(suppressions.suppression(&getprop_finding(FindingKind::Changed, "ro.serialno", "R58M")).is_some());
+        assert!(suppressions.suppression(&getprop_finding(FindingKind::Changed, "persist.sys.locale", "fr-FR")).is_some());
+        let mut finding = getprop_finding(FindingKind::Changed, "init", "1");
+        finding.module = String::from("ps");
+        assert_eq!(suppressions.suppression(&finding), Some(String::from("suppression rule")));
+        std::fs::write(&path, "keys = \"not a list\"\n").unwrap();
+        assert!(matches!(Suppressions::from_file(&path), Err(AndroidParserError::Parse { .. })));
+        let _ = std::fs::remove_file(&path);
+    }
+}
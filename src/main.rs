@@ -1,16 +1,750 @@
-use std::{env::current_dir, time::Instant};
-use rfd;
+use std::{path::{Path, PathBuf}, process::ExitCode, time::Instant};
+use clap::{Args, Parser, Subcommand};
 use indicatif::HumanDuration;
 use console::style;
 
-use android_sanity_checker::androidparser::AndroidParser;
+use android_sanity_checker::androidparser::{AndroidParserBuilder, create_run_dir, DEFAULT_CSV_DELIMITER, DEFAULT_RARE_THRESHOLD, DeviceSummary, diff_references, DiffKind, Normalizers, Outlier, ReferenceMetadata, RunSummary, SeverityModel, Suppressions, Verdict, yara_customizer};
 
-fn main() {
+mod progress;
+use progress::ProgressObserver;
+
+/// Exit code when the work is done.
+const EXIT_SUCCESS: u8 = 0;
+/// Exit code when the run could not be completed (missing directory, IO error...).
+const EXIT_FAILURE: u8 = 1;
+/// Exit code when the command line is invalid (same as clap).
+const EXIT_USAGE: u8 = 2;
+/// Exit code when the work is done, with findings (or differences) to review.
+const EXIT_FINDINGS: u8 = 3;
+/// Exit code when the work is done, but some files could not be read, parsed or reported
+/// (see 'reported_errors.csv'), whatever the findings.
+const EXIT_ERRORS: u8 = 4;
+/// Devices of the fleet overview printed at the end of the analysis.
+const FLEET_PRINTED_DEVICES: usize = 5;
+/// Rarest values printed at the end of the analysis.
+const PRINTED_OUTLIERS: usize = 10;
+
+#[derive(Parser)]
+#[command(name = "android_sanity_checker", version, about = "Compare ADB dumps of android smartphones against a white reference.")]
+struct Cli {
+    /// Without subcommand, the interactive dialogs are used.
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the reference from a white ADB dump and report how it went.
+    Ref(RefArgs),
+    /// Compare every device of the analysis directory against the reference.
+    Analyze(AnalyzeArgs),
+    /// Diff two dumps both ways (a device at two dates, or two suspects), without reference.
+    Diff(DiffArgs),
+    /// Diff two saved or exported references (old and new firmware) both ways, to review an update.
+    #[command(name = "ref-diff")]
+    RefDiff(RefDiffArgs),
+    /// Find and compile a YARA rules directory, reporting skipped rules.
+    Rules {
+        /// Directory of custom YARA rules.
+        #[arg(short, long)]
+        yara: PathBuf,
+        /// Save the compiled rules to this file.
+        #[arg(short, long)]
+        save: Option<PathBuf>,
+    },
+    /// Choose directories with dialogs.
+    Interactive,
+}
+
+/// Arguments of the 'ref' subcommand.
+#[derive(Args)]
+struct RefArgs {
+    /// Directory of the reference device (the White ADB Dump), or of many
+    /// known-good dumps building a baseline, or a saved or exported reference.
+    #[arg(short, long)]
+    reference: PathBuf,
+    /// Save the reference into this '.sqlite' file.
+    #[arg(short, long)]
+    save: Option<PathBuf>,
+    /// Save the reference into this library directory, named after its model and build fingerprint.
+    #[arg(short, long)]
+    library: Option<PathBuf>,
+    /// Export the reference into this directory, one editable JSON file per module.
+    #[arg(short, long)]
+    export: Option<PathBuf>,
+    /// Number of worker threads (default: number of CPUs).
+    #[arg(short, long)]
+    threads: Option<usize>,
+    /// TOML file of declared parsers, used besides the built-in ones.
+    #[arg(short, long)]
+    parsers: Option<PathBuf>,
+    /// TOML file of normalizers of the parsed values, besides the built-in ones. Give the same file to 'ref' and 'analyze'.
+    #[arg(short, long)]
+    normalize: Option<PathBuf>,
+    /// No progress bars, only the warnings and errors (implied when stderr is not a terminal).
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Arguments of the 'analyze' subcommand.
+#[derive(Args)]
+struct AnalyzeArgs {
+    /// Directory of the reference device (the White ADB Dump) or of many known-good dumps,
+    /// a saved '.sqlite' reference, or a library directory of saved references matched per device.
+    #[arg(short, long, required_unless_present = "outliers", conflicts_with = "outliers")]
+    reference: Option<PathBuf>,
+    /// Without reference, compare the devices against each other and report
+    /// the values held by only one or a small minority of them (see '--rare').
+    #[arg(long)]
+    outliers: bool,
+    /// Directory holding the devices to analyze.
+    #[arg(short, long)]
+    analyze: PathBuf,
+    /// Optional directory of custom YARA rules.
+    #[arg(short, long)]
+    yara: Option<PathBuf>,
+    /// Directory where the timestamped folder of the run is created (default: current directory).
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Number of worker threads (default: number of CPUs).
+    #[arg(short, long)]
+    threads: Option<usize>,
+    /// Field delimiter of the CSV reports.
+    #[arg(short, long, default_value_t = ';')]
+    delimiter: char,
+    /// TOML file of declared parsers, used besides the built-in ones.
+    #[arg(short, long)]
+    parsers: Option<PathBuf>,
+    /// TOML file of normalizers of the parsed values, besides the built-in ones. Give the same file to 'ref' and 'analyze'.
+    #[arg(short, long)]
+    normalize: Option<PathBuf>,
+    /// TOML file of the severity model (rules, weights and verdict thresholds).
+    #[arg(long)]
+    severity: Option<PathBuf>,
+    /// TOML file of the known noise (keys, key regexes and rules) suppressed from the findings.
+    #[arg(long)]
+    suppress: Option<PathBuf>,
+    /// Share of the dumps of a baseline reference up to which a matching value is reported as rare.
+    #[arg(long, default_value_t = DEFAULT_RARE_THRESHOLD)]
+    rare: f64,
+    /// No progress bars, only the warnings and errors (implied when stderr is not a terminal).
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+impl AnalyzeArgs {
+    /// Analysis of the devices of 'analyze' against 'reference', with the default options.
+    fn new(reference: PathBuf,
+            analyze: PathBuf) -> Self
+    {
+        Self {
+            reference: Some(reference),
+            outliers: false,
+            analyze,
+            yara: None,
+            output: None,
+            threads: None,
+            delimiter: DEFAULT_CSV_DELIMITER,
+            parsers: None,
+            normalize: None,
+            severity: None,
+            suppress: None,
+            rare: DEFAULT_RARE_THRESHOLD,
+            quiet: false,
+        }
+    }
+}
+
+/// Arguments of the 'diff' subcommand.
+#[derive(Args)]
+struct DiffArgs {
+    /// Directory of the first dump.
+    #[arg(short = 'a', long)]
+    device_a: PathBuf,
+    /// Directory of the second dump.
+    #[arg(short = 'b', long)]
+    device_b: PathBuf,
+    /// Directory where the timestamped folder of the run is created (default: current directory).
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Number of worker threads (default: number of CPUs).
+    #[arg(short, long)]
+    threads: Option<usize>,
+    /// Field delimiter of the CSV reports.
+    #[arg(short, long, default_value_t = ';')]
+    delimiter: char,
+    /// TOML file of declared parsers, used besides the built-in ones.
+    #[arg(short, long)]
+    parsers: Option<PathBuf>,
+    /// TOML file of normalizers of the parsed values, besides the built-in ones.
+    #[arg(short, long)]
+    normalize: Option<PathBuf>,
+    /// No progress bars, only the errors (implied when stderr is not a terminal).
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Arguments of the 'ref-diff' subcommand.
+#[derive(Args)]
+struct RefDiffArgs {
+    /// Saved (SQLite file) or exported (JSON directory) reference of the old firmware.
+    #[arg(long)]
+    old: PathBuf,
+    /// Saved (SQLite file) or exported (JSON directory) reference of the new firmware.
+    #[arg(long)]
+    new: PathBuf,
+    /// Directory where the timestamped folder of the run is created (default: current directory).
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Field delimiter of the CSV reports.
+    #[arg(short, long, default_value_t = ';')]
+    delimiter: char,
+}
+
+fn main() -> ExitCode {
 
     // !! FOR DEBUG !!
     // env::set_var("RUST_BACKTRACE", "1");
     // !! FOR DEBUG !!
 
+    let cli = match Cli::try_parse() {
+        Ok(x) => x,
+        Err(err) => {
+            let _ = err.print();
+            return match err.use_stderr() {
+                true => ExitCode::from(EXIT_USAGE),
+                false => ExitCode::from(EXIT_SUCCESS),
+            };
+        },
+    };
+    match cli.command {
+        Some(Command::Ref(args)) => run_ref(args),
+        Some(Command::Analyze(args)) => run_analysis(args),
+        Some(Command::Diff(args)) => run_diff(args),
+        Some(Command::RefDiff(args)) => run_ref_diff(args),
+        Some(Command::Rules { yara, save }) => run_rules(yara, save),
+        Some(Command::Interactive) | None => run_interactive(),
+    }
+}
+
+/// Exit code of a completed run, from its count of findings and of per-file errors.
+fn completed_exit_code(findings: usize,
+        errors: usize) -> ExitCode
+{
+    match (findings, errors) {
+        (_, 1..) => ExitCode::from(EXIT_ERRORS),
+        (1.., _) => ExitCode::from(EXIT_FINDINGS),
+        _ => ExitCode::from(EXIT_SUCCESS),
+    }
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// 'path' where it must not be changed, None (the error being printed) if it is not valid UTF-8.
+fn utf8_path(path: &Path) -> Option<&str> {
+    match path.to_str() {
+        Some(x) => Some(x),
+        None => {
+            eprintln!("{} Path {} is not valid UTF-8",
+                    style("[ERROR]").bold().dim().red(),
+                    path.display()
+            );
+            None
+        },
+    }
+}
+
+fn print_summary(summary: &RunSummary) {
+    println!("\tDevice(s) : {}\n\tFile(s) : {}\n\tFinding(s) : {}\n\tSuppressed finding(s) : {}\n\tError(s) : {}",
+            summary.devices,
+            summary.files,
+            summary.findings,
+            summary.suppressed,
+            match summary.errors {
+                0 => style(summary.errors).green(),
+                _ => style(summary.errors).bold().red(),
+            }
+    );
+}
+
+/// Print the most deviating devices of the fleet overview.
+fn print_fleet(device_summaries: &[DeviceSummary]) {
+    device_summaries.iter().take(FLEET_PRINTED_DEVICES).for_each(|x| {
+        println!("\t{} => {} (risk score {}), YARA match(es) : {}, Modified binaries : {}, Deviation(s) : {}, Rare value(s) : {}",
+                x.device,
+                match x.verdict {
+                    Verdict::Clean => style(x.verdict).green(),
+                    Verdict::Suspicious => style(x.verdict).bold().yellow(),
+                    Verdict::Compromised => style(x.verdict).bold().red(),
+                },
+                x.risk_score,
+                match x.yara_matches {
+                    0 => style(x.yara_matches).green(),
+                    _ => style(x.yara_matches).bold().red(),
+                },
+                match x.modified_binaries {
+                    0 => style(x.modified_binaries).green(),
+                    _ => style(x.modified_binaries).bold().red(),
+                },
+                x.deviations(),
+                x.rare_values()
+        );
+    });
+}
+
+/// Print the rarest values of the baseline, with the devices holding them.
+fn print_outliers(outliers: &[Outlier]) {
+    outliers.iter().take(PRINTED_OUTLIERS).for_each(|x| {
+        println!("\t{} {} {} = {} => seen on {}/{} : {}",
+                style("[RARE]").bold().yellow(),
+                x.module,
+                x.key,
+                x.values.join(" "),
+                x.frequency.seen,
+                x.frequency.baseline,
+                x.devices.join(", ")
+        );
+    });
+}
+
+/// The built-in normalizers and those of 'normalize', if any. None if the file is invalid.
+fn load_normalizers(normalize: Option<PathBuf>) -> Option<Normalizers> {
+    let normalize = match normalize {
+        Some(x) => x,
+        None => return Some(Normalizers::builtin()),
+    };
+    match Normalizers::from_file(&normalize) {
+        Ok(x) => Some(x),
+        Err(err) => {
+            eprintln!("{} Normalizers file {} => {}",
+                    style("[ERROR]").bold().dim().red(),
+                    normalize.display(),
+                    err
+            );
+            None
+        },
+    }
+}
+
+fn run_ref(args: RefArgs) -> ExitCode {
+    let RefArgs { reference, save, library, export, threads, parsers, normalize, quiet } = args;
+    let ref_dir = path_to_string(&reference);
+    let start_global = Instant::now();
+    let mut builder = AndroidParserBuilder::new(ref_dir.clone(), ref_dir)
+            .threads(threads.unwrap_or(num_cpus::get()))
+            .observer(ProgressObserver::new(quiet));
+    if let Some(parsers_path) = parsers {
+        builder = builder.parsers_file(parsers_path);
+    }
+    match load_normalizers(normalize) {
+        Some(x) => builder = builder.normalizers(x),
+        None => return ExitCode::from(EXIT_FAILURE),
+    };
+    let android_parser = match builder.build() {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("{} Reference folder {} => {}",
+                    style("[ERROR]").bold().dim().red(),
+                    reference.display(),
+                    err
+            );
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    println!("{} Creating reference into SQLite DB.\n\tPlease wait...",
+            style("[1/2]").bold().dim().green()
+    );
+    let ref_summary = match android_parser.go_ref() {
+        Ok(summary) => {
+            print_summary(&summary);
+            summary
+        },
+        Err(err) => {
+            eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    println!("\tCreating reference duration : {}",
+            HumanDuration(start_global.elapsed())
+    );
+    if let Some(save) = save {
+        println!("{} Saving reference => {}",
+                style("[2/2]").bold().dim().green(),
+                save.display()
+        );
+        let save_path = match utf8_path(&save) {
+            Some(x) => x,
+            None => return ExitCode::from(EXIT_FAILURE),
+        };
+        match android_parser.save_reference(save_path) {
+            Ok(metadata) => println!("\tBuild fingerprint : {}\n\tProduct model : {}",
+                    metadata.build_fingerprint,
+                    metadata.product_model
+            ),
+            Err(err) => {
+                eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+                return ExitCode::from(EXIT_FAILURE);
+            },
+        };
+    }
+    if let Some(library) = library {
+        let library_path = match utf8_path(&library) {
+            Some(x) => x,
+            None => return ExitCode::from(EXIT_FAILURE),
+        };
+        match android_parser.save_reference_to_library(library_path) {
+            Ok((path, metadata)) => println!("{} Reference added to library => {}\n\tBuild fingerprint : {}\n\tProduct model : {}",
+                    style("[2/2]").bold().dim().green(),
+                    path.display(),
+                    metadata.build_fingerprint,
+                    metadata.product_model
+            ),
+            Err(err) => {
+                eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+                return ExitCode::from(EXIT_FAILURE);
+            },
+        };
+    }
+    if let Some(export) = export {
+        match android_parser.export_reference(&export) {
+            Ok(document) => println!("{} Reference exported => {}\n\tModule(s) : {}",
+                    style("[2/2]").bold().dim().green(),
+                    export.display(),
+                    document.modules.join(", ")
+            ),
+            Err(err) => {
+                eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+                return ExitCode::from(EXIT_FAILURE);
+            },
+        };
+    }
+    completed_exit_code(0, ref_summary.errors)
+}
+
+fn run_diff(args: DiffArgs) -> ExitCode {
+    let DiffArgs { device_a, device_b, output, threads, delimiter, parsers, normalize, quiet } = args;
+    let start_global = Instant::now();
+    let mut builder = AndroidParserBuilder::new(path_to_string(&device_a), path_to_string(&device_b))
+            .threads(threads.unwrap_or(num_cpus::get()))
+            .csv_delimiter(delimiter)
+            .embedded_rules(false)
+            .load_reference(false)
+            .observer(ProgressObserver::new(quiet));
+    if let Some(output_path) = output {
+        builder = builder.output_dir(path_to_string(&output_path));
+    }
+    if let Some(parsers_path) = parsers {
+        builder = builder.parsers_file(parsers_path);
+    }
+    match load_normalizers(normalize) {
+        Some(x) => builder = builder.normalizers(x),
+        None => return ExitCode::from(EXIT_FAILURE),
+    };
+    let android_parser = match builder.build() {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    println!("{} Diffing {} (A) and {} (B).\n\tPlease wait...",
+            style("[1/1]").bold().dim().green(),
+            device_a.display(),
+            device_b.display()
+    );
+    let device_diff = match android_parser.diff_devices() {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    device_diff.modules().iter().for_each(|(module, kinds)| {
+        println!("\t{} => only in A : {}, only in B : {}, changed : {}",
+                module,
+                kinds.get(&DiffKind::OnlyInA).unwrap_or(&0),
+                kinds.get(&DiffKind::OnlyInB).unwrap_or(&0),
+                kinds.get(&DiffKind::Changed).unwrap_or(&0)
+        );
+    });
+    println!("\tOnly in A : {}\n\tOnly in B : {}\n\tChanged : {}\n\tError(s) : {}",
+            device_diff.count(DiffKind::OnlyInA),
+            device_diff.count(DiffKind::OnlyInB),
+            device_diff.count(DiffKind::Changed),
+            match device_diff.errors {
+                0 => style(device_diff.errors).green(),
+                _ => style(device_diff.errors).bold().red(),
+            }
+    );
+    println!("\tGlobal duration : {}",
+          HumanDuration(start_global.elapsed())
+    );
+    println!("{}\nCheck at :\n\t{1}{2}reported_diff.csv\n\t{1}{2}device_diff.json\nto find the differences, and\n\t{1}{2}reported_errors.csv\nfor the files which could not be parsed.",
+            style("[WORK DONE]").bold().magenta(),
+            path_to_string(android_parser.report_dir()),
+            std::path::MAIN_SEPARATOR
+    );
+    completed_exit_code(device_diff.count(DiffKind::OnlyInA) + device_diff.count(DiffKind::OnlyInB) + device_diff.count(DiffKind::Changed),
+            device_diff.errors
+    )
+}
+
+fn run_ref_diff(args: RefDiffArgs) -> ExitCode {
+    let RefDiffArgs { old, new, output, delimiter } = args;
+    let start_global = Instant::now();
+    let output_dir = match output {
+        Some(x) => Ok(x),
+        None => std::env::current_dir(),
+    };
+    let report_dir = match output_dir.and_then(|x| create_run_dir(&x, &[old.as_path(), new.as_path()])) {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    println!("{} Diffing the references {} (old) and {} (new).\n\tPlease wait...",
+            style("[1/1]").bold().dim().green(),
+            old.display(),
+            new.display()
+    );
+    for (label, path) in [("Old", &old), ("New", &new)] {
+        if let Some(metadata) = ReferenceMetadata::from_file(path) {
+            println!("\t{} firmware : {} (built {})",
+                    label,
+                    metadata.build_fingerprint,
+                    metadata.created_at
+            );
+        }
+    }
+    let firmware_diff = match diff_references(&old, &new, &report_dir, delimiter) {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    firmware_diff.modules().iter().for_each(|(module, kinds)| {
+        println!("\t{} => removed : {}, added : {}, changed : {}",
+                module,
+                kinds.get(&DiffKind::OnlyInA).unwrap_or(&0),
+                kinds.get(&DiffKind::OnlyInB).unwrap_or(&0),
+                kinds.get(&DiffKind::Changed).unwrap_or(&0)
+        );
+    });
+    println!("\tRemoved : {}\n\tAdded : {}\n\tChanged : {}",
+            firmware_diff.count(DiffKind::OnlyInA),
+            firmware_diff.count(DiffKind::OnlyInB),
+            firmware_diff.count(DiffKind::Changed)
+    );
+    println!("\tGlobal duration : {}",
+          HumanDuration(start_global.elapsed())
+    );
+    println!("{}\nCheck at :\n\t{1}{2}reported_firmware_diff.csv\n\t{1}{2}firmware_diff.json\nto review the changes of the update before approving the new reference.",
+            style("[WORK DONE]").bold().magenta(),
+            path_to_string(&report_dir),
+            std::path::MAIN_SEPARATOR
+    );
+    completed_exit_code(firmware_diff.count(DiffKind::OnlyInA) + firmware_diff.count(DiffKind::OnlyInB) + firmware_diff.count(DiffKind::Changed),
+            0
+    )
+}
+
+fn run_rules(yara: PathBuf,
+        save: Option<PathBuf>) -> ExitCode
+{
+    if !yara.is_dir() {
+        eprintln!("{} YARA folder {} is not a directory",
+                style("[ERROR]").bold().dim().red(),
+                yara.display()
+        );
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    println!("{} Finding & compiling YARA rules.\n\tPlease wait...",
+            style("[1/1]").bold().dim().green()
+    );
+    let mut rules = match yara_customizer::yara_rules_finder(&yara)
+            .and_then(yara_customizer::yara_rules_ingester)
+    {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    if let Some(save) = save {
+        match rules.save(path_to_string(&save).as_str()) {
+            Ok(_) => println!("\tCompiled rules saved => {}", save.display()),
+            Err(err) => {
+                eprintln!("{} Saving compiled rules => {}",
+                        style("[ERROR]").bold().dim().red(),
+                        err
+                );
+                return ExitCode::from(EXIT_FAILURE);
+            },
+        };
+    }
+    ExitCode::from(EXIT_SUCCESS)
+}
+
+/// Analyze the devices of 'analyze' against 'reference', or against
+/// each other with '--outliers', reporting the values held by few devices.
+fn run_analysis(args: AnalyzeArgs) -> ExitCode {
+    let AnalyzeArgs { reference, outliers, analyze, yara, output, threads, delimiter, parsers, normalize, severity, suppress, rare, quiet } = args;
+    // the fleet baseline ignores the reference path, set to the analysis directory
+    let ref_dir = match reference {
+        Some(x) if !outliers => path_to_string(&x),
+        _ => path_to_string(&analyze),
+    };
+    let analysis_dir = path_to_string(&analyze);
+    let yara_rules = yara.as_deref().map(path_to_string);
+    match yara_rules {
+        Some(_) => println!("{} Finding & compiling YARA rules.\n\tPlease wait...",
+                style("[4/6]").bold().dim().green()
+        ),
+        None => println!("{} Loading default Yara Rules.\n\t Please wait...",
+                style("[4/6]").bold().dim().yellow()
+        ),
+    };
+    let start_global = Instant::now();
+    let mut builder = AndroidParserBuilder::new(ref_dir, analysis_dir)
+            .threads(threads.unwrap_or(num_cpus::get()))
+            .csv_delimiter(delimiter)
+            .rare_threshold(rare)
+            .observer(ProgressObserver::new(quiet));
+    if let Some(yara_path) = yara_rules {
+        builder = builder.yara_rules(yara_path);
+    }
+    if let Some(output_path) = output {
+        builder = builder.output_dir(path_to_string(&output_path));
+    }
+    if let Some(parsers_path) = parsers {
+        builder = builder.parsers_file(parsers_path);
+    }
+    match load_normalizers(normalize) {
+        Some(x) => builder = builder.normalizers(x),
+        None => return ExitCode::from(EXIT_FAILURE),
+    };
+    if let Some(severity_path) = severity {
+        match SeverityModel::from_file(&severity_path) {
+            Ok(x) => builder = builder.severity_model(x),
+            Err(err) => {
+                eprintln!("{} Severity file {} => {}",
+                        style("[ERROR]").bold().dim().red(),
+                        severity_path.display(),
+                        err
+                );
+                return ExitCode::from(EXIT_FAILURE);
+            },
+        };
+    }
+    if let Some(suppress_path) = suppress {
+        match Suppressions::from_file(&suppress_path) {
+            Ok(x) => builder = builder.suppressions(x),
+            Err(err) => {
+                eprintln!("{} Suppression file {} => {}",
+                        style("[ERROR]").bold().dim().red(),
+                        suppress_path.display(),
+                        err
+                );
+                return ExitCode::from(EXIT_FAILURE);
+            },
+        };
+    }
+    let android_parser = match builder.build() {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    println!("\tYARA ingesting duration : {}",
+            HumanDuration(start_global.elapsed())
+    );
+    let mut start_step = Instant::now();
+    let mut ref_errors: usize = 0;
+    match android_parser.reference_metadata() {
+        _ if outliers => {
+            println!("{} Referencing every device as the fleet baseline.\n\tPlease wait...",
+                    style("[5/6]").bold().dim().green()
+            );
+            match android_parser.go_fleet() {
+                Ok(summary) => {
+                    print_summary(&summary);
+                    ref_errors = summary.errors;
+                },
+                Err(err) => {
+                    eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+                    return ExitCode::from(EXIT_FAILURE);
+                },
+            };
+            println!("\tFleet baseline duration : {}",
+                    HumanDuration(start_step.elapsed())
+            );
+        },
+        _ if android_parser.is_reference_library() => println!("{} Reference library, each device is matched by build fingerprint or product model.",
+                style("[5/6]").bold().dim().green()
+        ),
+        Some(metadata) if android_parser.is_reference_loaded() => println!("{} Reference loaded from file.\n\tBuilt from : {} ({} dump(s))\n\tBuild fingerprint : {}\n\tCreated at : {} (v{})",
+                style("[5/6]").bold().dim().green(),
+                metadata.source_path,
+                metadata.baseline_devices.max(1),
+                metadata.build_fingerprint,
+                metadata.created_at,
+                metadata.tool_version
+        ),
+        _ => {
+            println!("{} Creating reference into SQLite DB.\n\tPlease wait...",
+                    style("[5/6]").bold().dim().green()
+            );
+            match android_parser.go_ref() {
+                Ok(summary) => {
+                    print_summary(&summary);
+                    ref_errors = summary.errors;
+                },
+                Err(err) => {
+                    eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+                    return ExitCode::from(EXIT_FAILURE);
+                },
+            };
+            println!("\tCreating reference duration : {}",
+                    HumanDuration(start_step.elapsed())
+            );
+        },
+    };
+    start_step = Instant::now();
+    println!("{} Working on the Analyse.\n\tPlease wait...",
+            style("[6/6]").bold().dim().green()
+    );
+    let summary = match android_parser.go_parse() {
+        Ok(summary) => {
+            print_summary(&summary);
+            summary
+        },
+        Err(err) => {
+            eprintln!("{} {}", style("[ERROR]").bold().dim().red(), err);
+            return ExitCode::from(EXIT_FAILURE);
+        },
+    };
+    print_fleet(&android_parser.device_summaries());
+    print_outliers(&android_parser.outliers());
+    println!("\tAnalysis duration : {}",
+            HumanDuration(start_step.elapsed())
+    );
+    println!("\tGlobal duration : {}",
+          HumanDuration(start_global.elapsed())
+    );
+    println!("{}\nCheck into each device directory of {1} to find reports.\nAlso check at :\n\t{1}{2}reported_yara_matches.csv\n\t{1}{2}reported_binaries.csv\nto find yara matches,\n\t{1}{2}reported_devices.csv\nfor the reference used by each device,\n\t{1}{2}reported_fleet.csv\nfor the devices ranked by deviations,\n\t{1}{2}reported_errors.csv\nfor the files which could not be analyzed,\n\t{1}{2}reported_suppressed.csv\nfor the findings suppressed as known noise, and\n\t{1}{2}reported_outliers.csv\nfor the values held by few devices.",
+            style("[WORK DONE]").bold().magenta(),
+            path_to_string(android_parser.report_dir()),
+            std::path::MAIN_SEPARATOR
+    );
+    completed_exit_code(summary.findings, ref_errors + summary.errors)
+}
+
+fn run_interactive() -> ExitCode {
     let tip_message: rfd::MessageDialog = rfd::MessageDialog::new()
             .set_title("Information")
             .set_description("Optionnal : Choose a YARA rules file.")
@@ -22,9 +756,9 @@ fn main() {
         Some(f) => {
             println!("{} YARA folder => {}",
                     style("[1/6]").bold().dim().green(),
-                    f.to_str().unwrap()
+                    f.display()
             );
-            Some(String::from(f.to_str().unwrap()))
+            Some(f)
         },
         _ => {
             println!("{} No given Yara rules, will continue with known Yara rules.",
@@ -34,9 +768,7 @@ fn main() {
         },
     };
 
-    // To Build a Yara precompiled uncomment below
-    // let _ = yara_rules.save(format!("{}\\yara_precompiled.yara", env::current_dir().unwrap().to_str().unwrap()).as_str());
-    //
+    // To Build a Yara precompiled, use the 'rules --save' subcommand.
 
     let tip_message: rfd::MessageDialog = rfd::MessageDialog::new()
             .set_title("Information")
@@ -49,11 +781,16 @@ fn main() {
         Some(d) => {
             println!("{} Reference folder => {}",
                     style("[2/6]").bold().dim().green(),
-                    d.to_str().unwrap()
+                    d.display()
             );
-            String::from(d.to_str().unwrap())
+            d
+        },
+        None => {
+            eprintln!("{} No reference directory selected.",
+                    style("[ABORT]").bold().dim().red()
+            );
+            return ExitCode::from(EXIT_FAILURE);
         },
-        None => panic!("[ABORT] No reference directory selected."),
     };
     let tip_message: rfd::MessageDialog = rfd::MessageDialog::new()
             .set_title("Information")
@@ -66,53 +803,21 @@ fn main() {
         Some(d) => {
             println!("{} Analysis directory => {}",
                 style("[3/6]").bold().dim().green(),
-                d.to_str().unwrap()
+                d.display()
         );
-            String::from(d.to_str().unwrap())
+            d
+        },
+        None => {
+            eprintln!("{} No analysis directory selected.",
+                    style("[ABORT]").bold().dim().red()
+            );
+            return ExitCode::from(EXIT_FAILURE);
         },
-        None => panic!("[ABORT] No analysis directory selected."),
-    };
-    match yara_rules {
-        Some(_) => println!("{} Finding & compiling YARA rules.\n\tPlease wait...",
-                style("[4/6]").bold().dim().green()
-        ),
-        None => println!("{} Loading default Yara Rules.\n\t Please wait...",
-                style("[4/6]").bold().dim().yellow()
-        ),
     };
-    let start_global = Instant::now();
-    if let Ok(android_parser) = AndroidParser::new(ref_dir,
-            analysis_dir,
-            yara_rules
-    ){
-        println!("\tYARA ingesting duration : {}", 
-                HumanDuration(start_global.elapsed())
-        );
-        let mut start_step = Instant::now();
-        println!("{} Creating reference into SQLite DB.\n\tPlease wait...",
-                style("[5/6]").bold().dim().green()
-        );
-        android_parser.go_ref();
-        println!("\tCreating reference duration : {}",
-                HumanDuration(start_step.elapsed())
-        );
-        start_step = Instant::now();
-        println!("{} Working on the Analyse.\n\tPlease wait...",
-                style("[6/6]").bold().dim().green()
-        );
-        android_parser.go_parse();
-        println!("\tAnalysis duration : {}",
-                HumanDuration(start_step.elapsed())
-        );
-    }
-    println!("\tGlobal duration : {}",
-          HumanDuration(start_global.elapsed())
-    );
-    println!("{}\nCheck into each device directory to find reports.\nAlso check at :\n\t{1}\\reported_yara_matches.csv\n\t{1}\\reported_binaries.csv\nto find yara matches.",
-            style("[WORK DONE]").bold().magenta(),
-            current_dir().unwrap().to_str().unwrap()
-    );
-    // MainWindow::new().unwrap().run().unwrap();
+    run_analysis(AnalyzeArgs {
+        yara: yara_rules,
+        ..AnalyzeArgs::new(ref_dir, analysis_dir)
+    })
 }
 
 // slint::slint! {
@@ -132,4 +837,4 @@ fn main() {
 //     export component MainWindow inherits Window {
 //         MemoryTile {}
 //     }
-// }
\ No newline at end of file
+// }
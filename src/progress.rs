@@ -0,0 +1,187 @@
+//! Progress
+//!
+//! Progress bars of the console, drawn from the events of the runs:
+//! files of the reference, devices and files of the analysis, files of each
+//! device and YARA scan throughput. In quiet mode, only the warnings and
+//! errors are printed.
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use console::style;
+
+use android_sanity_checker::androidparser::{Event, Observer, RunPhase};
+
+/// Bars of the current run.
+#[derive(Default)]
+struct RunBars {
+    /// Files of the run, with its ETA.
+    files: Option<ProgressBar>,
+    /// Devices of the analysis.
+    devices: Option<ProgressBar>,
+    /// Bytes scanned with the YARA rules.
+    yara: Option<ProgressBar>,
+    yara_files: u64,
+    /// Files of each device being analyzed.
+    device_files: HashMap<String, ProgressBar>,
+}
+
+/// Draw the progress of the runs on stderr, or only print their warnings and errors if 'quiet'.
+pub struct ProgressObserver {
+    multi: MultiProgress,
+    quiet: bool,
+    bars: Mutex<RunBars>,
+}
+
+impl ProgressObserver {
+    /// Quiet anyway when stderr is not a terminal (redirected output, CI...).
+    pub fn new(quiet: bool) -> Self {
+        let quiet = quiet || !console::Term::stderr().is_term();
+        let multi = match quiet {
+            true => MultiProgress::with_draw_target(ProgressDrawTarget::hidden()),
+            false => MultiProgress::with_draw_target(ProgressDrawTarget::stderr()),
+        };
+        Self {
+            multi,
+            quiet,
+            bars: Mutex::new(RunBars::default()),
+        }
+    }
+
+    /// Print 'message' above the bars, on stderr.
+    fn println(&self,
+            message: String)
+    {
+        match self.quiet {
+            true => eprintln!("{message}"),
+            false => {
+                let _ = self.multi.println(message);
+            },
+        };
+    }
+
+    fn add_bar(&self,
+            length: Option<u64>,
+            template: &str,
+            prefix: String) -> ProgressBar
+    {
+        let bar = match length {
+            Some(x) => self.multi.add(ProgressBar::new(x)),
+            None => self.multi.add(ProgressBar::no_length()),
+        };
+        if let Ok(bar_style) = ProgressStyle::with_template(template) {
+            bar.set_style(bar_style.progress_chars("=> "));
+        }
+        bar.set_prefix(prefix);
+        bar.enable_steady_tick(Duration::from_millis(200));
+        bar
+    }
+
+    /// One more file done for 'device'.
+    fn file_done(&self,
+            bars: &RunBars,
+            device: &str)
+    {
+        if let Some(bar) = &bars.files {
+            bar.inc(1);
+        }
+        if let Some(bar) = bars.device_files.get(device) {
+            bar.inc(1);
+        }
+    }
+}
+
+impl Observer for ProgressObserver {
+    fn on_event(&self,
+            event: &Event)
+    {
+        match event {
+            Event::DeviceStarted { device, reference, reference_match, .. } if reference.is_empty() => {
+                self.println(format!("{} {} => {}",
+                        style("[WARNING]").bold().dim().yellow(),
+                        reference_match,
+                        device
+                ));
+            },
+            Event::Error { file, message, .. } => {
+                self.println(format!("{} {} => {}",
+                        style("[ERROR]").bold().dim().red(),
+                        file,
+                        message
+                ));
+            },
+            _ => (),
+        };
+        if self.quiet {
+            return;
+        }
+        let mut bars = match self.bars.lock() {
+            Ok(x) => x,
+            Err(_) => return,
+        };
+        match event {
+            Event::RunStarted { phase: RunPhase::Reference, files, .. } => {
+                bars.files = Some(self.add_bar(Some(*files as u64),
+                        "{prefix:>10} [{elapsed_precise}] [{bar:40.green/blue}] {pos}/{len} files",
+                        String::from("reference")
+                ));
+            },
+            Event::RunStarted { phase: RunPhase::Diff, files, .. } => {
+                bars.files = Some(self.add_bar(Some(*files as u64),
+                        "{prefix:>10} [{elapsed_precise}] [{bar:40.green/blue}] {pos}/{len} files",
+                        String::from("diff")
+                ));
+            },
+            Event::RunStarted { phase: RunPhase::Analysis, devices, files } => {
+                bars.devices = Some(self.add_bar(Some(*devices as u64),
+                        "{prefix:>10} [{elapsed_precise}] [{bar:40.magenta/blue}] {pos}/{len} devices",
+                        String::from("analysis")
+                ));
+                bars.files = Some(self.add_bar(Some(*files as u64),
+                        "{prefix:>10} [{eta_precise}] [{bar:40.green/blue}] {pos}/{len} files",
+                        String::from("eta")
+                ));
+                bars.yara = Some(self.add_bar(None,
+                        "{prefix:>10} {spinner} {binary_bytes} scanned ({binary_bytes_per_sec}) {msg}",
+                        String::from("yara")
+                ));
+                bars.yara_files = 0;
+            },
+            Event::DeviceStarted { device, files, .. } => {
+                let bar = self.add_bar(Some(*files as u64),
+                        "{prefix:>10} [{bar:40.cyan/blue}] {pos}/{len} {msg}",
+                        String::from("device")
+                );
+                bar.set_message(device.clone());
+                bars.device_files.insert(device.clone(), bar);
+            },
+            Event::FileParsed { device, .. } | Event::FileSkipped { device, .. } => self.file_done(&bars, device),
+            Event::FileScanned { device, bytes, .. } => {
+                self.file_done(&bars, device);
+                bars.yara_files += 1;
+                if let Some(bar) = &bars.yara {
+                    bar.inc(*bytes);
+                    bar.set_message(format!("{} file(s)", bars.yara_files));
+                }
+            },
+            Event::DeviceFinished { device } => {
+                if let Some(bar) = bars.device_files.remove(device) {
+                    bar.finish_and_clear();
+                    self.multi.remove(&bar);
+                }
+                if let Some(bar) = &bars.devices {
+                    bar.inc(1);
+                }
+            },
+            Event::RunFinished { .. } => {
+                let finished = std::mem::take(&mut *bars);
+                finished.files.iter()
+                        .chain(finished.devices.iter())
+                        .chain(finished.yara.iter())
+                        .chain(finished.device_files.values())
+                        .for_each(|x| x.finish_and_clear());
+                let _ = self.multi.clear();
+            },
+            _ => (),
+        };
+    }
+}